@@ -0,0 +1,4 @@
+ALTER TABLE issue_delievery_queue
+  ADD COLUMN n_retries INT NOT NULL DEFAULT 0,
+  ADD COLUMN execute_after timestamptz NOT NULL DEFAULT now(),
+  ADD COLUMN enqueued_at timestamptz NOT NULL DEFAULT now();
@@ -0,0 +1,9 @@
+CREATE TABLE subscription_confirmation_dead_letters (
+  subscriber_id uuid NOT NULL
+    REFERENCES subscriptions (id),
+  n_attempts INT NOT NULL,
+  last_error TEXT NOT NULL,
+  enqueued_at timestamptz NOT NULL,
+  failed_at timestamptz NOT NULL,
+  PRIMARY KEY(subscriber_id)
+);
@@ -0,0 +1,17 @@
+CREATE TABLE newsletter_deliveries (
+  newsletter_issue_id uuid NOT NULL
+    REFERENCES newsletter_issues (newsletter_issue_id),
+  subscriber_id uuid NOT NULL
+    REFERENCES subscriptions (id),
+  subscriber_email TEXT NOT NULL,
+  status TEXT NOT NULL,
+  provider_message_id TEXT,
+  error_code INT,
+  error_message TEXT,
+  n_attempts INT NOT NULL,
+  first_attempted_at timestamptz NOT NULL,
+  last_attempted_at timestamptz NOT NULL,
+  sent_at timestamptz,
+  PRIMARY KEY(newsletter_issue_id, subscriber_id)
+);
+CREATE INDEX newsletter_deliveries_subscriber_email_idx ON newsletter_deliveries (subscriber_email);
@@ -0,0 +1,8 @@
+CREATE TABLE email_suppressions (
+  email TEXT NOT NULL,
+  reason TEXT NOT NULL,
+  provider_message_id TEXT,
+  description TEXT,
+  suppressed_at timestamptz NOT NULL,
+  PRIMARY KEY(email)
+);
@@ -0,0 +1,5 @@
+ALTER TABLE newsletter_issues ALTER COLUMN published_at DROP NOT NULL;
+ALTER TABLE newsletter_issues ADD COLUMN scheduled_for timestamptz NULL;
+ALTER TABLE newsletter_issues ADD COLUMN timezone TEXT NULL;
+CREATE INDEX newsletter_issues_due_idx ON newsletter_issues (scheduled_for)
+  WHERE published_at IS NULL;
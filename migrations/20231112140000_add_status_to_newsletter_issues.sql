@@ -0,0 +1,18 @@
+ALTER TABLE newsletter_issues ADD COLUMN status TEXT NULL;
+BEGIN;
+  -- backfill status
+  UPDATE newsletter_issues
+    SET status = CASE
+      WHEN published_at IS NULL THEN 'scheduled'
+      WHEN EXISTS (
+        SELECT 1 FROM issue_delievery_queue
+        WHERE issue_delievery_queue.newsletter_issue_id = newsletter_issues.newsletter_issue_id
+      ) THEN 'sending'
+      ELSE 'sent'
+    END
+    WHERE status IS NULL;
+  -- enforce not null
+  ALTER TABLE newsletter_issues ALTER COLUMN status SET NOT NULL;
+COMMIT;
+DROP INDEX newsletter_issues_due_idx;
+CREATE INDEX newsletter_issues_status_idx ON newsletter_issues (status, scheduled_for);
@@ -0,0 +1,31 @@
+CREATE TABLE lists (
+  list_id uuid NOT NULL,
+  PRIMARY KEY (list_id),
+  name TEXT NOT NULL UNIQUE,
+  created_at timestamptz NOT NULL DEFAULT now()
+);
+
+INSERT INTO lists (list_id, name, created_at)
+VALUES ('7f3c2a9e-5b1d-4e8a-9c6f-2d4b8e1a0c35', 'Newsletter', '2023-06-04 18:01:37+00');
+
+CREATE TABLE list_memberships (
+  list_id uuid NOT NULL
+    REFERENCES lists (list_id),
+  subscriber_id uuid NOT NULL
+    REFERENCES subscriptions (id),
+  PRIMARY KEY (list_id, subscriber_id),
+  status TEXT NOT NULL,
+  subscribed_at timestamptz NOT NULL DEFAULT now()
+);
+
+INSERT INTO list_memberships (list_id, subscriber_id, status, subscribed_at)
+SELECT '7f3c2a9e-5b1d-4e8a-9c6f-2d4b8e1a0c35', id, status, subscribed_at
+FROM subscriptions;
+
+ALTER TABLE subscriptions_tokens ADD COLUMN list_id uuid NULL REFERENCES lists (list_id);
+UPDATE subscriptions_tokens SET list_id = '7f3c2a9e-5b1d-4e8a-9c6f-2d4b8e1a0c35';
+ALTER TABLE subscriptions_tokens ALTER COLUMN list_id SET NOT NULL;
+
+ALTER TABLE newsletter_issues ADD COLUMN list_id uuid NULL REFERENCES lists (list_id);
+UPDATE newsletter_issues SET list_id = '7f3c2a9e-5b1d-4e8a-9c6f-2d4b8e1a0c35';
+ALTER TABLE newsletter_issues ALTER COLUMN list_id SET NOT NULL;
@@ -0,0 +1 @@
+ALTER TABLE subscriptions ADD COLUMN confirmation_requested_at timestamptz NULL;
@@ -0,0 +1,16 @@
+ALTER TABLE subscriptions_tokens
+  ADD COLUMN created_at timestamptz NOT NULL DEFAULT now(),
+  ADD COLUMN consumed_at timestamptz NULL;
+
+UPDATE subscriptions_tokens
+SET consumed_at = now()
+FROM list_memberships
+WHERE
+  list_memberships.subscriber_id = subscriptions_tokens.subscriber_id AND
+  list_memberships.list_id = subscriptions_tokens.list_id AND
+  list_memberships.status = 'confirmed';
+
+UPDATE subscriptions_tokens
+SET subscriptions_token = encode(sha256(convert_to(subscriptions_token, 'UTF8')), 'hex');
+
+ALTER TABLE subscriptions_tokens RENAME COLUMN subscriptions_token TO token_hash;
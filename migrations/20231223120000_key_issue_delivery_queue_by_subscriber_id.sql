@@ -0,0 +1,38 @@
+BEGIN;
+  ALTER TABLE issue_delievery_queue
+    ADD COLUMN subscriber_id uuid NULL REFERENCES subscriptions (id);
+  UPDATE issue_delievery_queue
+    SET subscriber_id = subscriptions.id
+    FROM subscriptions
+    WHERE subscriptions.email = issue_delievery_queue.subscriber_email;
+  -- tasks whose address no longer matches a subscriber can never be delivered
+  DELETE FROM issue_delievery_queue WHERE subscriber_id IS NULL;
+  ALTER TABLE issue_delievery_queue
+    DROP CONSTRAINT issue_delievery_queue_pkey,
+    DROP COLUMN subscriber_email,
+    ALTER COLUMN subscriber_id SET NOT NULL,
+    ADD PRIMARY KEY (newsletter_issue_id, subscriber_id);
+
+  ALTER TABLE issue_delivery_dead_letters
+    ADD COLUMN subscriber_id uuid NULL REFERENCES subscriptions (id);
+  UPDATE issue_delivery_dead_letters
+    SET subscriber_id = subscriptions.id
+    FROM subscriptions
+    WHERE subscriptions.email = issue_delivery_dead_letters.subscriber_email;
+  DELETE FROM issue_delivery_dead_letters WHERE subscriber_id IS NULL;
+  ALTER TABLE issue_delivery_dead_letters
+    DROP CONSTRAINT issue_delivery_dead_letters_pkey,
+    DROP COLUMN subscriber_email,
+    ALTER COLUMN subscriber_id SET NOT NULL,
+    ADD PRIMARY KEY (newsletter_issue_id, subscriber_id);
+
+  -- issues whose last pending task was dropped above are done
+  UPDATE newsletter_issues
+    SET status = 'sent'
+    WHERE
+      status = 'sending' AND
+      NOT EXISTS (
+        SELECT 1 FROM issue_delievery_queue
+        WHERE issue_delievery_queue.newsletter_issue_id = newsletter_issues.newsletter_issue_id
+      );
+COMMIT;
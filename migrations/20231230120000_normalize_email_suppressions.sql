@@ -0,0 +1,11 @@
+BEGIN;
+  -- Keep only the most recent suppression per address before folding case,
+  -- otherwise lowercasing would collide on the primary key.
+  DELETE FROM email_suppressions a
+  USING email_suppressions b
+  WHERE
+    lower(a.email) = lower(b.email) AND
+    a.email <> b.email AND
+    (a.suppressed_at, a.email) < (b.suppressed_at, b.email);
+  UPDATE email_suppressions SET email = lower(email);
+COMMIT;
@@ -0,0 +1,11 @@
+BEGIN;
+  -- An email change is held here until the new address is confirmed, so the
+  -- subscriber keeps receiving issues at the old address in the meantime.
+  ALTER TABLE subscriptions ADD COLUMN pending_email TEXT NULL;
+  -- A token confirms either a list membership or an email change.
+  ALTER TABLE subscriptions_tokens
+    ADD COLUMN new_email TEXT NULL,
+    ALTER COLUMN list_id DROP NOT NULL,
+    ADD CONSTRAINT subscriptions_tokens_list_or_new_email
+      CHECK ((list_id IS NULL) <> (new_email IS NULL));
+COMMIT;
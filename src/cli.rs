@@ -0,0 +1,102 @@
+pub const USAGE: &str = "\
+Usage: zero2prod [all | serve | worker newsletters | worker confirmations]
+
+  all                   Run the API and every background job (default)
+  serve                 Run the API only
+  worker newsletters    Deliver newsletter issues and publish scheduled issues when they fall due
+  worker confirmations  Deliver confirmation emails and purge stale pending subscriptions";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Role {
+    /// Runs the API and every background job in a single process.
+    All,
+    /// Runs the API only.
+    Serve,
+    /// Runs the newsletter delivery worker and the newsletter scheduler, which
+    /// publishes scheduled issues when they fall due. Scheduled issues are only
+    /// published while at least one process runs this role or `all`.
+    NewsletterWorker,
+    /// Runs the confirmation delivery worker and the cleanup job that purges
+    /// pending subscriptions whose confirmation tokens have expired. Stale
+    /// pending subscriptions are only purged while at least one process runs
+    /// this role or `all`.
+    ConfirmationWorker,
+}
+
+impl Role {
+    pub fn runs_api(&self) -> bool {
+        matches!(self, Role::All | Role::Serve)
+    }
+
+    pub fn runs_newsletter_worker(&self) -> bool {
+        matches!(self, Role::All | Role::NewsletterWorker)
+    }
+
+    pub fn runs_confirmation_worker(&self) -> bool {
+        matches!(self, Role::All | Role::ConfirmationWorker)
+    }
+}
+
+impl TryFrom<Vec<String>> for Role {
+    type Error = String;
+
+    fn try_from(args: Vec<String>) -> Result<Self, Self::Error> {
+        let args: Vec<&str> = args.iter().map(String::as_str).collect();
+        match args.as_slice() {
+            [] | ["all"] => Ok(Role::All),
+            ["serve"] => Ok(Role::Serve),
+            ["worker", "newsletters"] => Ok(Role::NewsletterWorker),
+            ["worker", "confirmations"] => Ok(Role::ConfirmationWorker),
+            _ => Err(format!(
+                "{} is not a supported command.\n{}",
+                args.join(" "),
+                USAGE
+            )),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Role;
+    use claims::{assert_err, assert_ok_eq};
+
+    fn parse(args: &[&str]) -> Result<Role, String> {
+        Role::try_from(args.iter().map(|a| a.to_string()).collect::<Vec<_>>())
+    }
+
+    #[test]
+    fn no_arguments_runs_everything() {
+        assert_ok_eq!(parse(&[]), Role::All);
+        assert_ok_eq!(parse(&["all"]), Role::All);
+    }
+
+    #[test]
+    fn each_role_can_be_selected() {
+        assert_ok_eq!(parse(&["serve"]), Role::Serve);
+        assert_ok_eq!(parse(&["worker", "newsletters"]), Role::NewsletterWorker);
+        assert_ok_eq!(
+            parse(&["worker", "confirmations"]),
+            Role::ConfirmationWorker
+        );
+    }
+
+    #[test]
+    fn unknown_commands_are_rejected() {
+        assert_err!(parse(&["worker"]));
+        assert_err!(parse(&["worker", "everything"]));
+        assert_err!(parse(&["serve", "now"]));
+    }
+
+    #[test]
+    fn only_the_all_role_runs_more_than_one_component() {
+        let serve = Role::Serve;
+        assert!(serve.runs_api());
+        assert!(!serve.runs_newsletter_worker());
+        assert!(!serve.runs_confirmation_worker());
+        let worker = Role::NewsletterWorker;
+        assert!(!worker.runs_api());
+        assert!(worker.runs_newsletter_worker());
+        assert!(!worker.runs_confirmation_worker());
+    }
+}
@@ -1,9 +1,13 @@
 use crate::domain::SubscriberEmail;
-use crate::email_client::EmailClient;
+use crate::email_client::{EmailClient, FileTransport, PostmarkClient, SmtpClient};
+use crate::retry_policy::RetryPolicy;
+use crate::task_queue::WorkerOptions;
 use secrecy::{ExposeSecret, Secret};
 use serde_aux::field_attributes::deserialize_number_from_string;
 use sqlx::postgres::{PgConnectOptions, PgSslMode};
 use sqlx::ConnectOptions;
+use std::num::NonZeroUsize;
+use std::sync::Arc;
 
 #[derive(serde::Deserialize, Clone)]
 pub struct Settings {
@@ -11,6 +15,73 @@ pub struct Settings {
     pub database: DatabaseSettings,
     pub email_client: EmailClientSettings,
     pub redis_uri: Secret<String>,
+    pub subscriptions: SubscriptionSettings,
+    pub workers: WorkersSettings,
+}
+
+#[derive(serde::Deserialize, Clone)]
+pub struct SubscriptionSettings {
+    #[serde(deserialize_with = "deserialize_number_from_string")]
+    pub confirmation_resend_interval_seconds: u64,
+    #[serde(deserialize_with = "deserialize_number_from_string")]
+    pub confirmation_token_expiry_hours: u64,
+}
+
+impl SubscriptionSettings {
+    pub fn confirmation_resend_interval(&self) -> std::time::Duration {
+        std::time::Duration::from_secs(self.confirmation_resend_interval_seconds)
+    }
+
+    pub fn confirmation_token_expiry(&self) -> std::time::Duration {
+        std::time::Duration::from_secs(self.confirmation_token_expiry_hours * 60 * 60)
+    }
+}
+
+#[derive(serde::Deserialize, Clone)]
+pub struct WorkersSettings {
+    pub newsletter_delivery: WorkerSettings,
+    pub subscription_confirmation: WorkerSettings,
+    pub newsletter_scheduler: SchedulerSettings,
+    pub pending_subscription_cleanup: SchedulerSettings,
+}
+
+#[derive(serde::Deserialize, Clone)]
+pub struct SchedulerSettings {
+    #[serde(deserialize_with = "deserialize_number_from_string")]
+    pub poll_interval_milliseconds: u64,
+}
+
+impl SchedulerSettings {
+    pub fn poll_interval(&self) -> std::time::Duration {
+        std::time::Duration::from_millis(self.poll_interval_milliseconds)
+    }
+}
+
+#[derive(serde::Deserialize, Clone)]
+pub struct WorkerSettings {
+    #[serde(deserialize_with = "deserialize_number_from_string")]
+    pub concurrency: NonZeroUsize,
+    #[serde(deserialize_with = "deserialize_number_from_string")]
+    pub batch_size: NonZeroUsize,
+    #[serde(deserialize_with = "deserialize_number_from_string")]
+    pub empty_queue_sleep_milliseconds: u64,
+    #[serde(deserialize_with = "deserialize_number_from_string")]
+    pub error_sleep_milliseconds: u64,
+    pub retry: RetryPolicy,
+}
+
+impl WorkerSettings {
+    pub fn options(self) -> WorkerOptions {
+        WorkerOptions {
+            concurrency: self.concurrency.get(),
+            batch_size: self.batch_size.get(),
+            empty_queue_sleep: std::time::Duration::from_millis(
+                self.empty_queue_sleep_milliseconds,
+            ),
+            error_sleep: std::time::Duration::from_millis(self.error_sleep_milliseconds),
+            retry_policy: self.retry,
+        }
+    }
 }
 
 #[derive(serde::Deserialize, Clone)]
@@ -20,6 +91,14 @@ pub struct ApplicationSettings {
     pub port: u16,
     pub base_url: String,
     pub hmac_secret: Secret<String>,
+    #[serde(deserialize_with = "deserialize_number_from_string")]
+    pub drain_timeout_seconds: u64,
+}
+
+impl ApplicationSettings {
+    pub fn drain_timeout(&self) -> std::time::Duration {
+        std::time::Duration::from_secs(self.drain_timeout_seconds)
+    }
 }
 
 #[derive(serde::Deserialize, Clone)]
@@ -57,10 +136,35 @@ impl DatabaseSettings {
 
 #[derive(serde::Deserialize, Clone)]
 pub struct EmailClientSettings {
+    #[serde(default)]
+    pub backend: EmailBackend,
     pub base_url: String,
     pub sender_email: String,
     pub authorization_token: Secret<String>,
     pub timeout_milliseconds: u64,
+    pub smtp: Option<SmtpSettings>,
+    pub outbox_directory: Option<String>,
+    pub webhook_username: String,
+    pub webhook_password: Secret<String>,
+}
+
+#[derive(serde::Deserialize, Clone, Debug, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum EmailBackend {
+    #[default]
+    Postmark,
+    Smtp,
+    File,
+}
+
+#[derive(serde::Deserialize, Clone)]
+pub struct SmtpSettings {
+    pub host: String,
+    #[serde(deserialize_with = "deserialize_number_from_string")]
+    pub port: u16,
+    pub require_tls: bool,
+    pub username: Option<String>,
+    pub password: Option<Secret<String>>,
 }
 
 impl EmailClientSettings {
@@ -75,12 +179,40 @@ impl EmailClientSettings {
     pub fn client(self) -> EmailClient {
         let sender_email = self.sender().expect("Invalid sender email address.");
         let timeout = self.timeout();
-        EmailClient::new(
-            self.base_url,
-            sender_email,
-            self.authorization_token,
-            timeout,
-        )
+        match self.backend {
+            EmailBackend::Postmark => Arc::new(PostmarkClient::new(
+                self.base_url,
+                sender_email,
+                self.authorization_token,
+                timeout,
+            )),
+            EmailBackend::Smtp => {
+                let smtp = self
+                    .smtp
+                    .expect("Missing smtp settings for the smtp backend.");
+                let credentials = smtp.username.zip(smtp.password);
+                Arc::new(
+                    SmtpClient::new(
+                        &smtp.host,
+                        smtp.port,
+                        smtp.require_tls,
+                        credentials,
+                        sender_email,
+                        timeout,
+                    )
+                    .expect("Invalid smtp settings."),
+                )
+            }
+            EmailBackend::File => {
+                let directory = self
+                    .outbox_directory
+                    .expect("Missing outbox_directory for the file backend.");
+                Arc::new(
+                    FileTransport::new(directory.into(), sender_email)
+                        .expect("Failed to create the outbox directory."),
+                )
+            }
+        }
     }
 }
 
@@ -133,3 +265,39 @@ impl TryFrom<String> for Environment {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::WorkerSettings;
+    use claims::{assert_err, assert_ok};
+
+    fn parse_worker_settings(concurrency: &str, batch_size: &str) -> Result<(), serde_json::Error> {
+        serde_json::from_value::<WorkerSettings>(serde_json::json!({
+            "concurrency": concurrency,
+            "batch_size": batch_size,
+            "empty_queue_sleep_milliseconds": "1000",
+            "error_sleep_milliseconds": "1000",
+            "retry": {
+                "max_attempts": 8,
+                "base_delay_milliseconds": 1000,
+                "max_delay_milliseconds": 60000
+            }
+        }))
+        .map(|_| ())
+    }
+
+    #[test]
+    fn positive_concurrency_and_batch_size_are_accepted() {
+        assert_ok!(parse_worker_settings("2", "10"));
+    }
+
+    #[test]
+    fn zero_concurrency_is_rejected() {
+        assert_err!(parse_worker_settings("0", "10"));
+    }
+
+    #[test]
+    fn a_zero_batch_size_is_rejected() {
+        assert_err!(parse_worker_settings("2", "0"));
+    }
+}
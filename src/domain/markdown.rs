@@ -0,0 +1,134 @@
+use pulldown_cmark::{escape::escape_html, html, Event, Parser, Tag};
+
+pub fn markdown_to_html(markdown: &str) -> String {
+    let events = Parser::new(markdown).map(|event| match event {
+        Event::Start(Tag::Link(_, url, title)) if url.contains("{{") => {
+            let mut anchor = String::from("<a href=\"");
+            escape_html(&mut anchor, &url).unwrap();
+            if !title.is_empty() {
+                anchor.push_str("\" title=\"");
+                escape_html(&mut anchor, &title).unwrap();
+            }
+            anchor.push_str("\">");
+            Event::Html(anchor.into())
+        }
+        Event::End(Tag::Link(_, url, _)) if url.contains("{{") => Event::Html("</a>".into()),
+        event => event,
+    });
+    let mut html_output = String::new();
+    html::push_html(&mut html_output, events);
+    html_output
+}
+
+pub fn markdown_to_text(markdown: &str) -> String {
+    let mut text = String::new();
+    let mut lists: Vec<Option<u64>> = vec![];
+    let mut links: Vec<(String, usize)> = vec![];
+    for event in Parser::new(markdown) {
+        match event {
+            Event::Text(t) | Event::Code(t) | Event::Html(t) => text.push_str(&t),
+            Event::SoftBreak | Event::HardBreak => text.push('\n'),
+            Event::Rule => text.push_str("---\n\n"),
+            Event::Start(Tag::Item) => {
+                let depth = lists.len().saturating_sub(1);
+                text.push_str(&"  ".repeat(depth));
+                match lists.last_mut() {
+                    Some(Some(n)) => {
+                        text.push_str(&format!("{}. ", n));
+                        *n += 1;
+                    }
+                    _ => text.push_str("- "),
+                }
+            }
+            Event::Start(Tag::List(start)) => {
+                if !lists.is_empty() && !text.ends_with('\n') {
+                    text.push('\n');
+                }
+                lists.push(start);
+            }
+            Event::Start(Tag::Link(_, url, _)) | Event::Start(Tag::Image(_, url, _)) => {
+                links.push((url.to_string(), text.len()));
+            }
+            Event::End(Tag::Link(..)) | Event::End(Tag::Image(..)) => {
+                if let Some((url, start)) = links.pop() {
+                    if text[start..] != url {
+                        text.push_str(&format!(" ({})", url));
+                    }
+                }
+            }
+            Event::End(Tag::Item) if !text.ends_with('\n') => text.push('\n'),
+            Event::End(Tag::List(_)) => {
+                lists.pop();
+                if lists.is_empty() {
+                    text.push('\n');
+                }
+            }
+            Event::End(Tag::Paragraph) if !lists.is_empty() => text.push('\n'),
+            Event::End(Tag::Paragraph | Tag::Heading(..) | Tag::CodeBlock(_) | Tag::BlockQuote) => {
+                while !text.ends_with("\n\n") {
+                    text.push('\n');
+                }
+            }
+            _ => {}
+        }
+    }
+    text.trim_end().to_owned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{markdown_to_html, markdown_to_text};
+
+    #[test]
+    fn markdown_is_rendered_to_html() {
+        let html =
+            markdown_to_html("# Hello\n\nSome *emphasis* and a [link](https://example.com).");
+        assert_eq!(
+            html,
+            "<h1>Hello</h1>\n<p>Some <em>emphasis</em> and a <a href=\"https://example.com\">link</a>.</p>\n"
+        );
+    }
+
+    #[test]
+    fn merge_tags_survive_in_link_destinations() {
+        let html = markdown_to_html("Hi {{name}}, [unsubscribe]({{unsubscribe_url}} \"Bye\").");
+        assert_eq!(
+            html,
+            "<p>Hi {{name}}, <a href=\"{{unsubscribe_url}}\" title=\"Bye\">unsubscribe</a>.</p>\n"
+        );
+    }
+
+    #[test]
+    fn paragraphs_and_headings_are_separated_by_blank_lines() {
+        let text = markdown_to_text("# Hello\n\nFirst paragraph\ncontinues.\n\nSecond paragraph.");
+        assert_eq!(
+            text,
+            "Hello\n\nFirst paragraph\ncontinues.\n\nSecond paragraph."
+        );
+    }
+
+    #[test]
+    fn links_keep_their_url() {
+        let text =
+            markdown_to_text("Read [the docs](https://example.com/docs) or <https://example.com>.");
+        assert_eq!(
+            text,
+            "Read the docs (https://example.com/docs) or https://example.com."
+        );
+    }
+
+    #[test]
+    fn lists_are_rendered_with_markers() {
+        let text = markdown_to_text("Intro\n\n- one\n- two\n  1. nested\n  2. again\n\nAfter");
+        assert_eq!(
+            text,
+            "Intro\n\n- one\n- two\n  1. nested\n  2. again\n\nAfter"
+        );
+    }
+
+    #[test]
+    fn formatting_is_stripped() {
+        let text = markdown_to_text("Some **bold**, *italic* and `code`.");
+        assert_eq!(text, "Some bold, italic and code.");
+    }
+}
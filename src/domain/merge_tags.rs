@@ -0,0 +1,146 @@
+use handlebars::template::{HelperTemplate, Template, TemplateElement};
+use handlebars::{no_escape, Handlebars, RenderError};
+use serde::Serialize;
+
+const ALLOWED_TAGS: [&str; 3] = ["name", "email", "unsubscribe_url"];
+
+pub const EXAMPLE_NAME: &str = "Ursula Le Guin";
+pub const EXAMPLE_EMAIL: &str = "ursula_le_guin@example.com";
+
+#[derive(Debug, Serialize)]
+pub struct MergeTags<'a> {
+    pub name: &'a str,
+    pub email: &'a str,
+    pub unsubscribe_url: &'a str,
+}
+
+impl<'a> MergeTags<'a> {
+    pub fn example(unsubscribe_url: &'a str) -> Self {
+        Self {
+            name: EXAMPLE_NAME,
+            email: EXAMPLE_EMAIL,
+            unsubscribe_url,
+        }
+    }
+}
+
+pub fn render_html(template: &str, merge_tags: &MergeTags) -> Result<String, RenderError> {
+    render(template, merge_tags, true)
+}
+
+pub fn render_text(template: &str, merge_tags: &MergeTags) -> Result<String, RenderError> {
+    render(template, merge_tags, false)
+}
+
+fn render(
+    template: &str,
+    merge_tags: &MergeTags,
+    escape_html: bool,
+) -> Result<String, RenderError> {
+    let template = Template::compile(template)?;
+    check_allowed_tags(&template)?;
+    let mut handlebars = Handlebars::new();
+    handlebars.set_strict_mode(true);
+    if !escape_html {
+        handlebars.register_escape_fn(no_escape);
+    }
+    handlebars.register_template("merge_tags", template);
+    handlebars.render("merge_tags", merge_tags)
+}
+
+fn check_allowed_tags(template: &Template) -> Result<(), RenderError> {
+    for element in &template.elements {
+        match element {
+            TemplateElement::RawString(_) | TemplateElement::Comment(_) => {}
+            TemplateElement::Expression(tag) if is_allowed_tag(tag) => {}
+            _ => {
+                return Err(RenderError::new(
+                    "Only {{name}}, {{email}} and {{unsubscribe_url}} merge tags are allowed",
+                ))
+            }
+        }
+    }
+    Ok(())
+}
+
+fn is_allowed_tag(tag: &HelperTemplate) -> bool {
+    tag.params.is_empty()
+        && tag.hash.is_empty()
+        && tag.block_param.is_none()
+        && tag.template.is_none()
+        && tag.inverse.is_none()
+        && !tag.block
+        && matches!(tag.name.as_name(), Some(name) if ALLOWED_TAGS.contains(&name))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{render_html, render_text, MergeTags};
+    use claims::assert_err;
+
+    fn merge_tags() -> MergeTags<'static> {
+        MergeTags {
+            name: "Tom & Jerry",
+            email: "tom@example.com",
+            unsubscribe_url: "https://example.com/unsubscribe?a=1&b=2",
+        }
+    }
+
+    #[test]
+    fn html_is_escaped() {
+        let html = render_html(
+            "<p>Hi {{name}}</p><a href=\"{{unsubscribe_url}}\">{{email}}</a>",
+            &merge_tags(),
+        )
+        .unwrap();
+        assert_eq!(
+            html,
+            "<p>Hi Tom &amp; Jerry</p><a href=\"https://example.com/unsubscribe?a&#x3D;1&amp;b&#x3D;2\">tom@example.com</a>"
+        );
+    }
+
+    #[test]
+    fn text_is_not_escaped() {
+        let text = render_text("Hi {{name}}, visit {{unsubscribe_url}}", &merge_tags()).unwrap();
+        assert_eq!(
+            text,
+            "Hi Tom & Jerry, visit https://example.com/unsubscribe?a=1&b=2"
+        );
+    }
+
+    #[test]
+    fn unknown_variables_are_rejected() {
+        assert_err!(render_text("Hi {{first_name}}", &merge_tags()));
+    }
+
+    #[test]
+    fn malformed_tags_are_rejected() {
+        assert_err!(render_text("Hi {{name", &merge_tags()));
+    }
+
+    #[test]
+    fn unescaped_tags_are_rejected() {
+        assert_err!(render_html("<p>Hi {{{name}}}</p>", &merge_tags()));
+        assert_err!(render_html("<p>Hi {{&name}}</p>", &merge_tags()));
+    }
+
+    #[test]
+    fn helpers_are_rejected() {
+        for template in [
+            "{{#each this}}{{this}}{{/each}}",
+            "{{#if name}}Hi{{/if}}",
+            "{{log name}}",
+            "{{lookup this \"name\"}}",
+            "{{> footer}}",
+            "{{this}}",
+        ] {
+            assert_err!(render_html(template, &merge_tags()));
+        }
+    }
+
+    #[test]
+    fn comments_are_allowed() {
+        let html = render_html("{{! greeting }}Hi {{name}}", &merge_tags()).unwrap();
+        assert_eq!(html, "Hi Tom &amp; Jerry");
+    }
+}
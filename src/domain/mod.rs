@@ -1,10 +1,20 @@
+pub mod markdown;
+pub mod merge_tags;
 pub mod new_subscriber;
 pub mod newsletter_issue;
+pub mod preferences_token;
+pub mod publication_schedule;
+pub mod signed_token;
 pub mod subscriber_email;
 pub mod subscriber_name;
 pub mod tasks;
+pub mod unsubscribe_token;
 
+pub use merge_tags::MergeTags;
 pub use new_subscriber::NewSubscriber;
 pub use newsletter_issue::NewsletterIssue;
+pub use preferences_token::{preferences_link, PreferencesToken};
+pub use publication_schedule::{timezone_names, PublicationSchedule};
 pub use subscriber_email::SubscriberEmail;
 pub use subscriber_name::SubscriberName;
+pub use unsubscribe_token::{one_click_unsubscribe_link, unsubscribe_link, UnsubscribeToken};
@@ -2,6 +2,7 @@ use crate::domain::subscriber_email::SubscriberEmail;
 use crate::domain::subscriber_name::SubscriberName;
 use rand::distributions::Alphanumeric;
 use rand::{thread_rng, Rng};
+use sha2::{Digest, Sha256};
 
 #[derive(Debug)]
 pub struct NewSubscriber {
@@ -16,3 +17,30 @@ pub fn generate_confirmation_token() -> String {
         .take(25)
         .collect()
 }
+
+pub fn hash_confirmation_token(confirmation_token: &str) -> String {
+    hex::encode(Sha256::digest(confirmation_token.as_bytes()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{generate_confirmation_token, hash_confirmation_token};
+
+    #[test]
+    fn the_hash_is_a_stable_sha256_hex_digest() {
+        assert_eq!(
+            hash_confirmation_token("abc"),
+            "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"
+        );
+    }
+
+    #[test]
+    fn different_tokens_have_different_hashes() {
+        let token = generate_confirmation_token();
+        assert_ne!(hash_confirmation_token(&token), token);
+        assert_ne!(
+            hash_confirmation_token(&token),
+            hash_confirmation_token(&generate_confirmation_token())
+        );
+    }
+}
@@ -1,3 +1,6 @@
+use super::markdown::{markdown_to_html, markdown_to_text};
+use super::merge_tags::{render_html, render_text, MergeTags};
+use handlebars::RenderError;
 use std::error::Error;
 
 #[derive(Debug)]
@@ -5,6 +8,7 @@ pub struct NewsletterIssue {
     title: String,
     text_content: String,
     html_content: String,
+    markdown_content: Option<String>,
 }
 
 #[derive(Debug)]
@@ -40,18 +44,100 @@ impl NewsletterIssue {
         if html_content.is_empty() {
             validation_msgs.push("The newsletter must have HTML content.");
         }
+        let example = MergeTags::example("https://example.com/unsubscribe");
+        if render_text(&title, &example).is_err() {
+            validation_msgs.push("The newsletter title uses an unknown or malformed merge tag.");
+        }
+        if render_text(&text_content, &example).is_err() {
+            validation_msgs
+                .push("The newsletter text content uses an unknown or malformed merge tag.");
+        }
+        if render_html(&html_content, &example).is_err() {
+            validation_msgs
+                .push("The newsletter HTML content uses an unknown or malformed merge tag.");
+        }
 
         if validation_msgs.is_empty() {
             Ok(Self {
                 title,
                 text_content,
                 html_content,
+                markdown_content: None,
             })
         } else {
             Err(NewsletterValidationError(validation_msgs))
         }
     }
 
+    /// Rebuilds an issue that was validated before it was persisted.
+    pub fn from_stored(
+        title: String,
+        text_content: String,
+        html_content: String,
+        markdown_content: Option<String>,
+    ) -> Self {
+        Self {
+            title,
+            text_content,
+            html_content,
+            markdown_content,
+        }
+    }
+
+    pub fn from_markdown(
+        title: String,
+        markdown_content: String,
+        html_override: Option<String>,
+        text_override: Option<String>,
+    ) -> Result<Self, NewsletterValidationError> {
+        if markdown_content.trim().is_empty() {
+            let mut validation_msgs = vec!["The newsletter must have Markdown content."];
+            if title.is_empty() {
+                validation_msgs.insert(0, "The newsletter must have a title.");
+            }
+            return Err(NewsletterValidationError(validation_msgs));
+        }
+        let html_content = html_override.unwrap_or_else(|| markdown_to_html(&markdown_content));
+        let text_content = text_override.unwrap_or_else(|| markdown_to_text(&markdown_content));
+        let issue = Self::validate_new(title, text_content, html_content)?;
+        Ok(Self {
+            markdown_content: Some(markdown_content),
+            ..issue
+        })
+    }
+
+    pub fn with_merge_tags(&self, merge_tags: &MergeTags) -> Result<Self, RenderError> {
+        Ok(Self {
+            title: render_text(&self.title, merge_tags)?,
+            text_content: render_text(&self.text_content, merge_tags)?,
+            html_content: render_html(&self.html_content, merge_tags)?,
+            markdown_content: self.markdown_content.clone(),
+        })
+    }
+
+    pub fn with_subscription_links(&self, unsubscribe_link: &str, preferences_link: &str) -> Self {
+        Self {
+            title: self.title.clone(),
+            text_content: format!(
+                "{}\n\n\
+                To manage your preferences, visit {}\n\
+                To stop receiving this newsletter, visit {}",
+                self.text_content, preferences_link, unsubscribe_link
+            ),
+            html_content: format!(
+                "{}<br />\
+                <p><a href=\"{}\">Manage your preferences</a> or \
+                <a href=\"{}\">unsubscribe</a> from this newsletter.</p>",
+                self.html_content, preferences_link, unsubscribe_link
+            ),
+            markdown_content: self.markdown_content.clone(),
+        }
+    }
+
+    pub fn markdown(&self) -> Option<&str> {
+        self.markdown_content.as_deref()
+    }
+
     pub fn title(&self) -> &str {
         &self.title
     }
@@ -64,3 +150,91 @@ impl NewsletterIssue {
         &self.html_content
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::NewsletterIssue;
+    use crate::domain::MergeTags;
+    use claims::{assert_err, assert_ok};
+
+    #[test]
+    fn both_bodies_are_generated_from_markdown() {
+        let issue = assert_ok!(NewsletterIssue::from_markdown(
+            "Title".into(),
+            "Hello **world**".into(),
+            None,
+            None
+        ));
+        assert_eq!(issue.html(), "<p>Hello <strong>world</strong></p>\n");
+        assert_eq!(issue.text(), "Hello world");
+        assert_eq!(issue.markdown(), Some("Hello **world**"));
+    }
+
+    #[test]
+    fn overrides_take_precedence_over_markdown() {
+        let issue = assert_ok!(NewsletterIssue::from_markdown(
+            "Title".into(),
+            "Hello **world**".into(),
+            Some("<p>Custom</p>".into()),
+            Some("Custom".into())
+        ));
+        assert_eq!(issue.html(), "<p>Custom</p>");
+        assert_eq!(issue.text(), "Custom");
+    }
+
+    #[test]
+    fn blank_markdown_is_rejected() {
+        let e = assert_err!(NewsletterIssue::from_markdown(
+            "".into(),
+            "  ".into(),
+            None,
+            None
+        ));
+        assert_eq!(e.iter().count(), 2);
+    }
+
+    #[test]
+    fn unknown_merge_tags_are_rejected() {
+        let e = assert_err!(NewsletterIssue::validate_new(
+            "Hi {{name}}".into(),
+            "Hi {{first_name}}".into(),
+            "<p>Hi {{name}}</p>".into()
+        ));
+        assert_eq!(
+            e.iter().collect::<Vec<_>>(),
+            vec![&"The newsletter text content uses an unknown or malformed merge tag."]
+        );
+    }
+
+    #[test]
+    fn merge_tags_are_rendered_per_recipient() {
+        let issue = assert_ok!(NewsletterIssue::validate_new(
+            "Hi {{name}}".into(),
+            "Hi {{name}} <{{email}}>".into(),
+            "<p>Hi {{name}}</p>".into()
+        ));
+        let issue = assert_ok!(issue.with_merge_tags(&MergeTags {
+            name: "Tom & Jerry",
+            email: "tom@example.com",
+            unsubscribe_url: "https://example.com/unsubscribe",
+        }));
+        assert_eq!(issue.title(), "Hi Tom & Jerry");
+        assert_eq!(issue.text(), "Hi Tom & Jerry <tom@example.com>");
+        assert_eq!(issue.html(), "<p>Hi Tom &amp; Jerry</p>");
+    }
+
+    #[test]
+    fn the_markdown_source_survives_personalization() {
+        let issue = assert_ok!(NewsletterIssue::from_markdown(
+            "Title".into(),
+            "Hello".into(),
+            None,
+            None
+        ));
+        let issue = issue.with_subscription_links(
+            "https://example.com/unsubscribe",
+            "https://example.com/preferences",
+        );
+        assert_eq!(issue.markdown(), Some("Hello"));
+    }
+}
@@ -0,0 +1,153 @@
+use super::signed_token;
+use anyhow::Context;
+use chrono::{DateTime, Duration, TimeZone, Utc};
+use hmac::{Hmac, Mac};
+use secrecy::Secret;
+use sha2::Sha256;
+use uuid::Uuid;
+
+const KEY_LABEL: &[u8] = b"zero2prod preferences token key";
+const PURPOSE: &[u8] = b"preferences:";
+const VALIDITY_DAYS: i64 = 30;
+
+#[derive(Debug)]
+pub struct PreferencesToken(String);
+
+impl PreferencesToken {
+    pub fn sign(
+        subscriber_id: Uuid,
+        expires_at: DateTime<Utc>,
+        hmac_secret: &Secret<String>,
+    ) -> Self {
+        let expires_at = expires_at.timestamp();
+        let tag = mac(subscriber_id, expires_at, hmac_secret).finalize();
+        Self(format!("{}.{}", expires_at, hex::encode(tag.into_bytes())))
+    }
+
+    pub fn verify(
+        &self,
+        subscriber_id: Uuid,
+        hmac_secret: &Secret<String>,
+    ) -> Result<(), anyhow::Error> {
+        let (expires_at, tag) = self
+            .0
+            .split_once('.')
+            .context("The preferences token is malformed.")?;
+        let expires_at: i64 = expires_at.parse()?;
+        mac(subscriber_id, expires_at, hmac_secret).verify_slice(&hex::decode(tag)?)?;
+        let expires_at = Utc
+            .timestamp_opt(expires_at, 0)
+            .single()
+            .context("The preferences token has an invalid expiry.")?;
+        anyhow::ensure!(
+            expires_at > Utc::now(),
+            "The preferences token has expired."
+        );
+        Ok(())
+    }
+}
+
+fn mac(subscriber_id: Uuid, expires_at: i64, hmac_secret: &Secret<String>) -> Hmac<Sha256> {
+    signed_token::mac(
+        KEY_LABEL,
+        PURPOSE,
+        &[subscriber_id.as_bytes(), &expires_at.to_be_bytes()],
+        hmac_secret,
+    )
+}
+
+impl From<String> for PreferencesToken {
+    fn from(s: String) -> Self {
+        Self(s)
+    }
+}
+
+impl AsRef<str> for PreferencesToken {
+    fn as_ref(&self) -> &str {
+        &self.0
+    }
+}
+
+pub fn preferences_link(
+    base_url: &str,
+    subscriber_id: Uuid,
+    hmac_secret: &Secret<String>,
+) -> String {
+    let token = PreferencesToken::sign(
+        subscriber_id,
+        Utc::now() + Duration::days(VALIDITY_DAYS),
+        hmac_secret,
+    );
+    format!(
+        "{}/subscriptions/preferences?subscriber_id={}&token={}",
+        base_url,
+        subscriber_id,
+        token.as_ref()
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::PreferencesToken;
+    use crate::domain::UnsubscribeToken;
+    use chrono::{Duration, Utc};
+    use claims::{assert_err, assert_ok};
+    use secrecy::Secret;
+    use uuid::Uuid;
+
+    fn secret() -> Secret<String> {
+        Secret::new(Uuid::new_v4().to_string())
+    }
+
+    fn tomorrow() -> chrono::DateTime<Utc> {
+        Utc::now() + Duration::days(1)
+    }
+
+    #[test]
+    fn a_signed_token_is_valid_for_its_subscriber() {
+        let secret = secret();
+        let subscriber_id = Uuid::new_v4();
+        let token = PreferencesToken::sign(subscriber_id, tomorrow(), &secret);
+        assert_ok!(token.verify(subscriber_id, &secret));
+    }
+
+    #[test]
+    fn a_token_is_rejected_for_another_subscriber() {
+        let secret = secret();
+        let token = PreferencesToken::sign(Uuid::new_v4(), tomorrow(), &secret);
+        assert_err!(token.verify(Uuid::new_v4(), &secret));
+    }
+
+    #[test]
+    fn an_expired_token_is_rejected() {
+        let secret = secret();
+        let subscriber_id = Uuid::new_v4();
+        let token = PreferencesToken::sign(subscriber_id, Utc::now() - Duration::days(1), &secret);
+        assert_err!(token.verify(subscriber_id, &secret));
+    }
+
+    #[test]
+    fn a_token_with_a_tampered_expiry_is_rejected() {
+        let secret = secret();
+        let subscriber_id = Uuid::new_v4();
+        let token = PreferencesToken::sign(subscriber_id, tomorrow(), &secret);
+        let (_, tag) = token.as_ref().split_once('.').unwrap();
+        let forged = format!("{}.{}", (Utc::now() + Duration::days(365)).timestamp(), tag);
+        assert_err!(PreferencesToken::from(forged).verify(subscriber_id, &secret));
+    }
+
+    #[test]
+    fn an_unsubscribe_token_is_not_a_preferences_token() {
+        let secret = secret();
+        let subscriber_id = Uuid::new_v4();
+        let unsubscribe = UnsubscribeToken::sign(subscriber_id, &secret);
+        let forged = format!("{}.{}", tomorrow().timestamp(), unsubscribe.as_ref());
+        assert_err!(PreferencesToken::from(forged).verify(subscriber_id, &secret));
+    }
+
+    #[test]
+    fn a_malformed_token_is_rejected() {
+        let token = PreferencesToken::from("not-a-token".to_string());
+        assert_err!(token.verify(Uuid::new_v4(), &secret()));
+    }
+}
@@ -0,0 +1,151 @@
+use chrono::{DateTime, LocalResult, NaiveDateTime, TimeZone, Utc};
+use chrono_tz::Tz;
+
+#[derive(Debug, Clone, Copy)]
+pub struct PublicationSchedule {
+    scheduled_for: DateTime<Utc>,
+    timezone: Tz,
+}
+
+impl PublicationSchedule {
+    pub fn parse(scheduled_for: &str, timezone: &str, now: DateTime<Utc>) -> Result<Self, String> {
+        let timezone: Tz = timezone
+            .parse()
+            .map_err(|_| format!("{} is not a known timezone.", timezone))?;
+        let local = NaiveDateTime::parse_from_str(scheduled_for, "%Y-%m-%dT%H:%M")
+            .or_else(|_| NaiveDateTime::parse_from_str(scheduled_for, "%Y-%m-%dT%H:%M:%S"))
+            .map_err(|_| format!("{} is not a valid date and time.", scheduled_for))?;
+        let scheduled_for = match timezone.from_local_datetime(&local) {
+            LocalResult::Single(t) => t,
+            LocalResult::Ambiguous(earliest, _) => earliest,
+            LocalResult::None => {
+                return Err(format!(
+                    "{} does not exist in {} because of a daylight saving change.",
+                    local.format("%Y-%m-%d %H:%M"),
+                    timezone
+                ))
+            }
+        }
+        .with_timezone(&Utc);
+        if scheduled_for <= now {
+            return Err("The newsletter must be scheduled in the future.".into());
+        }
+        Ok(Self {
+            scheduled_for,
+            timezone,
+        })
+    }
+
+    pub fn from_stored(scheduled_for: DateTime<Utc>, timezone: &str) -> Self {
+        Self {
+            scheduled_for,
+            timezone: timezone.parse().unwrap_or(Tz::UTC),
+        }
+    }
+
+    pub fn scheduled_for(&self) -> DateTime<Utc> {
+        self.scheduled_for
+    }
+
+    pub fn timezone(&self) -> &'static str {
+        self.timezone.name()
+    }
+
+    pub fn local_input_value(&self) -> String {
+        self.scheduled_for
+            .with_timezone(&self.timezone)
+            .format("%Y-%m-%dT%H:%M")
+            .to_string()
+    }
+
+    pub fn describe(&self) -> String {
+        format!(
+            "{} ({})",
+            self.scheduled_for
+                .with_timezone(&self.timezone)
+                .format("%Y-%m-%d %H:%M %Z"),
+            self.timezone
+        )
+    }
+}
+
+pub fn timezone_names() -> Vec<&'static str> {
+    chrono_tz::TZ_VARIANTS.iter().map(|tz| tz.name()).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::PublicationSchedule;
+    use chrono::{TimeZone, Utc};
+    use claims::{assert_err, assert_ok};
+
+    fn now() -> chrono::DateTime<Utc> {
+        Utc.with_ymd_and_hms(2023, 11, 10, 17, 0, 0).unwrap()
+    }
+
+    #[test]
+    fn a_local_time_is_converted_to_utc() {
+        let schedule = assert_ok!(PublicationSchedule::parse(
+            "2023-11-13T09:00",
+            "Europe/Rome",
+            now()
+        ));
+        assert_eq!(
+            schedule.scheduled_for(),
+            Utc.with_ymd_and_hms(2023, 11, 13, 8, 0, 0).unwrap()
+        );
+        assert_eq!(schedule.timezone(), "Europe/Rome");
+        assert_eq!(schedule.local_input_value(), "2023-11-13T09:00");
+        assert_eq!(schedule.describe(), "2023-11-13 09:00 CET (Europe/Rome)");
+    }
+
+    #[test]
+    fn seconds_are_accepted() {
+        assert_ok!(PublicationSchedule::parse(
+            "2023-11-13T09:00:30",
+            "UTC",
+            now()
+        ));
+    }
+
+    #[test]
+    fn unknown_timezones_are_rejected() {
+        assert_err!(PublicationSchedule::parse(
+            "2023-11-13T09:00",
+            "Mars/Olympus_Mons",
+            now()
+        ));
+    }
+
+    #[test]
+    fn malformed_dates_are_rejected() {
+        assert_err!(PublicationSchedule::parse("next monday", "UTC", now()));
+    }
+
+    #[test]
+    fn times_in_the_past_are_rejected() {
+        assert_err!(PublicationSchedule::parse("2023-11-10T16:59", "UTC", now()));
+    }
+
+    #[test]
+    fn times_skipped_by_daylight_saving_are_rejected() {
+        assert_err!(PublicationSchedule::parse(
+            "2024-03-10T02:30",
+            "America/New_York",
+            now()
+        ));
+    }
+
+    #[test]
+    fn ambiguous_times_resolve_to_the_earliest_instant() {
+        let schedule = assert_ok!(PublicationSchedule::parse(
+            "2024-11-03T01:30",
+            "America/New_York",
+            now()
+        ));
+        assert_eq!(
+            schedule.scheduled_for(),
+            Utc.with_ymd_and_hms(2024, 11, 3, 5, 30, 0).unwrap()
+        );
+    }
+}
@@ -0,0 +1,27 @@
+use hmac::{Hmac, Mac};
+use secrecy::{ExposeSecret, Secret};
+use sha2::Sha256;
+
+/// Starts an HMAC over `purpose` and `parts`, keyed with a key derived from
+/// the secret and `label` so that each kind of token has its own key.
+pub fn mac(
+    label: &[u8],
+    purpose: &[u8],
+    parts: &[&[u8]],
+    hmac_secret: &Secret<String>,
+) -> Hmac<Sha256> {
+    let mut mac = Hmac::<Sha256>::new_from_slice(&derive_key(label, hmac_secret))
+        .expect("HMAC can take a key of any size");
+    mac.update(purpose);
+    for part in parts {
+        mac.update(part);
+    }
+    mac
+}
+
+fn derive_key(label: &[u8], hmac_secret: &Secret<String>) -> Vec<u8> {
+    let mut mac = Hmac::<Sha256>::new_from_slice(hmac_secret.expose_secret().as_bytes())
+        .expect("HMAC can take a key of any size");
+    mac.update(label);
+    mac.finalize().into_bytes().to_vec()
+}
@@ -1,9 +1,24 @@
-use super::NewSubscriber;
 use uuid::Uuid;
 
+#[derive(Debug)]
+pub struct Job<P> {
+    pub payload: P,
+    pub n_retries: i32,
+}
+
 #[derive(Debug)]
 pub struct SubscriptionConfirmationTask {
     pub subscriber_id: Uuid,
-    pub subscriber: NewSubscriber,
-    pub confirmation_token: String,
+    pub subscriber_email: String,
+    pub subscriber_name: String,
+    pub pending_email: Option<String>,
+    pub pending_lists: Vec<(Uuid, String)>,
+}
+
+#[derive(Debug)]
+pub struct NewsletterDeliveryTask {
+    pub newsletter_issue_id: Uuid,
+    pub subscriber_id: Uuid,
+    pub subscriber_email: String,
+    pub subscriber_name: String,
 }
@@ -0,0 +1,125 @@
+use super::signed_token;
+use hmac::{Hmac, Mac};
+use secrecy::Secret;
+use sha2::Sha256;
+use uuid::Uuid;
+
+const KEY_LABEL: &[u8] = b"zero2prod unsubscribe token key";
+const PURPOSE: &[u8] = b"unsubscribe:";
+
+#[derive(Debug)]
+pub struct UnsubscribeToken(String);
+
+impl UnsubscribeToken {
+    pub fn sign(subscriber_id: Uuid, hmac_secret: &Secret<String>) -> Self {
+        let tag = mac(subscriber_id, hmac_secret).finalize();
+        Self(hex::encode(tag.into_bytes()))
+    }
+
+    pub fn verify(
+        &self,
+        subscriber_id: Uuid,
+        hmac_secret: &Secret<String>,
+    ) -> Result<(), anyhow::Error> {
+        let tag = hex::decode(&self.0)?;
+        mac(subscriber_id, hmac_secret).verify_slice(&tag)?;
+        Ok(())
+    }
+}
+
+fn mac(subscriber_id: Uuid, hmac_secret: &Secret<String>) -> Hmac<Sha256> {
+    signed_token::mac(KEY_LABEL, PURPOSE, &[subscriber_id.as_bytes()], hmac_secret)
+}
+
+impl From<String> for UnsubscribeToken {
+    fn from(s: String) -> Self {
+        Self(s)
+    }
+}
+
+impl AsRef<str> for UnsubscribeToken {
+    fn as_ref(&self) -> &str {
+        &self.0
+    }
+}
+
+pub fn unsubscribe_link(
+    base_url: &str,
+    subscriber_id: Uuid,
+    hmac_secret: &Secret<String>,
+) -> String {
+    format!(
+        "{}/subscriptions/unsubscribe?{}",
+        base_url,
+        unsubscribe_query(subscriber_id, hmac_secret)
+    )
+}
+
+pub fn one_click_unsubscribe_link(
+    base_url: &str,
+    subscriber_id: Uuid,
+    hmac_secret: &Secret<String>,
+) -> String {
+    format!(
+        "{}/subscriptions/unsubscribe/one-click?{}",
+        base_url,
+        unsubscribe_query(subscriber_id, hmac_secret)
+    )
+}
+
+fn unsubscribe_query(subscriber_id: Uuid, hmac_secret: &Secret<String>) -> String {
+    let token = UnsubscribeToken::sign(subscriber_id, hmac_secret);
+    format!("subscriber_id={}&token={}", subscriber_id, token.as_ref())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::UnsubscribeToken;
+    use claims::{assert_err, assert_ok};
+    use hmac::{Hmac, Mac};
+    use secrecy::{ExposeSecret, Secret};
+    use sha2::Sha256;
+    use uuid::Uuid;
+
+    fn secret() -> Secret<String> {
+        Secret::new(Uuid::new_v4().to_string())
+    }
+
+    #[test]
+    fn a_signed_token_is_valid_for_its_subscriber() {
+        let secret = secret();
+        let subscriber_id = Uuid::new_v4();
+        let token = UnsubscribeToken::sign(subscriber_id, &secret);
+        assert_ok!(token.verify(subscriber_id, &secret));
+    }
+
+    #[test]
+    fn a_token_is_rejected_for_another_subscriber() {
+        let secret = secret();
+        let token = UnsubscribeToken::sign(Uuid::new_v4(), &secret);
+        assert_err!(token.verify(Uuid::new_v4(), &secret));
+    }
+
+    #[test]
+    fn a_token_signed_with_another_secret_is_rejected() {
+        let subscriber_id = Uuid::new_v4();
+        let token = UnsubscribeToken::sign(subscriber_id, &secret());
+        assert_err!(token.verify(subscriber_id, &secret()));
+    }
+
+    #[test]
+    fn a_mac_over_the_bare_id_with_the_raw_secret_is_rejected() {
+        let secret = secret();
+        let subscriber_id = Uuid::new_v4();
+        let mut mac = Hmac::<Sha256>::new_from_slice(secret.expose_secret().as_bytes()).unwrap();
+        mac.update(subscriber_id.as_bytes());
+        let forged = hex::encode(mac.finalize().into_bytes());
+        assert_err!(UnsubscribeToken::from(forged).verify(subscriber_id, &secret));
+    }
+
+    #[test]
+    fn a_malformed_token_is_rejected() {
+        let token = UnsubscribeToken::from("not-hex".to_string());
+        assert_err!(token.verify(Uuid::new_v4(), &secret()));
+    }
+}
@@ -0,0 +1,73 @@
+use super::message::format_message;
+use super::{EmailError, EmailHeader, EmailReceipt, EmailTransport};
+use crate::domain::{NewsletterIssue, SubscriberEmail};
+use anyhow::Context;
+use lettre::{AsyncFileTransport, AsyncTransport, Tokio1Executor};
+use std::path::PathBuf;
+
+pub struct FileTransport {
+    transport: AsyncFileTransport<Tokio1Executor>,
+    sender: SubscriberEmail,
+}
+
+impl FileTransport {
+    pub fn new(directory: PathBuf, sender: SubscriberEmail) -> Result<Self, std::io::Error> {
+        std::fs::create_dir_all(&directory)?;
+        Ok(Self {
+            transport: AsyncFileTransport::new(directory),
+            sender,
+        })
+    }
+}
+
+#[async_trait::async_trait]
+impl EmailTransport for FileTransport {
+    #[tracing::instrument(name = "Write email to the outbox directory", skip_all)]
+    async fn send_email_with_headers(
+        &self,
+        recipient: &SubscriberEmail,
+        newsletter_issue: &NewsletterIssue,
+        headers: &[EmailHeader],
+    ) -> Result<EmailReceipt, EmailError> {
+        let (envelope, raw) = format_message(&self.sender, recipient, newsletter_issue, headers)?;
+        let id = self
+            .transport
+            .send_raw(&envelope, &raw)
+            .await
+            .context("Failed to write the email")?;
+        tracing::info!("Wrote email {}.eml", id);
+        Ok(EmailReceipt {
+            message_id: Some(id),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::domain::{NewsletterIssue, SubscriberEmail};
+    use crate::email_client::{EmailTransport, FileTransport};
+    use claims::assert_ok;
+
+    #[tokio::test]
+    async fn emails_are_written_as_eml_files() {
+        let directory = std::env::temp_dir().join(uuid::Uuid::new_v4().to_string());
+        let sender = SubscriberEmail::parse("sender@example.com".into()).unwrap();
+        let transport = FileTransport::new(directory.clone(), sender).unwrap();
+        let recipient = SubscriberEmail::parse("recipient@example.com".into()).unwrap();
+        let issue =
+            NewsletterIssue::validate_new("Subject".into(), "Text".into(), "<p>Html</p>".into())
+                .unwrap();
+
+        assert_ok!(transport.send_email(&recipient, &issue).await);
+
+        let files: Vec<_> = std::fs::read_dir(&directory)
+            .unwrap()
+            .map(|entry| entry.unwrap().path())
+            .collect();
+        assert_eq!(files.len(), 1);
+        assert_eq!(files[0].extension().unwrap(), "eml");
+        let content = std::fs::read_to_string(&files[0]).unwrap();
+        assert!(content.contains("To: recipient@example.com"));
+        std::fs::remove_dir_all(directory).unwrap();
+    }
+}
@@ -0,0 +1,112 @@
+use super::EmailHeader;
+use crate::domain::{NewsletterIssue, SubscriberEmail};
+use anyhow::{anyhow, Context};
+use lettre::address::Envelope;
+use lettre::message::{Mailbox, MultiPart};
+use lettre::Message;
+
+pub fn format_message(
+    sender: &SubscriberEmail,
+    recipient: &SubscriberEmail,
+    newsletter_issue: &NewsletterIssue,
+    headers: &[EmailHeader],
+) -> Result<(Envelope, Vec<u8>), anyhow::Error> {
+    let from: Mailbox = sender.as_ref().parse().context("Invalid sender address")?;
+    let to: Mailbox = recipient
+        .as_ref()
+        .parse()
+        .context("Invalid recipient address")?;
+    let message = Message::builder()
+        .from(from)
+        .to(to)
+        .subject(newsletter_issue.title())
+        .multipart(MultiPart::alternative_plain_html(
+            newsletter_issue.text().to_owned(),
+            newsletter_issue.html().to_owned(),
+        ))?;
+    let mut raw = Vec::new();
+    for header in headers {
+        raw.extend_from_slice(format_header(header)?.as_bytes());
+    }
+    raw.extend(message.formatted());
+    Ok((message.envelope().clone(), raw))
+}
+
+fn format_header(header: &EmailHeader) -> Result<String, anyhow::Error> {
+    let valid_name = !header.name.is_empty()
+        && header
+            .name
+            .bytes()
+            .all(|b| b.is_ascii_graphic() && b != b':');
+    if !valid_name {
+        return Err(anyhow!("{:?} is not a valid header name", header.name));
+    }
+    if header.value.contains(['\r', '\n']) {
+        return Err(anyhow!(
+            "The value of the {} header spans lines",
+            header.name
+        ));
+    }
+    Ok(format!("{}: {}\r\n", header.name, header.value))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::format_message;
+    use crate::domain::{NewsletterIssue, SubscriberEmail};
+    use crate::email_client::EmailHeader;
+    use claims::assert_err;
+
+    fn email(address: &str) -> SubscriberEmail {
+        SubscriberEmail::parse(address.to_owned()).unwrap()
+    }
+
+    fn newsletter_issue() -> NewsletterIssue {
+        NewsletterIssue::validate_new("Subject".into(), "Plain text".into(), "<p>Html</p>".into())
+            .unwrap()
+    }
+
+    #[test]
+    fn the_message_carries_both_bodies_and_extra_headers() {
+        let headers = [EmailHeader::new("List-Unsubscribe", "<https://x>")];
+        let (envelope, raw) = format_message(
+            &email("sender@example.com"),
+            &email("recipient@example.com"),
+            &newsletter_issue(),
+            &headers,
+        )
+        .unwrap();
+
+        let raw = String::from_utf8(raw).unwrap();
+        assert!(raw.starts_with("List-Unsubscribe: <https://x>\r\n"));
+        assert!(raw.contains("Subject: Subject"));
+        assert!(raw.contains("Plain text"));
+        assert!(raw.contains("<p>Html</p>"));
+        assert_eq!(envelope.to()[0].to_string(), "recipient@example.com");
+    }
+
+    #[test]
+    fn header_injection_is_rejected() {
+        let headers = [EmailHeader::new(
+            "X-Test",
+            "value\r\nBcc: victim@example.com",
+        )];
+        assert_err!(format_message(
+            &email("sender@example.com"),
+            &email("recipient@example.com"),
+            &newsletter_issue(),
+            &headers,
+        ));
+    }
+
+    #[test]
+    fn invalid_header_names_are_rejected() {
+        let headers = [EmailHeader::new("X Test:", "value")];
+        assert_err!(format_message(
+            &email("sender@example.com"),
+            &email("recipient@example.com"),
+            &newsletter_issue(),
+            &headers,
+        ));
+    }
+}
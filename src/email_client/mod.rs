@@ -0,0 +1,131 @@
+use crate::domain::{NewsletterIssue, SubscriberEmail};
+use std::sync::Arc;
+
+mod message;
+
+pub mod file;
+pub use file::*;
+
+pub mod postmark;
+pub use postmark::*;
+
+pub mod smtp;
+pub use smtp::*;
+
+pub type EmailClient = Arc<dyn EmailTransport>;
+
+#[async_trait::async_trait]
+pub trait EmailTransport: Send + Sync {
+    async fn send_email_with_headers(
+        &self,
+        recipient: &SubscriberEmail,
+        newsletter_issue: &NewsletterIssue,
+        headers: &[EmailHeader],
+    ) -> Result<EmailReceipt, EmailError>;
+
+    async fn send_email(
+        &self,
+        recipient: &SubscriberEmail,
+        newsletter_issue: &NewsletterIssue,
+    ) -> Result<EmailReceipt, EmailError> {
+        self.send_email_with_headers(recipient, newsletter_issue, &[])
+            .await
+    }
+
+    fn max_batch_size(&self) -> usize {
+        usize::MAX
+    }
+
+    async fn send_batch(
+        &self,
+        emails: &[OutgoingEmail],
+    ) -> Result<Vec<Result<EmailReceipt, EmailError>>, EmailError> {
+        let mut results = Vec::with_capacity(emails.len());
+        for email in emails {
+            results.push(
+                self.send_email_with_headers(
+                    &email.recipient,
+                    &email.newsletter_issue,
+                    &email.headers,
+                )
+                .await,
+            );
+        }
+        Ok(results)
+    }
+}
+
+#[derive(Debug)]
+pub struct OutgoingEmail {
+    pub recipient: SubscriberEmail,
+    pub newsletter_issue: NewsletterIssue,
+    pub headers: Vec<EmailHeader>,
+}
+
+#[derive(Debug, Default)]
+pub struct EmailReceipt {
+    pub message_id: Option<String>,
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum EmailError {
+    #[error(
+        "The email provider rejected the email{}: {message}",
+        rejection_details(*.status, *.error_code)
+    )]
+    Rejected {
+        status: Option<u16>,
+        error_code: Option<i32>,
+        message: String,
+    },
+    #[error(transparent)]
+    UnexpectedError(#[from] anyhow::Error),
+}
+
+impl EmailError {
+    pub fn error_code(&self) -> Option<i32> {
+        match self {
+            EmailError::Rejected { error_code, .. } => *error_code,
+            EmailError::UnexpectedError(_) => None,
+        }
+    }
+
+    /// Whether retrying the same email can never succeed, e.g. because Postmark
+    /// reports the recipient as inactive (406) or the address as invalid (300).
+    pub fn is_permanent(&self) -> bool {
+        matches!(
+            self,
+            EmailError::Rejected {
+                error_code: Some(300 | 406),
+                ..
+            }
+        )
+    }
+}
+
+fn rejection_details(status: Option<u16>, error_code: Option<i32>) -> String {
+    match (status, error_code) {
+        (Some(status), Some(error_code)) => {
+            format!(" with status {} and error code {}", status, error_code)
+        }
+        (Some(status), None) => format!(" with status {}", status),
+        (None, Some(error_code)) => format!(" with error code {}", error_code),
+        (None, None) => String::new(),
+    }
+}
+
+#[derive(Debug, serde::Serialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct EmailHeader {
+    pub name: String,
+    pub value: String,
+}
+
+impl EmailHeader {
+    pub fn new(name: impl Into<String>, value: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            value: value.into(),
+        }
+    }
+}
@@ -0,0 +1,517 @@
+use super::{EmailError, EmailHeader, EmailReceipt, EmailTransport, OutgoingEmail};
+use crate::domain::{NewsletterIssue, SubscriberEmail};
+use anyhow::Context;
+use reqwest::Client;
+use secrecy::{ExposeSecret, Secret};
+
+pub const POSTMARK_MAX_BATCH_SIZE: usize = 500;
+
+pub struct PostmarkClient {
+    http_client: Client,
+    base_url: String,
+    sender: SubscriberEmail,
+    authorization_token: Secret<String>,
+}
+
+impl PostmarkClient {
+    pub fn new(
+        base_url: String,
+        sender: SubscriberEmail,
+        authorization_token: Secret<String>,
+        timeout: std::time::Duration,
+    ) -> Self {
+        let http_client = Client::builder().timeout(timeout).build().unwrap();
+        Self {
+            http_client,
+            base_url,
+            sender,
+            authorization_token,
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl EmailTransport for PostmarkClient {
+    async fn send_email_with_headers(
+        &self,
+        recipient: &SubscriberEmail,
+        newsletter_issue: &NewsletterIssue,
+        headers: &[EmailHeader],
+    ) -> Result<EmailReceipt, EmailError> {
+        let request_body = self.request_body(recipient, newsletter_issue, headers);
+        let response = self
+            .post("email", &request_body)
+            .await
+            .context("Failed to send the request to Postmark")?;
+        let status = response.status();
+        let body: Option<SendEmailResponse> = response.json().await.ok();
+        if status.is_success() {
+            Ok(EmailReceipt {
+                message_id: body.and_then(|b| b.message_id),
+            })
+        } else {
+            Err(rejected(status, body))
+        }
+    }
+
+    fn max_batch_size(&self) -> usize {
+        POSTMARK_MAX_BATCH_SIZE
+    }
+
+    async fn send_batch(
+        &self,
+        emails: &[OutgoingEmail],
+    ) -> Result<Vec<Result<EmailReceipt, EmailError>>, EmailError> {
+        if emails.len() > POSTMARK_MAX_BATCH_SIZE {
+            return Err(EmailError::UnexpectedError(anyhow::anyhow!(
+                "Postmark accepts at most {} emails per batch, got {}",
+                POSTMARK_MAX_BATCH_SIZE,
+                emails.len()
+            )));
+        }
+        let request_body: Vec<_> = emails
+            .iter()
+            .map(|e| self.request_body(&e.recipient, &e.newsletter_issue, &e.headers))
+            .collect();
+        let response = self
+            .post("email/batch", &request_body)
+            .await
+            .context("Failed to send the batch request to Postmark")?;
+        let status = response.status();
+        if !status.is_success() {
+            let body: Option<SendEmailResponse> = response.json().await.ok();
+            return Err(rejected(status, body));
+        }
+        // Postmark has accepted the batch at this point, so retrying it as a whole would
+        // deliver every message twice.
+        let results = match response.json::<Vec<SendEmailResponse>>().await {
+            Ok(results) if results.len() == emails.len() => results,
+            Ok(results) => {
+                tracing::warn!(
+                    "Postmark accepted a batch of {} emails but returned {} results, \
+                    recording every email as sent without a message id",
+                    emails.len(),
+                    results.len()
+                );
+                return Ok(emails.iter().map(|_| Ok(EmailReceipt::default())).collect());
+            }
+            Err(e) => {
+                tracing::warn!(
+                    error.cause_chain = ?e,
+                    "Postmark accepted a batch of {} emails without a per-email breakdown, \
+                    recording every email as sent without a message id",
+                    emails.len()
+                );
+                return Ok(emails.iter().map(|_| Ok(EmailReceipt::default())).collect());
+            }
+        };
+        Ok(results
+            .into_iter()
+            .map(|r| {
+                if r.error_code == 0 {
+                    Ok(EmailReceipt {
+                        message_id: r.message_id,
+                    })
+                } else {
+                    Err(EmailError::Rejected {
+                        status: None,
+                        error_code: Some(r.error_code),
+                        message: r.message,
+                    })
+                }
+            })
+            .collect())
+    }
+}
+
+impl PostmarkClient {
+    fn request_body<'a>(
+        &'a self,
+        recipient: &'a SubscriberEmail,
+        newsletter_issue: &'a NewsletterIssue,
+        headers: &'a [EmailHeader],
+    ) -> SendEmailRequest<'a> {
+        SendEmailRequest {
+            from: self.sender.as_ref(),
+            to: recipient.as_ref(),
+            subject: newsletter_issue.title(),
+            html_body: newsletter_issue.html(),
+            text_body: newsletter_issue.text(),
+            headers,
+        }
+    }
+
+    async fn post(
+        &self,
+        endpoint: &str,
+        body: &impl serde::Serialize,
+    ) -> Result<reqwest::Response, reqwest::Error> {
+        self.http_client
+            .post(format!("{}/{}", self.base_url, endpoint))
+            .header(
+                "X-Postmark-Server-Token",
+                self.authorization_token.expose_secret(),
+            )
+            .json(body)
+            .send()
+            .await
+    }
+}
+
+fn rejected(status: reqwest::StatusCode, body: Option<SendEmailResponse>) -> EmailError {
+    EmailError::Rejected {
+        status: Some(status.as_u16()),
+        error_code: body.as_ref().map(|b| b.error_code),
+        message: body
+            .map(|b| b.message)
+            .unwrap_or_else(|| status.to_string()),
+    }
+}
+
+#[derive(serde::Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct SendEmailResponse {
+    #[serde(rename = "MessageID")]
+    message_id: Option<String>,
+    error_code: i32,
+    message: String,
+}
+
+#[derive(serde::Serialize)]
+#[serde(rename_all = "PascalCase")]
+struct SendEmailRequest<'a> {
+    from: &'a str,
+    to: &'a str,
+    subject: &'a str,
+    html_body: &'a str,
+    text_body: &'a str,
+    #[serde(skip_serializing_if = "<[_]>::is_empty")]
+    headers: &'a [EmailHeader],
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::domain::{NewsletterIssue, SubscriberEmail};
+    use crate::email_client::{EmailHeader, EmailTransport, OutgoingEmail, PostmarkClient};
+    use claims::{assert_err, assert_ok};
+    use fake::faker::internet::en::SafeEmail;
+    use fake::faker::lorem::en::{Paragraph, Sentence};
+    use fake::{Fake, Faker};
+    use secrecy::Secret;
+    use wiremock::matchers::{any, header, header_exists, method, path};
+    use wiremock::{Mock, MockServer, Request, ResponseTemplate};
+
+    fn newsletter_issue() -> NewsletterIssue {
+        NewsletterIssue::validate_new(subject(), content(), content()).expect("invalid newsletter")
+    }
+
+    fn subject() -> String {
+        Sentence(1..2).fake()
+    }
+
+    fn content() -> String {
+        Paragraph(1..10).fake()
+    }
+
+    fn email() -> SubscriberEmail {
+        SubscriberEmail::parse(SafeEmail().fake()).unwrap()
+    }
+
+    fn email_client(base_url: String) -> PostmarkClient {
+        PostmarkClient::new(
+            base_url,
+            email(),
+            Secret::new(Faker.fake()),
+            std::time::Duration::from_millis(200),
+        )
+    }
+
+    struct SendEmailBodyMatcher;
+
+    impl wiremock::Match for SendEmailBodyMatcher {
+        fn matches(&self, request: &Request) -> bool {
+            let result: Result<serde_json::Value, _> = serde_json::from_slice(&request.body);
+            if let Ok(body) = result {
+                body.get("From").is_some()
+                    && body.get("To").is_some()
+                    && body.get("HtmlBody").is_some()
+                    && body.get("TextBody").is_some()
+            } else {
+                false
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn send_email_sends_the_expected_request() {
+        let mock_server = MockServer::start().await;
+        let email_client = email_client(mock_server.uri());
+        Mock::given(header_exists("X-Postmark-Server-Token"))
+            .and(header("Content-Type", "application/json"))
+            .and(path("/email"))
+            .and(method("POST"))
+            .and(SendEmailBodyMatcher)
+            .respond_with(ResponseTemplate::new(200))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        let _ = email_client.send_email(&email(), &newsletter_issue()).await;
+    }
+
+    struct HeadersBodyMatcher;
+
+    impl wiremock::Match for HeadersBodyMatcher {
+        fn matches(&self, request: &Request) -> bool {
+            let result: Result<serde_json::Value, _> = serde_json::from_slice(&request.body);
+            if let Ok(body) = result {
+                body["Headers"]
+                    == serde_json::json!([{"Name": "List-Unsubscribe", "Value": "<https://x>"}])
+            } else {
+                false
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn send_email_with_headers_sends_the_headers() {
+        let mock_server = MockServer::start().await;
+        let email_client = email_client(mock_server.uri());
+        Mock::given(path("/email"))
+            .and(method("POST"))
+            .and(HeadersBodyMatcher)
+            .respond_with(ResponseTemplate::new(200))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        let headers = [EmailHeader::new("List-Unsubscribe", "<https://x>")];
+        let _ = email_client
+            .send_email_with_headers(&email(), &newsletter_issue(), &headers)
+            .await;
+    }
+
+    #[tokio::test]
+    async fn send_email_succeeds_if_the_server_returns_200() {
+        let mock_server = MockServer::start().await;
+        let email_client = email_client(mock_server.uri());
+        Mock::given(any())
+            .respond_with(ResponseTemplate::new(200))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        let outcome = email_client.send_email(&email(), &newsletter_issue()).await;
+
+        assert_ok!(outcome);
+    }
+
+    #[tokio::test]
+    async fn send_email_fails_if_the_server_returns_500() {
+        let mock_server = MockServer::start().await;
+        let email_client = email_client(mock_server.uri());
+        Mock::given(any())
+            .respond_with(ResponseTemplate::new(500))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        let outcome = email_client.send_email(&email(), &newsletter_issue()).await;
+
+        assert_err!(outcome);
+    }
+
+    #[tokio::test]
+    async fn send_email_times_out_if_server_takes_too_long() {
+        let mock_server = MockServer::start().await;
+        let email_client = email_client(mock_server.uri());
+        let response = ResponseTemplate::new(200).set_delay(std::time::Duration::from_secs(180));
+        Mock::given(any())
+            .respond_with(response)
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        let outcome = email_client.send_email(&email(), &newsletter_issue()).await;
+
+        assert_err!(outcome);
+    }
+
+    #[tokio::test]
+    async fn send_email_returns_the_postmark_message_id() {
+        let mock_server = MockServer::start().await;
+        let email_client = email_client(mock_server.uri());
+        Mock::given(any())
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+                "To": "receiver@example.com",
+                "SubmittedAt": "2014-02-17T07:25:01.4178645-05:00",
+                "MessageID": "0a129aee-e1cd-480d-b08d-4f48548ff48d",
+                "ErrorCode": 0,
+                "Message": "OK"
+            })))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        let receipt = email_client
+            .send_email(&email(), &newsletter_issue())
+            .await
+            .unwrap();
+
+        assert_eq!(
+            receipt.message_id.as_deref(),
+            Some("0a129aee-e1cd-480d-b08d-4f48548ff48d")
+        );
+    }
+
+    #[tokio::test]
+    async fn send_email_reports_the_postmark_error_code() {
+        let mock_server = MockServer::start().await;
+        let email_client = email_client(mock_server.uri());
+        Mock::given(any())
+            .respond_with(ResponseTemplate::new(422).set_body_json(serde_json::json!({
+                "ErrorCode": 406,
+                "Message": "You tried to send to a recipient that has been marked as inactive."
+            })))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        let error = email_client
+            .send_email(&email(), &newsletter_issue())
+            .await
+            .unwrap_err();
+
+        assert_eq!(error.error_code(), Some(406));
+        assert!(error.to_string().contains("422"));
+    }
+
+    fn outgoing_email() -> OutgoingEmail {
+        OutgoingEmail {
+            recipient: email(),
+            newsletter_issue: newsletter_issue(),
+            headers: vec![EmailHeader::new("List-Unsubscribe", "<https://x>")],
+        }
+    }
+
+    #[tokio::test]
+    async fn send_batch_returns_a_result_per_email() {
+        let mock_server = MockServer::start().await;
+        let email_client = email_client(mock_server.uri());
+        Mock::given(path("/email/batch"))
+            .and(method("POST"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!([
+                {
+                    "ErrorCode": 0,
+                    "Message": "OK",
+                    "MessageID": "b7bc2f4a-e38e-4336-af7d-e6c392c2f817",
+                    "SubmittedAt": "2010-11-26T12:01:05.1794748-05:00",
+                    "To": "receiver1@example.com"
+                },
+                {
+                    "ErrorCode": 406,
+                    "Message": "You tried to send to a recipient that has been marked as inactive."
+                }
+            ])))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        let results = email_client
+            .send_batch(&[outgoing_email(), outgoing_email()])
+            .await
+            .unwrap();
+
+        let body: serde_json::Value =
+            serde_json::from_slice(&mock_server.received_requests().await.unwrap()[0].body)
+                .unwrap();
+        assert_eq!(body.as_array().unwrap().len(), 2);
+        assert_eq!(
+            body[0]["Headers"],
+            serde_json::json!([{"Name": "List-Unsubscribe", "Value": "<https://x>"}])
+        );
+        assert_eq!(
+            results[0].as_ref().unwrap().message_id.as_deref(),
+            Some("b7bc2f4a-e38e-4336-af7d-e6c392c2f817")
+        );
+        let error = results[1].as_ref().unwrap_err();
+        assert_eq!(error.error_code(), Some(406));
+        assert!(!error.to_string().contains("422"));
+    }
+
+    #[tokio::test]
+    async fn send_batch_records_an_accepted_batch_without_a_breakdown_as_sent() {
+        let mock_server = MockServer::start().await;
+        let email_client = email_client(mock_server.uri());
+        Mock::given(any())
+            .respond_with(ResponseTemplate::new(200))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        let results = email_client.send_batch(&[outgoing_email()]).await.unwrap();
+
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].as_ref().unwrap().message_id, None);
+    }
+
+    #[tokio::test]
+    async fn send_batch_records_a_mismatched_breakdown_as_sent() {
+        let mock_server = MockServer::start().await;
+        let email_client = email_client(mock_server.uri());
+        Mock::given(any())
+            .respond_with(
+                ResponseTemplate::new(200).set_body_json(serde_json::json!([{
+                    "ErrorCode": 0,
+                    "Message": "OK",
+                    "MessageID": "b7bc2f4a-e38e-4336-af7d-e6c392c2f817"
+                }])),
+            )
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        let results = email_client
+            .send_batch(&[outgoing_email(), outgoing_email()])
+            .await
+            .unwrap();
+
+        assert_eq!(results.len(), 2);
+        for result in results {
+            assert_eq!(result.unwrap().message_id, None);
+        }
+    }
+
+    #[tokio::test]
+    async fn send_batch_fails_if_the_server_returns_500() {
+        let mock_server = MockServer::start().await;
+        let email_client = email_client(mock_server.uri());
+        Mock::given(any())
+            .respond_with(ResponseTemplate::new(500))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        let outcome = email_client.send_batch(&[outgoing_email()]).await;
+
+        assert_err!(outcome);
+    }
+
+    #[tokio::test]
+    async fn send_batch_rejects_batches_over_the_postmark_limit() {
+        let mock_server = MockServer::start().await;
+        let email_client = email_client(mock_server.uri());
+        Mock::given(any())
+            .respond_with(ResponseTemplate::new(200))
+            .expect(0)
+            .mount(&mock_server)
+            .await;
+        let emails: Vec<_> = (0..=email_client.max_batch_size())
+            .map(|_| outgoing_email())
+            .collect();
+
+        let outcome = email_client.send_batch(&emails).await;
+
+        assert_err!(outcome);
+    }
+}
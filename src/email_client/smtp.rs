@@ -0,0 +1,163 @@
+use super::message::format_message;
+use super::{EmailError, EmailHeader, EmailReceipt, EmailTransport};
+use crate::domain::{NewsletterIssue, SubscriberEmail};
+use lettre::transport::smtp::authentication::Credentials;
+use lettre::{AsyncSmtpTransport, AsyncTransport, Tokio1Executor};
+use secrecy::{ExposeSecret, Secret};
+
+pub struct SmtpClient {
+    transport: AsyncSmtpTransport<Tokio1Executor>,
+    sender: SubscriberEmail,
+}
+
+impl SmtpClient {
+    pub fn new(
+        host: &str,
+        port: u16,
+        require_tls: bool,
+        credentials: Option<(String, Secret<String>)>,
+        sender: SubscriberEmail,
+        timeout: std::time::Duration,
+    ) -> Result<Self, lettre::transport::smtp::Error> {
+        let mut builder = if require_tls {
+            AsyncSmtpTransport::<Tokio1Executor>::starttls_relay(host)?
+        } else {
+            AsyncSmtpTransport::<Tokio1Executor>::builder_dangerous(host)
+        };
+        builder = builder.port(port).timeout(Some(timeout));
+        if let Some((username, password)) = credentials {
+            builder = builder.credentials(Credentials::new(
+                username,
+                password.expose_secret().to_owned(),
+            ));
+        }
+        Ok(Self {
+            transport: builder.build(),
+            sender,
+        })
+    }
+}
+
+#[async_trait::async_trait]
+impl EmailTransport for SmtpClient {
+    #[tracing::instrument(name = "Send email over SMTP", skip_all)]
+    async fn send_email_with_headers(
+        &self,
+        recipient: &SubscriberEmail,
+        newsletter_issue: &NewsletterIssue,
+        headers: &[EmailHeader],
+    ) -> Result<EmailReceipt, EmailError> {
+        let (envelope, raw) = format_message(&self.sender, recipient, newsletter_issue, headers)?;
+        match self.transport.send_raw(&envelope, &raw).await {
+            Ok(_) => Ok(EmailReceipt::default()),
+            Err(e) => match e.status() {
+                Some(code) if e.is_permanent() => Err(EmailError::Rejected {
+                    status: code.to_string().parse().ok(),
+                    error_code: None,
+                    message: e.to_string(),
+                }),
+                _ => Err(anyhow::Error::from(e).into()),
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::domain::{NewsletterIssue, SubscriberEmail};
+    use crate::email_client::{EmailError, EmailHeader, EmailTransport, SmtpClient};
+    use claims::{assert_err, assert_ok};
+    use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+    use tokio::net::TcpListener;
+    use tokio::task::JoinHandle;
+
+    async fn smtp_sink(reject_recipients: bool) -> (u16, JoinHandle<String>) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let handle = tokio::spawn(async move {
+            let (stream, _) = listener.accept().await.unwrap();
+            let (reader, mut writer) = stream.into_split();
+            let mut lines = BufReader::new(reader).lines();
+            let mut data = String::new();
+            writer.write_all(b"220 localhost ESMTP\r\n").await.unwrap();
+            while let Some(line) = lines.next_line().await.unwrap() {
+                let command = line.to_ascii_uppercase();
+                let reply: &[u8] = if command.starts_with("EHLO") || command.starts_with("HELO") {
+                    b"250 localhost\r\n"
+                } else if command.starts_with("RCPT") && reject_recipients {
+                    b"550 No such user\r\n"
+                } else if command.starts_with("DATA") {
+                    writer.write_all(b"354 End data with .\r\n").await.unwrap();
+                    while let Some(line) = lines.next_line().await.unwrap() {
+                        if line == "." {
+                            break;
+                        }
+                        data.push_str(&line);
+                        data.push('\n');
+                    }
+                    b"250 OK\r\n"
+                } else if command.starts_with("QUIT") {
+                    writer.write_all(b"221 Bye\r\n").await.unwrap();
+                    break;
+                } else {
+                    b"250 OK\r\n"
+                };
+                writer.write_all(reply).await.unwrap();
+            }
+            data
+        });
+        (port, handle)
+    }
+
+    fn client(port: u16) -> SmtpClient {
+        SmtpClient::new(
+            "127.0.0.1",
+            port,
+            false,
+            None,
+            SubscriberEmail::parse("sender@example.com".into()).unwrap(),
+            std::time::Duration::from_secs(5),
+        )
+        .unwrap()
+    }
+
+    fn newsletter_issue() -> NewsletterIssue {
+        NewsletterIssue::validate_new("Subject".into(), "Text".into(), "<p>Html</p>".into())
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn send_email_delivers_the_message_to_the_smtp_server() {
+        let (port, sink) = smtp_sink(false).await;
+        let recipient = SubscriberEmail::parse("recipient@example.com".into()).unwrap();
+        let headers = [EmailHeader::new("List-Unsubscribe", "<https://x>")];
+
+        let outcome = client(port)
+            .send_email_with_headers(&recipient, &newsletter_issue(), &headers)
+            .await;
+
+        assert_ok!(outcome);
+        let data = sink.await.unwrap();
+        assert!(data.contains("List-Unsubscribe: <https://x>"));
+        assert!(data.contains("To: recipient@example.com"));
+        assert!(data.contains("Subject: Subject"));
+    }
+
+    #[tokio::test]
+    async fn send_email_fails_if_the_smtp_server_rejects_the_recipient() {
+        let (port, _sink) = smtp_sink(true).await;
+        let recipient = SubscriberEmail::parse("recipient@example.com".into()).unwrap();
+
+        let outcome = client(port)
+            .send_email(&recipient, &newsletter_issue())
+            .await;
+
+        assert!(matches!(
+            assert_err!(outcome),
+            EmailError::Rejected {
+                status: Some(550),
+                ..
+            }
+        ));
+    }
+}
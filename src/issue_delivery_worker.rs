@@ -1,82 +1,243 @@
 use crate::{
     configuration::Settings,
-    domain::subscriber_email::SubscriberEmail,
-    email_client::EmailClient,
+    domain::{
+        one_click_unsubscribe_link, preferences_link,
+        subscriber_email::SubscriberEmail,
+        tasks::{Job, NewsletterDeliveryTask},
+        unsubscribe_link, MergeTags, NewsletterIssue,
+    },
+    email_client::{EmailClient, EmailError, EmailHeader, EmailReceipt, OutgoingEmail},
     persistence::{
-        delete_newsletter_delivery_task, fetch_newsletter_issue,
-        newsletter_delivery_task::dequeue_newsletter_delivery_task,
+        dead_letter_newsletter_delivery_task, delete_newsletter_delivery_task,
+        dequeue_newsletter_delivery_tasks, fetch_newsletter_issue, record_newsletter_delivery,
+        retry_newsletter_delivery_task, DeliveryStatus, PgTransaction, NEWSLETTER_DELIVERY_CHANNEL,
     },
-    startup::get_connection_pool,
+    startup::{get_worker_connection_pool, ApplicationBaseUrl, HmacSecret},
+    task_queue::{self, Task, TaskError},
 };
+use anyhow::anyhow;
+use handlebars::RenderError;
+use secrecy::Secret;
 use sqlx::PgPool;
-
+use std::collections::{hash_map::Entry, HashMap};
 use std::time::Duration;
-use tracing::{field::display, Span};
+use tokio_util::sync::CancellationToken;
+use uuid::Uuid;
 
-pub enum ExecutionOutcome {
-    TaskComplete,
-    EmptyQueue,
+pub struct IssueDelivery {
+    pub email_client: EmailClient,
+    pub base_url: ApplicationBaseUrl,
+    pub hmac_secret: HmacSecret,
 }
 
-#[tracing::instrument(
-    skip_all,
-    fields(
-        newsletter_issue_id=tracing::field::Empty,
-        subscriber_email=tracing::field::Empty,
-    ),
-    err
-)]
-pub async fn try_execute_task(
-    pool: &PgPool,
-    email_client: &EmailClient,
-) -> Result<ExecutionOutcome, anyhow::Error> {
-    let task = dequeue_newsletter_delivery_task(pool).await?;
-    if task.is_none() {
-        return Ok(ExecutionOutcome::EmptyQueue);
+#[async_trait::async_trait]
+impl Task for IssueDelivery {
+    type Payload = NewsletterDeliveryTask;
+    type Output = EmailReceipt;
+
+    fn name(&self) -> &'static str {
+        "newsletter_delivery"
     }
-    let (transaction, issue_id, email) = task.unwrap();
-    Span::current()
-        .record("newsletter_issue_id", &display(issue_id))
-        .record("subscriber_email", &display(&email));
-    match SubscriberEmail::parse(email.clone()) {
-        Ok(email) => {
-            let issue = fetch_newsletter_issue(pool, issue_id).await?;
-            if let Err(e) = email_client.send_email(&email, &issue).await {
-                tracing::error!(
-                    error.cause_chain = ?e,
-                    error.message = %e,
-                    "Failed to deliver issue to a confirmed subscriber, skipping",
-                );
-            }
-        }
-        Err(e) => {
-            tracing::error!(
-                error.cause_chain = ?e,
-                error.message = %e,
-                "Skipping a confirmed subscriber because their email was not validated",
-            );
-        }
+
+    fn channel(&self) -> &'static str {
+        NEWSLETTER_DELIVERY_CHANNEL
+    }
+
+    async fn dequeue(
+        &self,
+        transaction: &mut PgTransaction<'_>,
+        batch_size: usize,
+    ) -> Result<Vec<Job<NewsletterDeliveryTask>>, anyhow::Error> {
+        let batch_size = batch_size.min(self.email_client.max_batch_size());
+        dequeue_newsletter_delivery_tasks(transaction, batch_size as i64).await
     }
-    delete_newsletter_delivery_task(transaction, issue_id, &email).await?;
-    Ok(ExecutionOutcome::TaskComplete)
-}
 
-async fn worker_loop(pool: PgPool, email_client: EmailClient) -> Result<(), anyhow::Error> {
-    loop {
-        match try_execute_task(&pool, &email_client).await {
-            Ok(ExecutionOutcome::EmptyQueue) => {
-                tokio::time::sleep(Duration::from_secs(10)).await;
+    #[tracing::instrument(skip_all, fields(batch_size = tasks.len()))]
+    async fn execute(
+        &self,
+        pool: &PgPool,
+        tasks: &[&NewsletterDeliveryTask],
+    ) -> Result<Vec<Result<EmailReceipt, TaskError>>, TaskError> {
+        let mut issues = HashMap::new();
+        for task in tasks {
+            if let Entry::Vacant(entry) = issues.entry(task.newsletter_issue_id) {
+                entry.insert(fetch_newsletter_issue(pool, task.newsletter_issue_id).await?);
             }
-            Ok(ExecutionOutcome::TaskComplete) => {}
-            Err(_) => {
-                tokio::time::sleep(Duration::from_secs(1)).await;
+        }
+        let mut outcomes = Vec::with_capacity(tasks.len());
+        let mut emails = Vec::with_capacity(tasks.len());
+        for task in tasks {
+            let email = SubscriberEmail::parse(task.subscriber_email.clone())
+                .map_err(|e| anyhow!(e))
+                .and_then(|recipient| {
+                    personalized_email(
+                        recipient,
+                        &issues[&task.newsletter_issue_id],
+                        &self.base_url.0,
+                        task.subscriber_id,
+                        &task.subscriber_name,
+                        &self.hmac_secret.0,
+                    )
+                    .map_err(anyhow::Error::from)
+                });
+            match email {
+                Ok(email) => {
+                    emails.push(email);
+                    outcomes.push(None);
+                }
+                Err(e) => outcomes.push(Some(Err(TaskError::Fatal(e)))),
             }
         }
+        let mut sent = if emails.is_empty() {
+            Vec::new()
+        } else {
+            self.email_client
+                .send_batch(&emails)
+                .await
+                .map_err(anyhow::Error::from)?
+        }
+        .into_iter();
+        outcomes
+            .into_iter()
+            .map(|outcome| match outcome {
+                Some(outcome) => Ok(outcome),
+                None => sent
+                    .next()
+                    .map(|result| result.map_err(TaskError::from))
+                    .ok_or_else(|| TaskError::Transient(anyhow!("Missing a batch result"))),
+            })
+            .collect()
+    }
+
+    async fn complete(
+        &self,
+        transaction: &mut PgTransaction<'_>,
+        task: &NewsletterDeliveryTask,
+        receipt: EmailReceipt,
+    ) -> Result<(), anyhow::Error> {
+        record_newsletter_delivery(
+            transaction,
+            task,
+            DeliveryStatus::Sent,
+            receipt.message_id.as_deref(),
+            None,
+            None,
+        )
+        .await?;
+        delete_newsletter_delivery_task(transaction, task.newsletter_issue_id, task.subscriber_id)
+            .await
+    }
+
+    async fn retry(
+        &self,
+        transaction: &mut PgTransaction<'_>,
+        task: &NewsletterDeliveryTask,
+        error: &anyhow::Error,
+        backoff: Duration,
+    ) -> Result<(), anyhow::Error> {
+        record_newsletter_delivery(
+            transaction,
+            task,
+            DeliveryStatus::Retrying,
+            None,
+            provider_error_code(error),
+            Some(&error.to_string()),
+        )
+        .await?;
+        retry_newsletter_delivery_task(transaction, task, backoff).await
     }
+
+    async fn dead_letter(
+        &self,
+        transaction: &mut PgTransaction<'_>,
+        task: &NewsletterDeliveryTask,
+        error: &anyhow::Error,
+    ) -> Result<(), anyhow::Error> {
+        let last_error = error.to_string();
+        record_newsletter_delivery(
+            transaction,
+            task,
+            DeliveryStatus::Failed,
+            None,
+            provider_error_code(error),
+            Some(&last_error),
+        )
+        .await?;
+        dead_letter_newsletter_delivery_task(transaction, task, &last_error).await
+    }
+}
+
+fn provider_error_code(error: &anyhow::Error) -> Option<i32> {
+    error
+        .downcast_ref::<EmailError>()
+        .and_then(EmailError::error_code)
+}
+
+pub fn personalized_email(
+    recipient: SubscriberEmail,
+    issue: &NewsletterIssue,
+    base_url: &str,
+    subscriber_id: Uuid,
+    subscriber_name: &str,
+    hmac_secret: &Secret<String>,
+) -> Result<OutgoingEmail, RenderError> {
+    let newsletter_issue = personalize_newsletter(
+        issue,
+        base_url,
+        subscriber_id,
+        subscriber_name,
+        recipient.as_ref(),
+        hmac_secret,
+    )?;
+    let headers = list_unsubscribe_headers(&one_click_unsubscribe_link(
+        base_url,
+        subscriber_id,
+        hmac_secret,
+    ));
+    Ok(OutgoingEmail {
+        recipient,
+        newsletter_issue,
+        headers: headers.into(),
+    })
+}
+
+pub fn personalize_newsletter(
+    issue: &NewsletterIssue,
+    base_url: &str,
+    subscriber_id: Uuid,
+    subscriber_name: &str,
+    subscriber_email: &str,
+    hmac_secret: &Secret<String>,
+) -> Result<NewsletterIssue, RenderError> {
+    let unsubscribe_url = unsubscribe_link(base_url, subscriber_id, hmac_secret);
+    let merge_tags = MergeTags {
+        name: subscriber_name,
+        email: subscriber_email,
+        unsubscribe_url: &unsubscribe_url,
+    };
+    Ok(issue.with_merge_tags(&merge_tags)?.with_subscription_links(
+        &unsubscribe_url,
+        &preferences_link(base_url, subscriber_id, hmac_secret),
+    ))
+}
+
+fn list_unsubscribe_headers(one_click_link: &str) -> [EmailHeader; 2] {
+    [
+        EmailHeader::new("List-Unsubscribe", format!("<{}>", one_click_link)),
+        EmailHeader::new("List-Unsubscribe-Post", "List-Unsubscribe=One-Click"),
+    ]
 }
 
-pub async fn run_worker_until_stopped(configuration: Settings) -> Result<(), anyhow::Error> {
-    let connection_pool = get_connection_pool(&configuration.database);
-    let email_client = configuration.email_client.client();
-    worker_loop(connection_pool, email_client).await
+pub async fn run_worker_until_stopped(
+    configuration: Settings,
+    shutdown: CancellationToken,
+) -> Result<(), anyhow::Error> {
+    let options = configuration.workers.newsletter_delivery.options();
+    let connection_pool = get_worker_connection_pool(&configuration.database, options.concurrency);
+    let task = IssueDelivery {
+        email_client: configuration.email_client.client(),
+        base_url: ApplicationBaseUrl(configuration.application.base_url),
+        hmac_secret: HmacSecret(configuration.application.hmac_secret),
+    };
+    task_queue::run_until_stopped(connection_pool, task, options, shutdown).await
 }
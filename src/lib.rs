@@ -1,15 +1,20 @@
 pub mod authentication;
+pub mod cli;
 pub mod configuration;
 pub mod domain;
 pub mod email_client;
 pub mod idempotency;
 pub mod issue_delivery_worker;
+pub mod newsletter_scheduler;
 pub mod paths;
+pub mod pending_subscription_cleanup;
 pub mod persistence;
+pub mod retry_policy;
 pub mod routes;
 pub mod session_state;
 pub mod startup;
 pub mod subscription_confirmation_delivery_worker;
+pub mod task_queue;
 pub mod telemetry;
 pub mod templates;
 pub mod utils;
@@ -1,35 +1,149 @@
 use std::fmt::{Debug, Display};
-use tokio::task::JoinError;
+use std::future::Future;
+use tokio::task::{JoinError, JoinHandle};
+use tokio_util::sync::CancellationToken;
+use zero2prod::cli::Role;
 use zero2prod::configuration::get_configuration;
 use zero2prod::startup::Application;
 use zero2prod::telemetry::{get_subscriber, init_subscriber};
-use zero2prod::{issue_delivery_worker, subscription_confirmation_delivery_worker};
+use zero2prod::{
+    issue_delivery_worker, newsletter_scheduler, pending_subscription_cleanup,
+    subscription_confirmation_delivery_worker,
+};
 
 #[tokio::main]
 async fn main() -> Result<(), anyhow::Error> {
+    let role = match Role::try_from(std::env::args().skip(1).collect::<Vec<_>>()) {
+        Ok(role) => role,
+        Err(e) => {
+            eprintln!("{}", e);
+            std::process::exit(2);
+        }
+    };
     let subscriber = get_subscriber("zero2prod".into(), "info".into(), std::io::stdout);
     init_subscriber(subscriber);
     let configuration = get_configuration().expect("Could not get config.");
-    let application = Application::build(configuration.clone()).await?;
-    let application_task = tokio::spawn(application.run_until_stopped());
-    let issue_delivery_worker_task = tokio::spawn(issue_delivery_worker::run_worker_until_stopped(
-        configuration.clone(),
-    ));
-    let confirmation_delivery_worker_task = tokio::spawn(
-        subscription_confirmation_delivery_worker::run_worker_until_stopped(configuration.clone()),
-    );
+    let drain_timeout = configuration.application.drain_timeout();
+    let shutdown = CancellationToken::new();
+    tokio::spawn(cancel_on_shutdown_signal(shutdown.clone()));
 
+    let mut tasks = Vec::new();
+    if role.runs_api() {
+        let application = Application::build(configuration.clone()).await?;
+        tasks.push(spawn_role(
+            "API",
+            application.run_until_stopped(shutdown.clone()),
+            shutdown.clone(),
+        ));
+    }
+    if role.runs_newsletter_worker() {
+        tasks.push(spawn_role(
+            "Newsletter delivery worker",
+            issue_delivery_worker::run_worker_until_stopped(
+                configuration.clone(),
+                shutdown.clone(),
+            ),
+            shutdown.clone(),
+        ));
+        tasks.push(spawn_role(
+            "Newsletter scheduler",
+            newsletter_scheduler::run_scheduler_until_stopped(
+                configuration.clone(),
+                shutdown.clone(),
+            ),
+            shutdown.clone(),
+        ));
+    }
+    if role.runs_confirmation_worker() {
+        tasks.push(spawn_role(
+            "Confirmation delivery worker",
+            subscription_confirmation_delivery_worker::run_worker_until_stopped(
+                configuration.clone(),
+                shutdown.clone(),
+            ),
+            shutdown.clone(),
+        ));
+        tasks.push(spawn_role(
+            "Pending subscription cleanup",
+            pending_subscription_cleanup::run_cleanup_until_stopped(
+                configuration.clone(),
+                shutdown.clone(),
+            ),
+            shutdown.clone(),
+        ));
+    }
+
+    let all_stopped = async {
+        for task in tasks {
+            let _ = task.await;
+        }
+    };
+    let drain_expired = async {
+        shutdown.cancelled().await;
+        tokio::time::sleep(drain_timeout).await;
+    };
     tokio::select! {
-        o = application_task => report_exit("API", o),
-        o = issue_delivery_worker_task => report_exit("Newsletter delivery worker", o),
-        o = confirmation_delivery_worker_task => report_exit("Confirmation delivery worker", o),
+        _ = all_stopped => {}
+        _ = drain_expired => {
+            tracing::warn!("Timed out after {:?} waiting for tasks to stop", drain_timeout);
+        }
     }
 
     Ok(())
 }
 
-fn report_exit(task_name: &str, outcome: Result<Result<(), impl Debug + Display>, JoinError>) {
+fn spawn_role<F, E>(task_name: &'static str, role: F, shutdown: CancellationToken) -> JoinHandle<()>
+where
+    F: Future<Output = Result<(), E>> + Send + 'static,
+    E: Debug + Display + Send + 'static,
+{
+    let task = tokio::spawn(role);
+    tokio::spawn(async move { wait_for_exit(task_name, task, &shutdown).await })
+}
+
+async fn cancel_on_shutdown_signal(shutdown: CancellationToken) {
+    let ctrl_c = async {
+        tokio::signal::ctrl_c()
+            .await
+            .expect("Failed to install the Ctrl+C handler");
+    };
+    #[cfg(unix)]
+    let terminate = async {
+        tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate())
+            .expect("Failed to install the SIGTERM handler")
+            .recv()
+            .await;
+    };
+    #[cfg(not(unix))]
+    let terminate = std::future::pending::<()>();
+
+    tokio::select! {
+        _ = ctrl_c => {}
+        _ = terminate => {}
+    }
+    tracing::info!("Shutdown signal received, draining");
+    shutdown.cancel();
+}
+
+async fn wait_for_exit<E: Debug + Display>(
+    task_name: &str,
+    task: JoinHandle<Result<(), E>>,
+    shutdown: &CancellationToken,
+) {
+    let outcome = task.await;
+    report_exit(task_name, outcome, shutdown.is_cancelled());
+    shutdown.cancel();
+}
+
+fn report_exit(
+    task_name: &str,
+    outcome: Result<Result<(), impl Debug + Display>, JoinError>,
+    shutdown_requested: bool,
+) {
     match outcome {
+        Ok(Ok(())) if shutdown_requested => {
+            tracing::info!("{} has stopped cleanly", task_name)
+        }
         Ok(Ok(())) => {
             tracing::info!("{} has exited", task_name)
         }
@@ -0,0 +1,169 @@
+use rand::Rng;
+use std::time::Duration;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Latency {
+    Fixed(u64),
+    Random { min: u64, max: u64 },
+}
+
+impl Latency {
+    pub fn parse(s: &str) -> Result<Self, String> {
+        let parse_ms = |s: &str| {
+            s.trim()
+                .parse::<u64>()
+                .map_err(|_| format!("{} is not a number of milliseconds", s))
+        };
+        match s.split_once('-') {
+            Some((min, max)) => {
+                let (min, max) = (parse_ms(min)?, parse_ms(max)?);
+                if min > max {
+                    return Err(format!("{} is not a valid latency range", s));
+                }
+                Ok(Latency::Random { min, max })
+            }
+            None => Ok(Latency::Fixed(parse_ms(s)?)),
+        }
+    }
+
+    fn sample(&self) -> Duration {
+        match self {
+            Latency::Fixed(ms) => Duration::from_millis(*ms),
+            Latency::Random { min, max } => {
+                Duration::from_millis(rand::thread_rng().gen_range(*min..=*max))
+            }
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub struct Failure {
+    pub status: u16,
+    pub error_code: u16,
+    pub message: String,
+}
+
+#[derive(Debug, Clone)]
+pub struct FaultInjection {
+    pub failure_rate: f64,
+    pub failure_statuses: Vec<u16>,
+    pub latency: Option<Latency>,
+    pub always_fail_recipients: Vec<String>,
+}
+
+impl Default for FaultInjection {
+    fn default() -> Self {
+        Self {
+            failure_rate: 0.0,
+            failure_statuses: vec![500],
+            latency: None,
+            always_fail_recipients: vec![],
+        }
+    }
+}
+
+impl FaultInjection {
+    pub async fn delay(&self) {
+        if let Some(latency) = &self.latency {
+            tokio::time::sleep(latency.sample()).await;
+        }
+    }
+
+    pub fn failure_for(&self, recipient: &str) -> Option<Failure> {
+        self.recipient_failure(recipient)
+            .or_else(|| self.random_failure())
+    }
+
+    pub fn recipient_failure(&self, recipient: &str) -> Option<Failure> {
+        if self
+            .always_fail_recipients
+            .iter()
+            .any(|r| r.eq_ignore_ascii_case(recipient))
+        {
+            return Some(Failure {
+                status: 422,
+                error_code: 406,
+                message: "You tried to send to a recipient that has been marked as inactive."
+                    .into(),
+            });
+        }
+        None
+    }
+
+    pub fn random_failure(&self) -> Option<Failure> {
+        let mut rng = rand::thread_rng();
+        if self.failure_rate > 0.0 && rng.gen::<f64>() < self.failure_rate {
+            let status = self.failure_statuses[rng.gen_range(0..self.failure_statuses.len())];
+            return Some(failure_with_status(status));
+        }
+        None
+    }
+}
+
+fn failure_with_status(status: u16) -> Failure {
+    let (error_code, message) = match status {
+        401 => (10, "Bad or missing API token"),
+        422 => (300, "Invalid email request"),
+        429 => (429, "Rate limit exceeded"),
+        _ => (status, "Injected failure"),
+    };
+    Failure {
+        status,
+        error_code,
+        message: message.into(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{FaultInjection, Latency};
+    use claims::{assert_err, assert_none, assert_ok_eq, assert_some_eq};
+
+    #[test]
+    fn latency_can_be_fixed_or_a_range() {
+        assert_ok_eq!(Latency::parse("250"), Latency::Fixed(250));
+        assert_ok_eq!(
+            Latency::parse("100-2000"),
+            Latency::Random {
+                min: 100,
+                max: 2000
+            }
+        );
+        assert_err!(Latency::parse("2000-100"));
+        assert_err!(Latency::parse("fast"));
+    }
+
+    #[test]
+    fn always_fail_recipients_are_rejected_as_inactive() {
+        let faults = FaultInjection {
+            always_fail_recipients: vec!["bounce@example.com".into()],
+            ..FaultInjection::default()
+        };
+
+        let failure = faults.failure_for("Bounce@example.com").unwrap();
+        assert_eq!(failure.status, 422);
+        assert_eq!(failure.error_code, 406);
+        assert_none!(faults.failure_for("someone@example.com"));
+    }
+
+    #[test]
+    fn a_failure_rate_of_one_always_fails_with_a_configured_status() {
+        let faults = FaultInjection {
+            failure_rate: 1.0,
+            failure_statuses: vec![503],
+            ..FaultInjection::default()
+        };
+
+        for _ in 0..10 {
+            assert_some_eq!(faults.failure_for("a@example.com").map(|f| f.status), 503);
+        }
+    }
+
+    #[test]
+    fn no_faults_are_injected_by_default() {
+        let faults = FaultInjection::default();
+        for _ in 0..10 {
+            assert_none!(faults.failure_for("a@example.com"));
+        }
+    }
+}
@@ -0,0 +1,160 @@
+use chrono::Utc;
+use std::ffi::OsStr;
+use std::path::PathBuf;
+use std::sync::RwLock;
+use uuid::Uuid;
+
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct SendEmailRequest {
+    pub from: String,
+    pub to: String,
+    pub subject: String,
+    #[serde(default)]
+    pub html_body: String,
+    #[serde(default)]
+    pub text_body: String,
+    #[serde(default)]
+    pub headers: Vec<EmailHeader>,
+}
+
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct EmailHeader {
+    pub name: String,
+    pub value: String,
+}
+
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct StoredMessage {
+    pub id: Uuid,
+    pub received_at: String,
+    #[serde(flatten)]
+    pub email: SendEmailRequest,
+}
+
+pub struct Inbox {
+    messages: RwLock<Vec<StoredMessage>>,
+    directory: Option<PathBuf>,
+}
+
+impl Inbox {
+    pub fn in_memory() -> Self {
+        Self {
+            messages: RwLock::new(Vec::new()),
+            directory: None,
+        }
+    }
+
+    pub fn on_disk(directory: PathBuf) -> Result<Self, anyhow::Error> {
+        std::fs::create_dir_all(&directory)?;
+        let mut messages = Vec::new();
+        for entry in std::fs::read_dir(&directory)? {
+            let path = entry?.path();
+            if path.extension() == Some(OsStr::new("json")) {
+                let message: StoredMessage = serde_json::from_slice(&std::fs::read(&path)?)?;
+                messages.push(message);
+            }
+        }
+        messages.sort_by(|a, b| a.received_at.cmp(&b.received_at));
+        Ok(Self {
+            messages: RwLock::new(messages),
+            directory: Some(directory),
+        })
+    }
+
+    pub fn store(&self, email: SendEmailRequest) -> Result<StoredMessage, anyhow::Error> {
+        let message = StoredMessage {
+            id: Uuid::new_v4(),
+            received_at: Utc::now().to_rfc3339(),
+            email,
+        };
+        if let Some(directory) = &self.directory {
+            std::fs::write(
+                directory.join(format!("{}.json", message.id)),
+                serde_json::to_vec_pretty(&message)?,
+            )?;
+        }
+        self.messages.write().unwrap().push(message.clone());
+        Ok(message)
+    }
+
+    pub fn list(&self, to: Option<&str>) -> Vec<StoredMessage> {
+        self.messages
+            .read()
+            .unwrap()
+            .iter()
+            .rev()
+            .filter(|m| match to {
+                Some(to) => m.email.to.eq_ignore_ascii_case(to),
+                None => true,
+            })
+            .cloned()
+            .collect()
+    }
+
+    pub fn get(&self, id: Uuid) -> Option<StoredMessage> {
+        self.messages
+            .read()
+            .unwrap()
+            .iter()
+            .find(|m| m.id == id)
+            .cloned()
+    }
+
+    pub fn clear(&self) -> Result<(), anyhow::Error> {
+        let mut messages = self.messages.write().unwrap();
+        if let Some(directory) = &self.directory {
+            for message in messages.iter() {
+                std::fs::remove_file(directory.join(format!("{}.json", message.id)))?;
+            }
+        }
+        messages.clear();
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Inbox, SendEmailRequest};
+
+    fn email(to: &str) -> SendEmailRequest {
+        SendEmailRequest {
+            from: "sender@example.com".into(),
+            to: to.into(),
+            subject: "Subject".into(),
+            html_body: "<p>Html</p>".into(),
+            text_body: "Text".into(),
+            headers: vec![],
+        }
+    }
+
+    #[test]
+    fn messages_can_be_filtered_by_recipient() {
+        let inbox = Inbox::in_memory();
+        inbox.store(email("a@example.com")).unwrap();
+        inbox.store(email("b@example.com")).unwrap();
+
+        assert_eq!(inbox.list(None).len(), 2);
+        let for_a = inbox.list(Some("A@example.com"));
+        assert_eq!(for_a.len(), 1);
+        assert_eq!(for_a[0].email.to, "a@example.com");
+    }
+
+    #[test]
+    fn messages_on_disk_survive_a_restart_until_cleared() {
+        let directory = std::env::temp_dir().join(uuid::Uuid::new_v4().to_string());
+        let inbox = Inbox::on_disk(directory.clone()).unwrap();
+        let stored = inbox.store(email("a@example.com")).unwrap();
+
+        let reopened = Inbox::on_disk(directory.clone()).unwrap();
+        assert_eq!(reopened.get(stored.id).unwrap().email.to, "a@example.com");
+
+        reopened.clear().unwrap();
+        assert!(Inbox::on_disk(directory.clone())
+            .unwrap()
+            .list(None)
+            .is_empty());
+        std::fs::remove_dir_all(directory).unwrap();
+    }
+}
@@ -0,0 +1,72 @@
+mod faults;
+mod inbox;
+mod options;
+mod routes;
+mod webhooks;
+
+use actix_web::{web, App, HttpServer};
+use inbox::Inbox;
+use options::Options;
+use routes::{
+    delete_messages, fire_webhook, get_message, inbox_page, list_messages, message_html_body,
+    message_page, receive_batch, receive_email,
+};
+use std::net::TcpListener;
+use tracing_actix_web::TracingLogger;
+use webhooks::Webhooks;
+use zero2prod::telemetry::{get_subscriber, init_subscriber};
+
+fn configure_routes(config: &mut web::ServiceConfig) {
+    config
+        .route("/email", web::post().to(receive_email))
+        .route("/email/batch", web::post().to(receive_batch))
+        .route("/messages", web::get().to(list_messages))
+        .route("/messages", web::delete().to(delete_messages))
+        .route("/messages/{id}", web::get().to(get_message))
+        .route(
+            "/messages/{id}/webhooks/{kind}",
+            web::post().to(fire_webhook),
+        )
+        .route("/inbox", web::get().to(inbox_page))
+        .route("/inbox/{id}", web::get().to(message_page))
+        .route("/inbox/{id}/html", web::get().to(message_html_body));
+}
+
+#[tokio::main]
+async fn main() -> Result<(), anyhow::Error> {
+    let options = match Options::try_from(std::env::args().skip(1).collect::<Vec<_>>()) {
+        Ok(options) => options,
+        Err(e) => {
+            eprintln!("{}", e);
+            std::process::exit(2);
+        }
+    };
+    let subscriber = get_subscriber("mock-email-server".into(), "trace".into(), std::io::stdout);
+    init_subscriber(subscriber);
+
+    let inbox = match options.storage_dir {
+        Some(directory) => Inbox::on_disk(directory)?,
+        None => Inbox::in_memory(),
+    };
+    let inbox = web::Data::new(inbox);
+    let faults = web::Data::new(options.faults);
+    let webhooks = web::Data::new(Webhooks::new(
+        options.webhook_url,
+        options.webhook_username,
+        options.webhook_password,
+    ));
+    let address = format!("{}:{}", "localhost", options.port);
+    let listener = TcpListener::bind(address)?;
+    HttpServer::new(move || {
+        App::new()
+            .wrap(TracingLogger::default())
+            .app_data(inbox.clone())
+            .app_data(faults.clone())
+            .app_data(webhooks.clone())
+            .configure(configure_routes)
+    })
+    .listen(listener)?
+    .run()
+    .await?;
+    Ok(())
+}
@@ -0,0 +1,141 @@
+use crate::faults::{FaultInjection, Latency};
+use std::path::PathBuf;
+
+pub const USAGE: &str = "Usage: mock-email-server [--port PORT] [--storage-dir DIR] \
+[--failure-rate RATE] [--failure-status CODE,...] [--latency-ms MS|MIN-MAX] \
+[--always-fail EMAIL,...] [--webhook-url URL] [--webhook-username USER] \
+[--webhook-password PASSWORD]";
+
+#[derive(Debug)]
+pub struct Options {
+    pub port: u16,
+    pub storage_dir: Option<PathBuf>,
+    pub faults: FaultInjection,
+    pub webhook_url: Option<String>,
+    pub webhook_username: Option<String>,
+    pub webhook_password: Option<String>,
+}
+
+impl Default for Options {
+    fn default() -> Self {
+        Self {
+            port: 8008,
+            storage_dir: None,
+            faults: FaultInjection::default(),
+            webhook_url: None,
+            webhook_username: None,
+            webhook_password: None,
+        }
+    }
+}
+
+impl TryFrom<Vec<String>> for Options {
+    type Error = String;
+
+    fn try_from(args: Vec<String>) -> Result<Self, Self::Error> {
+        let mut options = Options::default();
+        let mut args = args.into_iter();
+        while let Some(flag) = args.next() {
+            let value = args
+                .next()
+                .ok_or_else(|| format!("{} expects a value.\n{}", flag, USAGE))?;
+            match flag.as_str() {
+                "--port" => {
+                    options.port = value
+                        .parse()
+                        .map_err(|_| format!("{} is not a valid port", value))?;
+                }
+                "--storage-dir" => options.storage_dir = Some(value.into()),
+                "--failure-rate" => {
+                    options.faults.failure_rate = value
+                        .parse()
+                        .ok()
+                        .filter(|rate| (0.0..=1.0).contains(rate))
+                        .ok_or_else(|| format!("{} is not a rate between 0 and 1", value))?;
+                }
+                "--failure-status" => {
+                    options.faults.failure_statuses = value
+                        .split(',')
+                        .map(|s| {
+                            s.trim()
+                                .parse::<u16>()
+                                .ok()
+                                .filter(|s| (400..600).contains(s))
+                                .ok_or_else(|| format!("{} is not an error status code", s))
+                        })
+                        .collect::<Result<_, _>>()?;
+                }
+                "--latency-ms" => options.faults.latency = Some(Latency::parse(&value)?),
+                "--always-fail" => {
+                    options.faults.always_fail_recipients =
+                        value.split(',').map(|s| s.trim().to_owned()).collect();
+                }
+                "--webhook-url" => options.webhook_url = Some(value),
+                "--webhook-username" => options.webhook_username = Some(value),
+                "--webhook-password" => options.webhook_password = Some(value),
+                _ => return Err(format!("Unknown option {}.\n{}", flag, USAGE)),
+            }
+        }
+        Ok(options)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Options;
+    use crate::faults::Latency;
+    use claims::assert_err;
+
+    fn parse(args: &[&str]) -> Result<Options, String> {
+        Options::try_from(args.iter().map(|a| a.to_string()).collect::<Vec<_>>())
+    }
+
+    #[test]
+    fn all_options_are_parsed() {
+        let options = parse(&[
+            "--port",
+            "9000",
+            "--failure-rate",
+            "0.25",
+            "--failure-status",
+            "500,503",
+            "--latency-ms",
+            "10-20",
+            "--always-fail",
+            "a@example.com, b@example.com",
+            "--webhook-url",
+            "http://localhost:8000/webhooks/email/bounce",
+            "--webhook-username",
+            "postmark",
+            "--webhook-password",
+            "secret",
+        ])
+        .unwrap();
+
+        assert_eq!(options.port, 9000);
+        assert_eq!(options.faults.failure_rate, 0.25);
+        assert_eq!(options.faults.failure_statuses, vec![500, 503]);
+        assert_eq!(
+            options.faults.latency,
+            Some(Latency::Random { min: 10, max: 20 })
+        );
+        assert_eq!(
+            options.faults.always_fail_recipients,
+            vec!["a@example.com", "b@example.com"]
+        );
+        assert_eq!(
+            options.webhook_url.as_deref(),
+            Some("http://localhost:8000/webhooks/email/bounce")
+        );
+        assert_eq!(options.webhook_username.as_deref(), Some("postmark"));
+        assert_eq!(options.webhook_password.as_deref(), Some("secret"));
+    }
+
+    #[test]
+    fn invalid_options_are_rejected() {
+        assert_err!(parse(&["--failure-rate", "2"]));
+        assert_err!(parse(&["--failure-status", "200"]));
+        assert_err!(parse(&["--port"]));
+        assert_err!(parse(&["--verbose", "yes"]));
+    }
+}
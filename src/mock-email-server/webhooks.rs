@@ -0,0 +1,103 @@
+use crate::inbox::StoredMessage;
+use chrono::Utc;
+use rand::Rng;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WebhookKind {
+    HardBounce,
+    SoftBounce,
+    SpamComplaint,
+}
+
+impl WebhookKind {
+    pub fn parse(s: &str) -> Option<Self> {
+        match s {
+            "bounce" => Some(WebhookKind::HardBounce),
+            "soft-bounce" => Some(WebhookKind::SoftBounce),
+            "spam-complaint" => Some(WebhookKind::SpamComplaint),
+            _ => None,
+        }
+    }
+
+    pub fn payload(&self, message: &StoredMessage) -> serde_json::Value {
+        let (record_type, bounce_type, type_code, inactive, description) = match self {
+            WebhookKind::HardBounce => (
+                "Bounce",
+                "HardBounce",
+                1,
+                true,
+                "The server was unable to deliver your message (ex: unknown user, mailbox not found).",
+            ),
+            WebhookKind::SoftBounce => (
+                "Bounce",
+                "SoftBounce",
+                4096,
+                false,
+                "The server could not temporarily deliver your message (ex: Message is delayed due to network troubles).",
+            ),
+            WebhookKind::SpamComplaint => (
+                "SpamComplaint",
+                "SpamComplaint",
+                512,
+                true,
+                "The subscriber explicitly marked this email as spam.",
+            ),
+        };
+        serde_json::json!({
+            "RecordType": record_type,
+            "ID": rand::thread_rng().gen::<u32>(),
+            "Type": bounce_type,
+            "TypeCode": type_code,
+            "MessageID": message.id,
+            "Email": message.email.to,
+            "From": message.email.from,
+            "Subject": message.email.subject,
+            "BouncedAt": Utc::now().to_rfc3339(),
+            "Description": description,
+            "Details": "Fired by the mock email server",
+            "Inactive": inactive,
+            "CanActivate": true,
+        })
+    }
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum WebhookError {
+    #[error("No webhook url was configured, start the server with --webhook-url")]
+    NotConfigured,
+    #[error("The webhook was rejected with status {0}")]
+    Rejected(reqwest::StatusCode),
+    #[error("Failed to deliver the webhook")]
+    Unreachable(#[from] reqwest::Error),
+}
+
+#[derive(Debug, Default)]
+pub struct Webhooks {
+    url: Option<String>,
+    credentials: Option<(String, String)>,
+    client: reqwest::Client,
+}
+
+impl Webhooks {
+    pub fn new(url: Option<String>, username: Option<String>, password: Option<String>) -> Self {
+        Self {
+            url,
+            credentials: username.map(|username| (username, password.unwrap_or_default())),
+            client: reqwest::Client::new(),
+        }
+    }
+
+    pub async fn fire(&self, payload: &serde_json::Value) -> Result<(), WebhookError> {
+        let url = self.url.as_ref().ok_or(WebhookError::NotConfigured)?;
+        let mut request = self.client.post(url).json(payload);
+        if let Some((username, password)) = &self.credentials {
+            request = request.basic_auth(username, Some(password));
+        }
+        let response = request.send().await?;
+        if response.status().is_success() {
+            Ok(())
+        } else {
+            Err(WebhookError::Rejected(response.status()))
+        }
+    }
+}
@@ -0,0 +1,61 @@
+use crate::{
+    configuration::Settings,
+    persistence::{enqueue_newsletter_delivery_tasks, publish_due_newsletter_issues},
+    startup::get_connection_pool,
+};
+use anyhow::Context;
+use sqlx::PgPool;
+use std::time::Duration;
+use tokio_util::sync::CancellationToken;
+
+#[tracing::instrument(skip_all, fields(n_published = tracing::field::Empty))]
+pub async fn publish_due_newsletters(pool: &PgPool) -> Result<usize, anyhow::Error> {
+    let mut transaction = pool
+        .begin()
+        .await
+        .context("Failed to acquire a Postgres connection from the pool")?;
+    let newsletter_issue_ids = publish_due_newsletter_issues(&mut transaction)
+        .await
+        .context("Failed to mark due newsletter issues as published")?;
+    for newsletter_issue_id in &newsletter_issue_ids {
+        enqueue_newsletter_delivery_tasks(&mut transaction, *newsletter_issue_id)
+            .await
+            .context("Failed to enqueue delivery tasks")?;
+    }
+    transaction
+        .commit()
+        .await
+        .context("Failed to commit the published newsletter issues")?;
+    tracing::Span::current().record("n_published", newsletter_issue_ids.len());
+    Ok(newsletter_issue_ids.len())
+}
+
+async fn scheduler_loop(pool: PgPool, poll_interval: Duration, shutdown: CancellationToken) {
+    while !shutdown.is_cancelled() {
+        if let Err(e) = publish_due_newsletters(&pool).await {
+            tracing::error!(
+                error.cause_chain = ?e,
+                error.message = %e,
+                "Failed to publish scheduled newsletters"
+            );
+        }
+        tokio::select! {
+            _ = tokio::time::sleep(poll_interval) => {}
+            _ = shutdown.cancelled() => {}
+        }
+    }
+}
+
+pub async fn run_scheduler_until_stopped(
+    configuration: Settings,
+    shutdown: CancellationToken,
+) -> Result<(), anyhow::Error> {
+    let connection_pool = get_connection_pool(&configuration.database);
+    scheduler_loop(
+        connection_pool,
+        configuration.workers.newsletter_scheduler.poll_interval(),
+        shutdown,
+    )
+    .await;
+    Ok(())
+}
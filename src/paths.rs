@@ -1,9 +1,23 @@
 pub enum Path {
     AdminDashboard,
     AdminNewsletters,
+    AdminScheduledNewsletters,
+    AdminNewsletterDrafts,
+    AdminNewsletterPreview,
+    AdminPublishNewsletterDraft,
+    AdminDeleteNewsletterDraft,
+    AdminLists,
+    AdminRescheduleNewsletter,
+    AdminCancelScheduledNewsletter,
     AdminPassword,
+    AdminEmail,
     AdminLogout,
+    AdminFailedDeliveries,
+    AdminRequeueNewsletterDeliveries,
+    AdminRequeueConfirmationDelivery,
     Login,
+    Preferences,
+    Unsubscribe,
 }
 
 impl TryFrom<&str> for Path {
@@ -13,9 +27,23 @@ impl TryFrom<&str> for Path {
         match value {
             "admin_dashboard" => Ok(Path::AdminDashboard),
             "admin_newsletter" => Ok(Path::AdminNewsletters),
+            "admin_scheduled_newsletters" => Ok(Path::AdminScheduledNewsletters),
+            "admin_newsletter_drafts" => Ok(Path::AdminNewsletterDrafts),
+            "admin_newsletter_preview" => Ok(Path::AdminNewsletterPreview),
+            "admin_publish_newsletter_draft" => Ok(Path::AdminPublishNewsletterDraft),
+            "admin_delete_newsletter_draft" => Ok(Path::AdminDeleteNewsletterDraft),
+            "admin_lists" => Ok(Path::AdminLists),
+            "admin_reschedule_newsletter" => Ok(Path::AdminRescheduleNewsletter),
+            "admin_cancel_scheduled_newsletter" => Ok(Path::AdminCancelScheduledNewsletter),
             "admin_password" => Ok(Path::AdminPassword),
+            "admin_email" => Ok(Path::AdminEmail),
             "admin_logout" => Ok(Path::AdminLogout),
+            "admin_failed_deliveries" => Ok(Path::AdminFailedDeliveries),
+            "admin_requeue_newsletter_deliveries" => Ok(Path::AdminRequeueNewsletterDeliveries),
+            "admin_requeue_confirmation_delivery" => Ok(Path::AdminRequeueConfirmationDelivery),
             "login" => Ok(Path::Login),
+            "preferences" => Ok(Path::Preferences),
+            "unsubscribe" => Ok(Path::Unsubscribe),
             _ => Err(anyhow::anyhow!("bad path")),
         }
     }
@@ -25,9 +53,23 @@ pub fn path_uri(path: Path) -> &'static str {
     match path {
         Path::AdminDashboard => "/admin/dashboard",
         Path::AdminNewsletters => "/admin/newsletters",
+        Path::AdminScheduledNewsletters => "/admin/newsletters/scheduled",
+        Path::AdminNewsletterDrafts => "/admin/newsletters/drafts",
+        Path::AdminNewsletterPreview => "/admin/newsletters/preview",
+        Path::AdminPublishNewsletterDraft => "/admin/newsletters/drafts/publish",
+        Path::AdminDeleteNewsletterDraft => "/admin/newsletters/drafts/delete",
+        Path::AdminLists => "/admin/lists",
+        Path::AdminRescheduleNewsletter => "/admin/newsletters/scheduled/reschedule",
+        Path::AdminCancelScheduledNewsletter => "/admin/newsletters/scheduled/cancel",
         Path::AdminPassword => "/admin/password",
+        Path::AdminEmail => "/admin/email",
         Path::AdminLogout => "/admin/logout",
+        Path::AdminFailedDeliveries => "/admin/deliveries/failed",
+        Path::AdminRequeueNewsletterDeliveries => "/admin/deliveries/failed/newsletters/requeue",
+        Path::AdminRequeueConfirmationDelivery => "/admin/deliveries/failed/confirmations/requeue",
         Path::Login => "/login",
+        Path::Preferences => "/subscriptions/preferences",
+        Path::Unsubscribe => "/subscriptions/unsubscribe",
     }
 }
 
@@ -0,0 +1,59 @@
+use crate::{
+    configuration::Settings, persistence::purge_stale_pending_subscriptions,
+    startup::get_connection_pool,
+};
+use anyhow::Context;
+use sqlx::PgPool;
+use std::time::Duration;
+use tokio_util::sync::CancellationToken;
+
+#[tracing::instrument(skip_all, fields(n_purged = tracing::field::Empty))]
+pub async fn purge_stale_subscriptions(
+    pool: &PgPool,
+    max_age: Duration,
+) -> Result<usize, anyhow::Error> {
+    let n_purged = purge_stale_pending_subscriptions(pool, max_age)
+        .await
+        .context("Failed to purge stale pending subscriptions")?;
+    tracing::Span::current().record("n_purged", n_purged);
+    Ok(n_purged)
+}
+
+async fn cleanup_loop(
+    pool: PgPool,
+    max_age: Duration,
+    poll_interval: Duration,
+    shutdown: CancellationToken,
+) {
+    while !shutdown.is_cancelled() {
+        if let Err(e) = purge_stale_subscriptions(&pool, max_age).await {
+            tracing::error!(
+                error.cause_chain = ?e,
+                error.message = %e,
+                "Failed to purge stale pending subscriptions"
+            );
+        }
+        tokio::select! {
+            _ = tokio::time::sleep(poll_interval) => {}
+            _ = shutdown.cancelled() => {}
+        }
+    }
+}
+
+pub async fn run_cleanup_until_stopped(
+    configuration: Settings,
+    shutdown: CancellationToken,
+) -> Result<(), anyhow::Error> {
+    let connection_pool = get_connection_pool(&configuration.database);
+    cleanup_loop(
+        connection_pool,
+        configuration.subscriptions.confirmation_token_expiry(),
+        configuration
+            .workers
+            .pending_subscription_cleanup
+            .poll_interval(),
+        shutdown,
+    )
+    .await;
+    Ok(())
+}
@@ -1,4 +1,6 @@
-use sqlx::{Postgres, Transaction};
+use crate::domain::new_subscriber::hash_confirmation_token;
+use chrono::{DateTime, Utc};
+use sqlx::{PgPool, Postgres, Transaction};
 use uuid::Uuid;
 
 #[derive(Debug)]
@@ -10,7 +12,21 @@ impl std::fmt::Display for StoreTokenError {
     }
 }
 
-impl std::error::Error for StoreTokenError {}
+impl std::error::Error for StoreTokenError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        Some(&self.0)
+    }
+}
+
+#[derive(Debug)]
+pub struct ConfirmationToken {
+    pub subscriber_id: Uuid,
+    pub list_id: Option<Uuid>,
+    pub list_name: Option<String>,
+    pub new_email: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub consumed_at: Option<DateTime<Utc>>,
+}
 
 #[tracing::instrument(
     name = "Store confirmation token into database"
@@ -19,16 +35,104 @@ impl std::error::Error for StoreTokenError {}
 pub async fn store_token(
     transaction: &mut Transaction<'_, Postgres>,
     subscriber_id: Uuid,
+    list_id: Uuid,
     confirmation_token: &str,
 ) -> Result<(), StoreTokenError> {
     sqlx::query!(
-        r#"INSERT INTO subscriptions_tokens (subscriptions_token, subscriber_id)
-    VALUES ($1, $2)"#,
-        confirmation_token,
-        subscriber_id
+        r#"INSERT INTO subscriptions_tokens (token_hash, subscriber_id, list_id)
+    VALUES ($1, $2, $3)"#,
+        hash_confirmation_token(confirmation_token),
+        subscriber_id,
+        list_id
+    )
+    .execute(transaction)
+    .await
+    .map_err(StoreTokenError)?;
+    Ok(())
+}
+
+#[tracing::instrument(
+    name = "Store email change token into database"
+    skip(confirmation_token, transaction)
+)]
+pub async fn store_email_change_token(
+    transaction: &mut Transaction<'_, Postgres>,
+    subscriber_id: Uuid,
+    new_email: &str,
+    confirmation_token: &str,
+) -> Result<(), StoreTokenError> {
+    sqlx::query!(
+        r#"INSERT INTO subscriptions_tokens (token_hash, subscriber_id, new_email)
+    VALUES ($1, $2, $3)"#,
+        hash_confirmation_token(confirmation_token),
+        subscriber_id,
+        new_email
     )
     .execute(transaction)
     .await
     .map_err(StoreTokenError)?;
     Ok(())
 }
+
+#[tracing::instrument(name = "Delete unused confirmation tokens", skip(transaction))]
+pub async fn delete_tokens(
+    transaction: &mut Transaction<'_, Postgres>,
+    subscriber_id: Uuid,
+    list_id: Option<Uuid>,
+) -> Result<(), sqlx::Error> {
+    sqlx::query!(
+        r#"DELETE FROM subscriptions_tokens
+        WHERE
+            subscriber_id = $1 AND
+            ($2::uuid IS NULL OR list_id = $2) AND
+            consumed_at IS NULL"#,
+        subscriber_id,
+        list_id
+    )
+    .execute(transaction)
+    .await?;
+    Ok(())
+}
+
+#[tracing::instrument(name = "Delete unused email change tokens", skip(transaction))]
+pub async fn delete_email_change_tokens(
+    transaction: &mut Transaction<'_, Postgres>,
+    subscriber_id: Uuid,
+) -> Result<(), sqlx::Error> {
+    sqlx::query!(
+        r#"DELETE FROM subscriptions_tokens
+        WHERE
+            subscriber_id = $1 AND
+            new_email IS NOT NULL AND
+            consumed_at IS NULL"#,
+        subscriber_id
+    )
+    .execute(transaction)
+    .await?;
+    Ok(())
+}
+
+#[tracing::instrument(name = "Fetch a confirmation token", skip(confirmation_token, pool))]
+pub async fn fetch_confirmation_token(
+    pool: &PgPool,
+    confirmation_token: &str,
+) -> Result<Option<ConfirmationToken>, sqlx::Error> {
+    sqlx::query_as!(
+        ConfirmationToken,
+        r#"
+        SELECT
+            subscriptions_tokens.subscriber_id,
+            subscriptions_tokens.list_id,
+            lists.name AS "list_name?",
+            subscriptions_tokens.new_email,
+            subscriptions_tokens.created_at,
+            subscriptions_tokens.consumed_at
+        FROM subscriptions_tokens
+        LEFT JOIN lists ON lists.list_id = subscriptions_tokens.list_id
+        WHERE token_hash = $1
+        "#,
+        hash_confirmation_token(confirmation_token)
+    )
+    .fetch_optional(pool)
+    .await
+}
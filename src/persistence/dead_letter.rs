@@ -0,0 +1,191 @@
+use super::{
+    mark_newsletter_issue_sending, notify_channel, NEWSLETTER_DELIVERY_CHANNEL,
+    SUBSCRIPTION_CONFIRMATION_CHANNEL,
+};
+use sqlx::PgPool;
+use uuid::Uuid;
+
+#[derive(Debug, serde::Serialize)]
+pub struct NewsletterDeadLetter {
+    pub newsletter_issue_id: Uuid,
+    pub issue_title: String,
+    pub subscriber_id: Uuid,
+    pub subscriber_email: String,
+    pub n_attempts: i32,
+    pub last_error: String,
+    pub enqueued_at: String,
+    pub failed_at: String,
+}
+
+#[derive(Debug, serde::Serialize)]
+pub struct ConfirmationDeadLetter {
+    pub subscriber_id: Uuid,
+    pub subscriber_email: String,
+    pub n_attempts: i32,
+    pub last_error: String,
+    pub enqueued_at: String,
+    pub failed_at: String,
+}
+
+#[tracing::instrument(skip_all)]
+pub async fn fetch_newsletter_dead_letters(
+    pool: &PgPool,
+) -> Result<Vec<NewsletterDeadLetter>, sqlx::Error> {
+    let rows = sqlx::query!(
+        r#"
+        SELECT
+            issue_delivery_dead_letters.newsletter_issue_id,
+            title,
+            subscriber_id,
+            subscriptions.email AS subscriber_email,
+            n_attempts,
+            last_error,
+            enqueued_at,
+            failed_at
+        FROM issue_delivery_dead_letters
+        JOIN newsletter_issues
+        ON newsletter_issues.newsletter_issue_id = issue_delivery_dead_letters.newsletter_issue_id
+        JOIN subscriptions
+        ON subscriptions.id = subscriber_id
+        ORDER BY failed_at DESC
+        "#
+    )
+    .fetch_all(pool)
+    .await?;
+    Ok(rows
+        .into_iter()
+        .map(|r| NewsletterDeadLetter {
+            newsletter_issue_id: r.newsletter_issue_id,
+            issue_title: r.title,
+            subscriber_id: r.subscriber_id,
+            subscriber_email: r.subscriber_email,
+            n_attempts: r.n_attempts,
+            last_error: r.last_error,
+            enqueued_at: r.enqueued_at.to_rfc3339(),
+            failed_at: r.failed_at.to_rfc3339(),
+        })
+        .collect())
+}
+
+#[tracing::instrument(skip_all)]
+pub async fn fetch_confirmation_dead_letters(
+    pool: &PgPool,
+) -> Result<Vec<ConfirmationDeadLetter>, sqlx::Error> {
+    let rows = sqlx::query!(
+        r#"
+        SELECT
+            subscriber_id,
+            email,
+            n_attempts,
+            last_error,
+            enqueued_at,
+            failed_at
+        FROM subscription_confirmation_dead_letters
+        JOIN subscriptions
+        ON subscriptions.id = subscriber_id
+        ORDER BY failed_at DESC
+        "#
+    )
+    .fetch_all(pool)
+    .await?;
+    Ok(rows
+        .into_iter()
+        .map(|r| ConfirmationDeadLetter {
+            subscriber_id: r.subscriber_id,
+            subscriber_email: r.email,
+            n_attempts: r.n_attempts,
+            last_error: r.last_error,
+            enqueued_at: r.enqueued_at.to_rfc3339(),
+            failed_at: r.failed_at.to_rfc3339(),
+        })
+        .collect())
+}
+
+#[tracing::instrument(skip(pool))]
+pub async fn requeue_newsletter_dead_letter(
+    pool: &PgPool,
+    newsletter_issue_id: Uuid,
+    subscriber_id: Uuid,
+) -> Result<u64, sqlx::Error> {
+    let mut transaction = pool.begin().await?;
+    let result = sqlx::query!(
+        r#"
+        WITH requeued AS (
+            DELETE FROM issue_delivery_dead_letters
+            WHERE
+                newsletter_issue_id = $1 AND
+                subscriber_id = $2
+            RETURNING newsletter_issue_id, subscriber_id, enqueued_at
+        )
+        INSERT INTO issue_delievery_queue (newsletter_issue_id, subscriber_id, enqueued_at)
+        SELECT newsletter_issue_id, subscriber_id, enqueued_at
+        FROM requeued
+        ON CONFLICT DO NOTHING
+        "#,
+        newsletter_issue_id,
+        subscriber_id
+    )
+    .execute(&mut transaction)
+    .await?;
+    if result.rows_affected() > 0 {
+        mark_newsletter_issue_sending(&mut transaction, newsletter_issue_id).await?;
+    }
+    notify_channel(&mut transaction, NEWSLETTER_DELIVERY_CHANNEL).await?;
+    transaction.commit().await?;
+    Ok(result.rows_affected())
+}
+
+#[tracing::instrument(skip(pool))]
+pub async fn requeue_newsletter_dead_letters_for_issue(
+    pool: &PgPool,
+    newsletter_issue_id: Uuid,
+) -> Result<u64, sqlx::Error> {
+    let mut transaction = pool.begin().await?;
+    let result = sqlx::query!(
+        r#"
+        WITH requeued AS (
+            DELETE FROM issue_delivery_dead_letters
+            WHERE newsletter_issue_id = $1
+            RETURNING newsletter_issue_id, subscriber_id, enqueued_at
+        )
+        INSERT INTO issue_delievery_queue (newsletter_issue_id, subscriber_id, enqueued_at)
+        SELECT newsletter_issue_id, subscriber_id, enqueued_at
+        FROM requeued
+        ON CONFLICT DO NOTHING
+        "#,
+        newsletter_issue_id
+    )
+    .execute(&mut transaction)
+    .await?;
+    if result.rows_affected() > 0 {
+        mark_newsletter_issue_sending(&mut transaction, newsletter_issue_id).await?;
+    }
+    notify_channel(&mut transaction, NEWSLETTER_DELIVERY_CHANNEL).await?;
+    transaction.commit().await?;
+    Ok(result.rows_affected())
+}
+
+#[tracing::instrument(skip(pool))]
+pub async fn requeue_confirmation_dead_letter(
+    pool: &PgPool,
+    subscriber_id: Uuid,
+) -> Result<u64, sqlx::Error> {
+    let result = sqlx::query!(
+        r#"
+        WITH requeued AS (
+            DELETE FROM subscription_confirmation_dead_letters
+            WHERE subscriber_id = $1
+            RETURNING subscriber_id, enqueued_at
+        )
+        INSERT INTO subscription_confirmation_delivery_queue (subscriber_id, enqueued_at)
+        SELECT subscriber_id, enqueued_at
+        FROM requeued
+        ON CONFLICT DO NOTHING
+        "#,
+        subscriber_id
+    )
+    .execute(pool)
+    .await?;
+    notify_channel(pool, SUBSCRIPTION_CONFIRMATION_CHANNEL).await?;
+    Ok(result.rows_affected())
+}
@@ -0,0 +1,196 @@
+use super::PgTransaction;
+use sqlx::PgPool;
+use uuid::Uuid;
+
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct MailingList {
+    pub list_id: Uuid,
+    pub name: String,
+}
+
+#[derive(Debug, serde::Serialize)]
+pub struct MailingListSummary {
+    pub list_id: Uuid,
+    pub name: String,
+    pub n_confirmed: i64,
+    pub n_pending: i64,
+}
+
+#[derive(Debug, serde::Serialize)]
+pub struct ListMembership {
+    pub list_id: Uuid,
+    pub name: String,
+    pub status: Option<String>,
+}
+
+#[tracing::instrument(skip_all)]
+pub async fn fetch_lists(pool: &PgPool) -> Result<Vec<MailingList>, sqlx::Error> {
+    sqlx::query_as!(
+        MailingList,
+        r#"
+        SELECT list_id, name
+        FROM lists
+        ORDER BY created_at, name
+        "#
+    )
+    .fetch_all(pool)
+    .await
+}
+
+#[tracing::instrument(skip(pool))]
+pub async fn fetch_list_or_default(
+    pool: &PgPool,
+    list_id: Option<Uuid>,
+) -> Result<Option<MailingList>, sqlx::Error> {
+    sqlx::query_as!(
+        MailingList,
+        r#"
+        SELECT list_id, name
+        FROM lists
+        WHERE $1::uuid IS NULL OR list_id = $1
+        ORDER BY created_at, name
+        LIMIT 1
+        "#,
+        list_id
+    )
+    .fetch_optional(pool)
+    .await
+}
+
+#[tracing::instrument(skip_all)]
+pub async fn fetch_list_summaries(pool: &PgPool) -> Result<Vec<MailingListSummary>, sqlx::Error> {
+    sqlx::query_as!(
+        MailingListSummary,
+        r#"
+        SELECT
+            lists.list_id,
+            lists.name,
+            COUNT(*) FILTER (WHERE list_memberships.status = 'confirmed') AS "n_confirmed!",
+            COUNT(*) FILTER (WHERE list_memberships.status = 'pending_confirmation') AS "n_pending!"
+        FROM lists
+        LEFT JOIN list_memberships
+        ON list_memberships.list_id = lists.list_id
+        GROUP BY lists.list_id
+        ORDER BY lists.created_at, lists.name
+        "#
+    )
+    .fetch_all(pool)
+    .await
+}
+
+#[tracing::instrument(skip(pool))]
+pub async fn insert_list(pool: &PgPool, name: &str) -> Result<Option<Uuid>, sqlx::Error> {
+    let row = sqlx::query!(
+        r#"
+        INSERT INTO lists (list_id, name)
+        VALUES ($1, $2)
+        ON CONFLICT (name) DO NOTHING
+        RETURNING list_id
+        "#,
+        Uuid::new_v4(),
+        name
+    )
+    .fetch_optional(pool)
+    .await?;
+    Ok(row.map(|r| r.list_id))
+}
+
+#[tracing::instrument(skip(transaction))]
+pub async fn insert_list_membership(
+    transaction: &mut PgTransaction<'_>,
+    list_id: Uuid,
+    subscriber_id: Uuid,
+) -> Result<(), sqlx::Error> {
+    sqlx::query!(
+        r#"
+        INSERT INTO list_memberships (list_id, subscriber_id, status)
+        VALUES ($1, $2, 'pending_confirmation')
+        "#,
+        list_id,
+        subscriber_id
+    )
+    .execute(&mut *transaction)
+    .await?;
+    Ok(())
+}
+
+#[tracing::instrument(skip(pool))]
+pub async fn fetch_list_memberships(
+    pool: &PgPool,
+    subscriber_id: Uuid,
+) -> Result<Vec<ListMembership>, sqlx::Error> {
+    sqlx::query_as!(
+        ListMembership,
+        r#"
+        SELECT lists.list_id, lists.name, list_memberships.status AS "status?"
+        FROM lists
+        LEFT JOIN list_memberships
+        ON list_memberships.list_id = lists.list_id AND list_memberships.subscriber_id = $1
+        ORDER BY lists.created_at, lists.name
+        "#,
+        subscriber_id
+    )
+    .fetch_all(pool)
+    .await
+}
+
+#[tracing::instrument(skip(transaction))]
+pub async fn fetch_list_membership_status(
+    transaction: &mut PgTransaction<'_>,
+    list_id: Uuid,
+    subscriber_id: Uuid,
+) -> Result<Option<String>, sqlx::Error> {
+    let row = sqlx::query!(
+        r#"
+        SELECT status FROM list_memberships
+        WHERE list_id = $1 AND subscriber_id = $2
+        FOR UPDATE
+        "#,
+        list_id,
+        subscriber_id
+    )
+    .fetch_optional(&mut *transaction)
+    .await?;
+    Ok(row.map(|r| r.status))
+}
+
+#[tracing::instrument(skip(transaction))]
+pub async fn rejoin_list(
+    transaction: &mut PgTransaction<'_>,
+    list_id: Uuid,
+    subscriber_id: Uuid,
+) -> Result<bool, sqlx::Error> {
+    let result = sqlx::query!(
+        r#"
+        INSERT INTO list_memberships (list_id, subscriber_id, status)
+        VALUES ($1, $2, 'pending_confirmation')
+        ON CONFLICT (list_id, subscriber_id) DO UPDATE
+        SET status = 'pending_confirmation', subscribed_at = now()
+        WHERE list_memberships.status = 'unsubscribed'
+        "#,
+        list_id,
+        subscriber_id
+    )
+    .execute(&mut *transaction)
+    .await?;
+    Ok(result.rows_affected() > 0)
+}
+
+#[tracing::instrument(skip(pool))]
+pub async fn leave_list(
+    pool: &PgPool,
+    list_id: Uuid,
+    subscriber_id: Uuid,
+) -> Result<bool, sqlx::Error> {
+    let result = sqlx::query!(
+        r#"
+        UPDATE list_memberships SET status = 'unsubscribed'
+        WHERE list_id = $1 AND subscriber_id = $2 AND status <> 'unsubscribed'
+        "#,
+        list_id,
+        subscriber_id
+    )
+    .execute(pool)
+    .await?;
+    Ok(result.rows_affected() > 0)
+}
@@ -1,6 +1,6 @@
 use sqlx::{Postgres, Transaction};
 
-type PgTransaction<'a> = Transaction<'a, Postgres>;
+pub type PgTransaction<'a> = Transaction<'a, Postgres>;
 
 pub mod subscriber;
 pub use subscriber::*;
@@ -8,6 +8,9 @@ pub use subscriber::*;
 pub mod subscription_confirmation_task;
 pub use subscription_confirmation_task::*;
 
+pub mod list;
+pub use list::*;
+
 pub mod user;
 pub use user::*;
 
@@ -19,3 +22,15 @@ pub use newsletter_delivery_task::*;
 
 pub mod confirmation_token;
 pub use confirmation_token::*;
+
+pub mod dead_letter;
+pub use dead_letter::*;
+
+pub mod notification;
+pub use notification::*;
+
+pub mod newsletter_delivery;
+pub use newsletter_delivery::*;
+
+pub mod suppression;
+pub use suppression::*;
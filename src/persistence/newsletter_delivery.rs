@@ -0,0 +1,76 @@
+use crate::domain::tasks::NewsletterDeliveryTask;
+
+use super::PgTransaction;
+
+pub enum DeliveryStatus {
+    Sent,
+    Retrying,
+    Failed,
+    Skipped,
+}
+
+impl DeliveryStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            DeliveryStatus::Sent => "sent",
+            DeliveryStatus::Retrying => "retrying",
+            DeliveryStatus::Failed => "failed",
+            DeliveryStatus::Skipped => "skipped",
+        }
+    }
+}
+
+#[tracing::instrument(skip_all)]
+pub async fn record_newsletter_delivery(
+    transaction: &mut PgTransaction<'_>,
+    task: &NewsletterDeliveryTask,
+    status: DeliveryStatus,
+    provider_message_id: Option<&str>,
+    error_code: Option<i32>,
+    error_message: Option<&str>,
+) -> Result<(), anyhow::Error> {
+    sqlx::query!(
+        r#"
+        INSERT INTO newsletter_deliveries (
+            newsletter_issue_id,
+            subscriber_id,
+            subscriber_email,
+            status,
+            provider_message_id,
+            error_code,
+            error_message,
+            n_attempts,
+            first_attempted_at,
+            last_attempted_at,
+            sent_at
+        )
+        VALUES (
+            $1, $2, $3, $4, $5, $6, $7, 1, now(), now(),
+            CASE WHEN $4 = 'sent' THEN now() END
+        )
+        ON CONFLICT (newsletter_issue_id, subscriber_id) DO UPDATE
+        SET
+            subscriber_email = EXCLUDED.subscriber_email,
+            status = EXCLUDED.status,
+            provider_message_id = COALESCE(
+                EXCLUDED.provider_message_id,
+                newsletter_deliveries.provider_message_id
+            ),
+            error_code = EXCLUDED.error_code,
+            error_message = EXCLUDED.error_message,
+            n_attempts = newsletter_deliveries.n_attempts + 1,
+            last_attempted_at = EXCLUDED.last_attempted_at,
+            sent_at = EXCLUDED.sent_at
+        "#,
+        task.newsletter_issue_id,
+        task.subscriber_id,
+        task.subscriber_email,
+        status.as_str(),
+        provider_message_id,
+        error_code,
+        error_message
+    )
+    .execute(&mut *transaction)
+    .await?;
+    Ok(())
+}
@@ -1,60 +1,253 @@
-use sqlx::{PgPool, Postgres, Transaction};
-use std::fmt::Debug;
+use crate::domain::tasks::{Job, NewsletterDeliveryTask};
+use chrono::Utc;
+use sqlx::{Postgres, Transaction};
+use std::time::Duration;
 
 use uuid::Uuid;
 
-use super::PgTransaction;
+use super::{
+    mark_newsletter_issue_sent_if_delivered, notify_channel, DeliveryStatus, PgTransaction,
+    NEWSLETTER_DELIVERY_CHANNEL,
+};
 
 #[tracing::instrument(skip_all)]
 pub async fn delete_newsletter_delivery_task(
-    mut transaction: PgTransaction<'_>,
+    transaction: &mut PgTransaction<'_>,
     issue_id: Uuid,
-    email: &str,
+    subscriber_id: Uuid,
 ) -> Result<(), anyhow::Error> {
     sqlx::query!(
         r#"
         DELETE FROM issue_delievery_queue
         WHERE
             newsletter_issue_id = $1 AND
-            subscriber_email = $2
+            subscriber_id = $2
         "#,
         issue_id,
-        email
+        subscriber_id
     )
-    .execute(&mut transaction)
+    .execute(&mut *transaction)
     .await?;
-    transaction.commit().await?;
+    mark_newsletter_issue_sent_if_delivered(transaction, issue_id).await?;
     Ok(())
 }
 
 #[tracing::instrument(skip_all)]
-pub async fn dequeue_newsletter_delivery_task(
-    pool: &PgPool,
-) -> Result<Option<(PgTransaction, Uuid, String)>, anyhow::Error> {
-    let mut transaction = pool.begin().await?;
-    let r = sqlx::query!(
+pub async fn retry_newsletter_delivery_task(
+    transaction: &mut PgTransaction<'_>,
+    task: &NewsletterDeliveryTask,
+    backoff: Duration,
+) -> Result<(), anyhow::Error> {
+    let execute_after = Utc::now() + chrono::Duration::from_std(backoff)?;
+    sqlx::query!(
         r#"
-        SELECT newsletter_issue_id, subscriber_email
+        UPDATE issue_delievery_queue
+        SET
+            n_retries = n_retries + 1,
+            execute_after = $3
+        WHERE
+            newsletter_issue_id = $1 AND
+            subscriber_id = $2
+        "#,
+        task.newsletter_issue_id,
+        task.subscriber_id,
+        execute_after
+    )
+    .execute(&mut *transaction)
+    .await?;
+    Ok(())
+}
+
+#[tracing::instrument(skip_all)]
+pub async fn dead_letter_newsletter_delivery_task(
+    transaction: &mut PgTransaction<'_>,
+    task: &NewsletterDeliveryTask,
+    last_error: &str,
+) -> Result<(), anyhow::Error> {
+    sqlx::query!(
+        r#"
+        INSERT INTO issue_delivery_dead_letters (
+            newsletter_issue_id,
+            subscriber_id,
+            n_attempts,
+            last_error,
+            enqueued_at,
+            failed_at
+        )
+        SELECT newsletter_issue_id, subscriber_id, n_retries + 1, $3, enqueued_at, now()
         FROM issue_delievery_queue
-        FOR UPDATE
-        SKIP LOCKED
-        LIMIT 1
-        "#
+        WHERE
+            newsletter_issue_id = $1 AND
+            subscriber_id = $2
+        ON CONFLICT (newsletter_issue_id, subscriber_id) DO UPDATE
+        SET
+            n_attempts = EXCLUDED.n_attempts,
+            last_error = EXCLUDED.last_error,
+            failed_at = EXCLUDED.failed_at
+        "#,
+        task.newsletter_issue_id,
+        task.subscriber_id,
+        last_error
     )
-    .fetch_optional(&mut transaction)
+    .execute(&mut *transaction)
     .await?;
+    delete_newsletter_delivery_task(transaction, task.newsletter_issue_id, task.subscriber_id).await
+}
+
+#[tracing::instrument(skip_all)]
+pub async fn dequeue_newsletter_delivery_tasks(
+    transaction: &mut PgTransaction<'_>,
+    batch_size: i64,
+) -> Result<Vec<Job<NewsletterDeliveryTask>>, anyhow::Error> {
+    // Eligibility can change after an issue is enqueued, so it is re-checked
+    // for the locked rows only and the ineligible ones are skipped. A batch
+    // that was skipped entirely must not look like an empty queue.
+    loop {
+        let rows = sqlx::query!(
+            r#"
+            SELECT
+                newsletter_issue_id,
+                subscriber_id,
+                n_retries,
+                subscriptions.email AS subscriber_email,
+                subscriptions.name AS subscriber_name,
+                CASE
+                    WHEN EXISTS (
+                        SELECT 1 FROM email_suppressions
+                        WHERE email_suppressions.email = lower(subscriptions.email)
+                    ) THEN 'The address is on the suppression list'
+                    WHEN subscriptions.paused_until > now()
+                        THEN 'The subscriber has paused deliveries'
+                    WHEN
+                        subscriptions.status <> 'confirmed' OR
+                        NOT EXISTS (
+                            SELECT 1
+                            FROM newsletter_issues
+                            JOIN list_memberships
+                            ON list_memberships.list_id = newsletter_issues.list_id
+                            WHERE
+                                newsletter_issues.newsletter_issue_id =
+                                    issue_delievery_queue.newsletter_issue_id AND
+                                list_memberships.subscriber_id =
+                                    issue_delievery_queue.subscriber_id AND
+                                list_memberships.status = 'confirmed'
+                        )
+                        THEN 'The subscriber is no longer subscribed to the list'
+                END AS skip_reason
+            FROM issue_delievery_queue
+            JOIN subscriptions
+            ON subscriptions.id = subscriber_id
+            WHERE execute_after <= now()
+            FOR UPDATE OF issue_delievery_queue
+            SKIP LOCKED
+            LIMIT $1
+            "#,
+            batch_size
+        )
+        .fetch_all(&mut *transaction)
+        .await?;
+        if rows.is_empty() {
+            return Ok(Vec::new());
+        }
 
-    if let Some(r) = r {
-        Ok(Some((
-            transaction,
-            r.newsletter_issue_id,
-            r.subscriber_email,
-        )))
-    } else {
-        Ok(None)
+        let mut jobs = Vec::with_capacity(rows.len());
+        let mut skipped = Vec::new();
+        for r in rows {
+            match r.skip_reason {
+                Some(reason) => skipped.push((r.newsletter_issue_id, r.subscriber_id, reason)),
+                None => jobs.push(Job {
+                    payload: NewsletterDeliveryTask {
+                        newsletter_issue_id: r.newsletter_issue_id,
+                        subscriber_id: r.subscriber_id,
+                        subscriber_email: r.subscriber_email,
+                        subscriber_name: r.subscriber_name,
+                    },
+                    n_retries: r.n_retries,
+                }),
+            }
+        }
+        skip_newsletter_delivery_tasks(transaction, skipped).await?;
+        if !jobs.is_empty() {
+            return Ok(jobs);
+        }
     }
 }
 
+#[tracing::instrument(skip_all)]
+async fn skip_newsletter_delivery_tasks(
+    transaction: &mut PgTransaction<'_>,
+    skipped: Vec<(Uuid, Uuid, String)>,
+) -> Result<(), anyhow::Error> {
+    if skipped.is_empty() {
+        return Ok(());
+    }
+    let mut issue_ids = Vec::with_capacity(skipped.len());
+    let mut subscriber_ids = Vec::with_capacity(skipped.len());
+    let mut reasons = Vec::with_capacity(skipped.len());
+    for (issue_id, subscriber_id, reason) in skipped {
+        tracing::info!(%issue_id, %subscriber_id, %reason, "Skipping a newsletter delivery");
+        issue_ids.push(issue_id);
+        subscriber_ids.push(subscriber_id);
+        reasons.push(reason);
+    }
+    sqlx::query!(
+        r#"
+        DELETE FROM issue_delievery_queue
+        WHERE (newsletter_issue_id, subscriber_id) IN (
+            SELECT * FROM UNNEST($1::uuid[], $2::uuid[])
+        )
+        "#,
+        &issue_ids,
+        &subscriber_ids
+    )
+    .execute(&mut *transaction)
+    .await?;
+    sqlx::query!(
+        r#"
+        INSERT INTO newsletter_deliveries (
+            newsletter_issue_id,
+            subscriber_id,
+            subscriber_email,
+            status,
+            error_message,
+            n_attempts,
+            first_attempted_at,
+            last_attempted_at
+        )
+        SELECT
+            skipped.newsletter_issue_id,
+            skipped.subscriber_id,
+            subscriptions.email,
+            $4,
+            skipped.reason,
+            0,
+            now(),
+            now()
+        FROM UNNEST($1::uuid[], $2::uuid[], $3::text[])
+            AS skipped(newsletter_issue_id, subscriber_id, reason)
+        JOIN subscriptions ON subscriptions.id = skipped.subscriber_id
+        ON CONFLICT (newsletter_issue_id, subscriber_id) DO UPDATE
+        SET
+            status = EXCLUDED.status,
+            error_code = NULL,
+            error_message = EXCLUDED.error_message,
+            last_attempted_at = EXCLUDED.last_attempted_at
+        "#,
+        &issue_ids,
+        &subscriber_ids,
+        &reasons,
+        DeliveryStatus::Skipped.as_str()
+    )
+    .execute(&mut *transaction)
+    .await?;
+    issue_ids.sort();
+    issue_ids.dedup();
+    for issue_id in issue_ids {
+        mark_newsletter_issue_sent_if_delivered(transaction, issue_id).await?;
+    }
+    Ok(())
+}
+
 #[tracing::instrument(skip_all)]
 pub async fn enqueue_newsletter_delivery_tasks(
     transaction: &mut Transaction<'_, Postgres>,
@@ -64,15 +257,29 @@ pub async fn enqueue_newsletter_delivery_tasks(
         r#"
         INSERT INTO issue_delievery_queue (
             newsletter_issue_id,
-            subscriber_email
+            subscriber_id
         )
-        SELECT $1, email
-        FROM subscriptions
-        WHERE status = 'confirmed'
+        SELECT newsletter_issues.newsletter_issue_id, subscriptions.id
+        FROM newsletter_issues
+        JOIN list_memberships
+        ON list_memberships.list_id = newsletter_issues.list_id
+        JOIN subscriptions
+        ON subscriptions.id = list_memberships.subscriber_id
+        WHERE
+            newsletter_issues.newsletter_issue_id = $1 AND
+            list_memberships.status = 'confirmed' AND
+            subscriptions.status = 'confirmed' AND
+            (subscriptions.paused_until IS NULL OR subscriptions.paused_until <= now()) AND
+            NOT EXISTS (
+                SELECT 1 FROM email_suppressions
+                WHERE email_suppressions.email = lower(subscriptions.email)
+            )
         "#,
         newsletter_issue_id
     )
-    .execute(transaction)
+    .execute(&mut *transaction)
     .await?;
+    mark_newsletter_issue_sent_if_delivered(transaction, newsletter_issue_id).await?;
+    notify_channel(transaction, NEWSLETTER_DELIVERY_CHANNEL).await?;
     Ok(())
 }
@@ -1,14 +1,55 @@
-use crate::domain::NewsletterIssue;
+use crate::domain::{NewsletterIssue, PublicationSchedule};
 use sqlx::{PgPool, Postgres, Transaction};
 use std::fmt::Debug;
 
 use uuid::Uuid;
 
+use super::PgTransaction;
+
+pub enum NewsletterStatus {
+    Draft,
+    Scheduled,
+    Sending,
+    Sent,
+}
+
+impl NewsletterStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            NewsletterStatus::Draft => "draft",
+            NewsletterStatus::Scheduled => "scheduled",
+            NewsletterStatus::Sending => "sending",
+            NewsletterStatus::Sent => "sent",
+        }
+    }
+}
+
+#[derive(Debug, serde::Serialize)]
+pub struct NewsletterDraft {
+    pub newsletter_issue_id: Uuid,
+    pub title: String,
+    pub text_content: String,
+    pub html_content: String,
+    pub markdown_content: Option<String>,
+    pub timezone: Option<String>,
+    pub list_id: Uuid,
+}
+
+#[derive(Debug, serde::Serialize)]
+pub struct ScheduledNewsletterIssue {
+    pub newsletter_issue_id: Uuid,
+    pub title: String,
+    pub scheduled_for: String,
+    pub scheduled_for_input: String,
+    pub timezone: String,
+}
+
 #[derive(Debug)]
 struct RawNewsletterIssue {
     title: String,
     text_content: String,
     html_content: String,
+    markdown_content: Option<String>,
 }
 
 #[tracing::instrument(skip_all)]
@@ -19,7 +60,7 @@ pub async fn fetch_newsletter_issue(
     let issue = sqlx::query_as!(
         RawNewsletterIssue,
         r#"
-        SELECT title, text_content, html_content
+        SELECT title, text_content, html_content, markdown_content
         FROM newsletter_issues
         WHERE
             newsletter_issue_id = $1
@@ -28,16 +69,19 @@ pub async fn fetch_newsletter_issue(
     )
     .fetch_one(pool)
     .await?;
-    Ok(
-        NewsletterIssue::validate_new(issue.title, issue.text_content, issue.html_content)
-            .expect("invalid newsletter stored in database"),
-    )
+    Ok(NewsletterIssue::from_stored(
+        issue.title,
+        issue.text_content,
+        issue.html_content,
+        issue.markdown_content,
+    ))
 }
 
 #[tracing::instrument(skip_all)]
 pub async fn insert_newsletter_issue(
     transaction: &mut Transaction<'_, Postgres>,
     newsletter_issue: &NewsletterIssue,
+    list_id: Uuid,
 ) -> Result<Uuid, sqlx::Error> {
     let newsletter_issue_id = Uuid::new_v4();
     sqlx::query!(
@@ -47,16 +91,413 @@ pub async fn insert_newsletter_issue(
             title,
             text_content,
             html_content,
+            markdown_content,
+            list_id,
+            status,
             published_at
         )
-        VALUES ($1, $2, $3, $4, now())
+        VALUES ($1, $2, $3, $4, $5, $6, $7, now())
         "#,
         newsletter_issue_id,
         newsletter_issue.title(),
         newsletter_issue.text(),
-        newsletter_issue.html()
+        newsletter_issue.html(),
+        newsletter_issue.markdown(),
+        list_id,
+        NewsletterStatus::Sending.as_str()
     )
     .execute(transaction)
     .await?;
     Ok(newsletter_issue_id)
 }
+
+#[tracing::instrument(skip_all)]
+pub async fn insert_scheduled_newsletter_issue(
+    transaction: &mut PgTransaction<'_>,
+    newsletter_issue: &NewsletterIssue,
+    list_id: Uuid,
+    schedule: &PublicationSchedule,
+) -> Result<Uuid, sqlx::Error> {
+    let newsletter_issue_id = Uuid::new_v4();
+    sqlx::query!(
+        r#"
+        INSERT INTO newsletter_issues (
+            newsletter_issue_id,
+            title,
+            text_content,
+            html_content,
+            markdown_content,
+            list_id,
+            status,
+            scheduled_for,
+            timezone
+        )
+        VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9)
+        "#,
+        newsletter_issue_id,
+        newsletter_issue.title(),
+        newsletter_issue.text(),
+        newsletter_issue.html(),
+        newsletter_issue.markdown(),
+        list_id,
+        NewsletterStatus::Scheduled.as_str(),
+        schedule.scheduled_for(),
+        schedule.timezone()
+    )
+    .execute(&mut *transaction)
+    .await?;
+    Ok(newsletter_issue_id)
+}
+
+#[tracing::instrument(skip_all)]
+pub async fn fetch_scheduled_newsletter_issues(
+    pool: &PgPool,
+) -> Result<Vec<ScheduledNewsletterIssue>, sqlx::Error> {
+    let rows = sqlx::query!(
+        r#"
+        SELECT
+            newsletter_issue_id,
+            title,
+            scheduled_for AS "scheduled_for!",
+            timezone AS "timezone!"
+        FROM newsletter_issues
+        WHERE status = $1
+        ORDER BY scheduled_for
+        "#,
+        NewsletterStatus::Scheduled.as_str()
+    )
+    .fetch_all(pool)
+    .await?;
+    Ok(rows
+        .into_iter()
+        .map(|r| {
+            let schedule = PublicationSchedule::from_stored(r.scheduled_for, &r.timezone);
+            ScheduledNewsletterIssue {
+                newsletter_issue_id: r.newsletter_issue_id,
+                title: r.title,
+                scheduled_for: schedule.describe(),
+                scheduled_for_input: schedule.local_input_value(),
+                timezone: r.timezone,
+            }
+        })
+        .collect())
+}
+
+#[tracing::instrument(skip(pool, schedule))]
+pub async fn reschedule_newsletter_issue(
+    pool: &PgPool,
+    newsletter_issue_id: Uuid,
+    schedule: &PublicationSchedule,
+) -> Result<u64, sqlx::Error> {
+    let result = sqlx::query!(
+        r#"
+        UPDATE newsletter_issues
+        SET
+            scheduled_for = $2,
+            timezone = $3
+        WHERE
+            newsletter_issue_id = $1 AND
+            status = $4
+        "#,
+        newsletter_issue_id,
+        schedule.scheduled_for(),
+        schedule.timezone(),
+        NewsletterStatus::Scheduled.as_str()
+    )
+    .execute(pool)
+    .await?;
+    Ok(result.rows_affected())
+}
+
+#[tracing::instrument(skip(pool))]
+pub async fn cancel_scheduled_newsletter_issue(
+    pool: &PgPool,
+    newsletter_issue_id: Uuid,
+) -> Result<u64, sqlx::Error> {
+    let result = sqlx::query!(
+        r#"
+        UPDATE newsletter_issues
+        SET
+            status = $3,
+            scheduled_for = NULL
+        WHERE
+            newsletter_issue_id = $1 AND
+            status = $2
+        "#,
+        newsletter_issue_id,
+        NewsletterStatus::Scheduled.as_str(),
+        NewsletterStatus::Draft.as_str()
+    )
+    .execute(pool)
+    .await?;
+    Ok(result.rows_affected())
+}
+
+#[tracing::instrument(skip_all)]
+pub async fn publish_due_newsletter_issues(
+    transaction: &mut PgTransaction<'_>,
+) -> Result<Vec<Uuid>, sqlx::Error> {
+    let rows = sqlx::query!(
+        r#"
+        UPDATE newsletter_issues
+        SET
+            status = $2,
+            published_at = now()
+        WHERE newsletter_issue_id IN (
+            SELECT newsletter_issue_id
+            FROM newsletter_issues
+            WHERE
+                status = $1 AND
+                scheduled_for <= now()
+            FOR UPDATE
+            SKIP LOCKED
+        )
+        RETURNING newsletter_issue_id
+        "#,
+        NewsletterStatus::Scheduled.as_str(),
+        NewsletterStatus::Sending.as_str()
+    )
+    .fetch_all(&mut *transaction)
+    .await?;
+    Ok(rows.into_iter().map(|r| r.newsletter_issue_id).collect())
+}
+
+#[tracing::instrument(skip_all)]
+pub async fn mark_newsletter_issue_sent_if_delivered(
+    transaction: &mut PgTransaction<'_>,
+    newsletter_issue_id: Uuid,
+) -> Result<(), sqlx::Error> {
+    sqlx::query!(
+        r#"
+        UPDATE newsletter_issues
+        SET status = $3
+        WHERE
+            newsletter_issue_id = $1 AND
+            status = $2 AND
+            NOT EXISTS (
+                SELECT 1 FROM issue_delievery_queue
+                WHERE newsletter_issue_id = $1
+            )
+        "#,
+        newsletter_issue_id,
+        NewsletterStatus::Sending.as_str(),
+        NewsletterStatus::Sent.as_str()
+    )
+    .execute(&mut *transaction)
+    .await?;
+    Ok(())
+}
+
+#[tracing::instrument(skip(transaction))]
+pub async fn mark_newsletter_issue_sending(
+    transaction: &mut PgTransaction<'_>,
+    newsletter_issue_id: Uuid,
+) -> Result<(), sqlx::Error> {
+    sqlx::query!(
+        r#"
+        UPDATE newsletter_issues
+        SET status = $3
+        WHERE
+            newsletter_issue_id = $1 AND
+            status = $2
+        "#,
+        newsletter_issue_id,
+        NewsletterStatus::Sent.as_str(),
+        NewsletterStatus::Sending.as_str()
+    )
+    .execute(&mut *transaction)
+    .await?;
+    Ok(())
+}
+
+#[tracing::instrument(skip_all)]
+pub async fn insert_newsletter_draft(
+    pool: &PgPool,
+    title: &str,
+    text_content: &str,
+    html_content: &str,
+    markdown_content: &str,
+    list_id: Uuid,
+) -> Result<Uuid, sqlx::Error> {
+    let newsletter_issue_id = Uuid::new_v4();
+    sqlx::query!(
+        r#"
+        INSERT INTO newsletter_issues (
+            newsletter_issue_id,
+            title,
+            text_content,
+            html_content,
+            markdown_content,
+            list_id,
+            status
+        )
+        VALUES ($1, $2, $3, $4, $5, $6, $7)
+        "#,
+        newsletter_issue_id,
+        title,
+        text_content,
+        html_content,
+        markdown_content,
+        list_id,
+        NewsletterStatus::Draft.as_str()
+    )
+    .execute(pool)
+    .await?;
+    Ok(newsletter_issue_id)
+}
+
+#[tracing::instrument(skip(pool, text_content, html_content, markdown_content))]
+pub async fn update_newsletter_draft(
+    pool: &PgPool,
+    newsletter_issue_id: Uuid,
+    title: &str,
+    text_content: &str,
+    html_content: &str,
+    markdown_content: &str,
+    list_id: Uuid,
+) -> Result<u64, sqlx::Error> {
+    let result = sqlx::query!(
+        r#"
+        UPDATE newsletter_issues
+        SET
+            title = $2,
+            text_content = $3,
+            html_content = $4,
+            markdown_content = $5,
+            list_id = $6
+        WHERE
+            newsletter_issue_id = $1 AND
+            status = $7
+        "#,
+        newsletter_issue_id,
+        title,
+        text_content,
+        html_content,
+        markdown_content,
+        list_id,
+        NewsletterStatus::Draft.as_str()
+    )
+    .execute(pool)
+    .await?;
+    Ok(result.rows_affected())
+}
+
+#[tracing::instrument(skip(pool))]
+pub async fn fetch_newsletter_draft(
+    pool: &PgPool,
+    newsletter_issue_id: Uuid,
+) -> Result<Option<NewsletterDraft>, sqlx::Error> {
+    sqlx::query_as!(
+        NewsletterDraft,
+        r#"
+        SELECT
+            newsletter_issue_id,
+            title,
+            text_content,
+            html_content,
+            markdown_content,
+            timezone,
+            list_id
+        FROM newsletter_issues
+        WHERE
+            newsletter_issue_id = $1 AND
+            status = $2
+        "#,
+        newsletter_issue_id,
+        NewsletterStatus::Draft.as_str()
+    )
+    .fetch_optional(pool)
+    .await
+}
+
+#[tracing::instrument(skip_all)]
+pub async fn fetch_newsletter_drafts(pool: &PgPool) -> Result<Vec<NewsletterDraft>, sqlx::Error> {
+    sqlx::query_as!(
+        NewsletterDraft,
+        r#"
+        SELECT
+            newsletter_issue_id,
+            title,
+            text_content,
+            html_content,
+            markdown_content,
+            timezone,
+            list_id
+        FROM newsletter_issues
+        WHERE status = $1
+        ORDER BY title
+        "#,
+        NewsletterStatus::Draft.as_str()
+    )
+    .fetch_all(pool)
+    .await
+}
+
+#[tracing::instrument(skip(pool))]
+pub async fn delete_newsletter_draft(
+    pool: &PgPool,
+    newsletter_issue_id: Uuid,
+) -> Result<u64, sqlx::Error> {
+    let result = sqlx::query!(
+        r#"
+        DELETE FROM newsletter_issues
+        WHERE
+            newsletter_issue_id = $1 AND
+            status = $2
+        "#,
+        newsletter_issue_id,
+        NewsletterStatus::Draft.as_str()
+    )
+    .execute(pool)
+    .await?;
+    Ok(result.rows_affected())
+}
+
+#[tracing::instrument(skip(transaction, newsletter_issue, schedule))]
+pub async fn publish_newsletter_draft(
+    transaction: &mut PgTransaction<'_>,
+    newsletter_issue_id: Uuid,
+    newsletter_issue: &NewsletterIssue,
+    list_id: Uuid,
+    schedule: Option<&PublicationSchedule>,
+) -> Result<u64, sqlx::Error> {
+    let (status, scheduled_for, timezone) = match schedule {
+        Some(schedule) => (
+            NewsletterStatus::Scheduled,
+            Some(schedule.scheduled_for()),
+            Some(schedule.timezone()),
+        ),
+        None => (NewsletterStatus::Sending, None, None),
+    };
+    let result = sqlx::query!(
+        r#"
+        UPDATE newsletter_issues
+        SET
+            title = $2,
+            text_content = $3,
+            html_content = $4,
+            markdown_content = $5,
+            list_id = $6,
+            status = $7,
+            scheduled_for = $8,
+            timezone = COALESCE($9, timezone),
+            published_at = CASE WHEN $7 = 'sending' THEN now() END
+        WHERE
+            newsletter_issue_id = $1 AND
+            status = $10
+        "#,
+        newsletter_issue_id,
+        newsletter_issue.title(),
+        newsletter_issue.text(),
+        newsletter_issue.html(),
+        newsletter_issue.markdown(),
+        list_id,
+        status.as_str(),
+        scheduled_for,
+        timezone,
+        NewsletterStatus::Draft.as_str()
+    )
+    .execute(&mut *transaction)
+    .await?;
+    Ok(result.rows_affected())
+}
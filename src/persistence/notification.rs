@@ -0,0 +1,16 @@
+use sqlx::PgExecutor;
+
+pub const NEWSLETTER_DELIVERY_CHANNEL: &str = "newsletter_delivery_queue";
+pub const SUBSCRIPTION_CONFIRMATION_CHANNEL: &str = "subscription_confirmation_queue";
+
+#[tracing::instrument(skip(executor))]
+pub async fn notify_channel<'e, E>(executor: E, channel: &str) -> Result<(), sqlx::Error>
+where
+    E: PgExecutor<'e>,
+{
+    sqlx::query("SELECT pg_notify($1, '')")
+        .bind(channel)
+        .execute(executor)
+        .await?;
+    Ok(())
+}
@@ -1,6 +1,7 @@
 use super::PgTransaction;
-use crate::domain::NewSubscriber;
-use chrono::Utc;
+use crate::domain::new_subscriber::hash_confirmation_token;
+use crate::domain::{NewSubscriber, SubscriberEmail, SubscriberName};
+use chrono::{DateTime, Utc};
 use sqlx::PgPool;
 use uuid::Uuid;
 
@@ -12,44 +13,252 @@ pub async fn insert_subscriber(
     transaction: &mut PgTransaction<'_>,
     new_subscriber: &NewSubscriber,
 ) -> Result<Uuid, sqlx::Error> {
-    let subscriber_id = Uuid::new_v4();
-    sqlx::query!(
+    let row = sqlx::query!(
         r#"
-    INSERT INTO subscriptions (id, email, name, subscribed_at, status)
-    VALUES ($1, $2, $3, $4, 'pending_confirmation')
+    INSERT INTO subscriptions (id, email, name, subscribed_at, status, confirmation_requested_at)
+    VALUES ($1, $2, $3, $4, 'pending_confirmation', $4)
+    ON CONFLICT (email) DO UPDATE SET email = EXCLUDED.email
+    RETURNING id
             "#,
-        subscriber_id,
+        Uuid::new_v4(),
         new_subscriber.email.as_ref(),
         new_subscriber.name.as_ref(),
         Utc::now()
     )
-    .execute(transaction)
+    .fetch_one(transaction)
     .await?;
-    Ok(subscriber_id)
+    Ok(row.id)
 }
 
-#[tracing::instrument(name = "Get subscriber_id from token", skip(subscription_token, pool))]
-pub async fn get_subscriber_id_from_token(
+#[tracing::instrument(name = "Mark subscriber as confirmed", skip(confirmation_token, pool))]
+pub async fn confirm_subscriber(
     pool: &PgPool,
-    subscription_token: &str,
-) -> Result<Option<Uuid>, sqlx::Error> {
-    let result = sqlx::query!(
-        r#"SELECT subscriber_id FROM subscriptions_tokens
-        WHERE subscriptions_token = $1"#,
-        subscription_token
+    confirmation_token: &str,
+) -> Result<bool, sqlx::Error> {
+    let mut transaction = pool.begin().await?;
+    let token = sqlx::query!(
+        r#"UPDATE subscriptions_tokens SET consumed_at = now()
+        WHERE token_hash = $1 AND consumed_at IS NULL
+        RETURNING subscriber_id, list_id, new_email"#,
+        hash_confirmation_token(confirmation_token)
     )
-    .fetch_optional(pool)
+    .fetch_optional(&mut transaction)
+    .await?;
+    let token = match token {
+        Some(token) => token,
+        None => return Ok(false),
+    };
+    if let Some(new_email) = token.new_email {
+        match sqlx::query!(
+            r#"UPDATE subscriptions SET email = $2, pending_email = NULL WHERE id = $1"#,
+            token.subscriber_id,
+            new_email
+        )
+        .execute(&mut transaction)
+        .await
+        {
+            Err(sqlx::Error::Database(e)) if e.code().as_deref() == Some("23505") => {
+                return Ok(false)
+            }
+            result => result?,
+        };
+        transaction.commit().await?;
+        return Ok(true);
+    }
+    sqlx::query!(
+        r#"UPDATE subscriptions SET status = 'confirmed' where id = $1"#,
+        token.subscriber_id
+    )
+    .execute(&mut transaction)
+    .await?;
+    sqlx::query!(
+        r#"UPDATE list_memberships SET status = 'confirmed'
+        WHERE subscriber_id = $1 AND list_id = $2"#,
+        token.subscriber_id,
+        token.list_id
+    )
+    .execute(&mut transaction)
     .await?;
-    Ok(result.map(|r| r.subscriber_id))
+    transaction.commit().await?;
+    Ok(true)
 }
 
-#[tracing::instrument(name = "Mark subscriber as confirmed", skip(subscriber_id, pool))]
-pub async fn confirm_subscriber(pool: &PgPool, subscriber_id: Uuid) -> Result<(), sqlx::Error> {
+#[tracing::instrument(name = "Mark subscriber as unsubscribed", skip(subscriber_id, pool))]
+pub async fn unsubscribe_subscriber(pool: &PgPool, subscriber_id: Uuid) -> Result<(), sqlx::Error> {
+    let mut transaction = pool.begin().await?;
     sqlx::query!(
-        r#"UPDATE subscriptions SET status = 'confirmed' where id = $1"#,
+        r#"UPDATE subscriptions SET status = 'unsubscribed' where id = $1"#,
         subscriber_id
     )
+    .execute(&mut transaction)
+    .await?;
+    sqlx::query!(
+        r#"UPDATE list_memberships SET status = 'unsubscribed' where subscriber_id = $1"#,
+        subscriber_id
+    )
+    .execute(&mut transaction)
+    .await?;
+    transaction.commit().await
+}
+
+#[derive(Debug)]
+pub struct SubscriberProfile {
+    pub name: String,
+    pub email: String,
+    pub paused_until: Option<DateTime<Utc>>,
+}
+
+#[tracing::instrument(skip(pool))]
+pub async fn fetch_subscriber_profile(
+    pool: &PgPool,
+    subscriber_id: Uuid,
+) -> Result<Option<SubscriberProfile>, sqlx::Error> {
+    sqlx::query_as!(
+        SubscriberProfile,
+        r#"SELECT name, email, paused_until FROM subscriptions WHERE id = $1"#,
+        subscriber_id
+    )
+    .fetch_optional(pool)
+    .await
+}
+
+#[tracing::instrument(skip(transaction))]
+pub async fn update_subscriber_name(
+    transaction: &mut PgTransaction<'_>,
+    subscriber_id: Uuid,
+    name: &SubscriberName,
+) -> Result<(), sqlx::Error> {
+    sqlx::query!(
+        r#"UPDATE subscriptions SET name = $2 WHERE id = $1"#,
+        subscriber_id,
+        name.as_ref()
+    )
+    .execute(&mut *transaction)
+    .await?;
+    Ok(())
+}
+
+#[tracing::instrument(skip(transaction))]
+pub async fn request_subscriber_email_change(
+    transaction: &mut PgTransaction<'_>,
+    subscriber_id: Uuid,
+    email: &SubscriberEmail,
+) -> Result<bool, sqlx::Error> {
+    let result = sqlx::query!(
+        r#"
+        UPDATE subscriptions SET pending_email = $2
+        WHERE
+            id = $1 AND
+            NOT EXISTS (SELECT 1 FROM subscriptions WHERE email = $2)
+        "#,
+        subscriber_id,
+        email.as_ref()
+    )
+    .execute(&mut *transaction)
+    .await?;
+    Ok(result.rows_affected() > 0)
+}
+
+#[tracing::instrument(skip(pool))]
+pub async fn pause_subscriber(
+    pool: &PgPool,
+    subscriber_id: Uuid,
+    paused_until: Option<DateTime<Utc>>,
+) -> Result<(), sqlx::Error> {
+    sqlx::query!(
+        r#"UPDATE subscriptions SET paused_until = $2 WHERE id = $1"#,
+        subscriber_id,
+        paused_until
+    )
     .execute(pool)
     .await?;
     Ok(())
 }
+
+#[tracing::instrument(skip(transaction))]
+pub async fn claim_confirmation_resend(
+    transaction: &mut PgTransaction<'_>,
+    subscriber_id: Uuid,
+    min_interval: std::time::Duration,
+) -> Result<bool, sqlx::Error> {
+    let result = sqlx::query!(
+        r#"
+        UPDATE subscriptions SET confirmation_requested_at = now()
+        WHERE
+            id = $1 AND (
+                confirmation_requested_at IS NULL OR
+                confirmation_requested_at <= now() - make_interval(secs => $2)
+            )
+        "#,
+        subscriber_id,
+        min_interval.as_secs_f64()
+    )
+    .execute(&mut *transaction)
+    .await?;
+    Ok(result.rows_affected() > 0)
+}
+
+#[tracing::instrument(skip(pool))]
+pub async fn purge_stale_pending_subscriptions(
+    pool: &PgPool,
+    max_age: std::time::Duration,
+) -> Result<usize, sqlx::Error> {
+    let mut transaction = pool.begin().await?;
+    let stale = sqlx::query!(
+        r#"
+        SELECT id FROM subscriptions
+        WHERE
+            status = 'pending_confirmation' AND
+            GREATEST(
+                subscribed_at,
+                confirmation_requested_at,
+                (
+                    SELECT MAX(subscriptions_tokens.created_at) FROM subscriptions_tokens
+                    WHERE subscriber_id = subscriptions.id
+                )
+            ) <= now() - make_interval(secs => $1) AND
+            NOT EXISTS (
+                SELECT 1 FROM list_memberships
+                WHERE subscriber_id = subscriptions.id AND list_memberships.status = 'confirmed'
+            ) AND
+            NOT EXISTS (
+                SELECT 1 FROM newsletter_deliveries WHERE subscriber_id = subscriptions.id
+            ) AND
+            NOT EXISTS (
+                SELECT 1 FROM subscription_confirmation_delivery_queue
+                WHERE subscriber_id = subscriptions.id
+            )
+        FOR UPDATE SKIP LOCKED
+        "#,
+        max_age.as_secs_f64()
+    )
+    .fetch_all(&mut transaction)
+    .await?;
+    let ids: Vec<Uuid> = stale.into_iter().map(|r| r.id).collect();
+    if ids.is_empty() {
+        return Ok(0);
+    }
+    sqlx::query!(
+        r#"DELETE FROM subscriptions_tokens WHERE subscriber_id = ANY($1)"#,
+        &ids
+    )
+    .execute(&mut transaction)
+    .await?;
+    sqlx::query!(
+        r#"DELETE FROM subscription_confirmation_dead_letters WHERE subscriber_id = ANY($1)"#,
+        &ids
+    )
+    .execute(&mut transaction)
+    .await?;
+    sqlx::query!(
+        r#"DELETE FROM list_memberships WHERE subscriber_id = ANY($1)"#,
+        &ids
+    )
+    .execute(&mut transaction)
+    .await?;
+    sqlx::query!(r#"DELETE FROM subscriptions WHERE id = ANY($1)"#, &ids)
+        .execute(&mut transaction)
+        .await?;
+    transaction.commit().await?;
+    Ok(ids.len())
+}
@@ -1,12 +1,12 @@
-use crate::domain::{
-    tasks::SubscriptionConfirmationTask, NewSubscriber, SubscriberEmail, SubscriberName,
-};
+use crate::domain::tasks::{Job, SubscriptionConfirmationTask};
 
+use chrono::Utc;
 use sqlx::PgPool;
+use std::time::Duration;
 
 use uuid::Uuid;
 
-use super::PgTransaction;
+use super::{notify_channel, PgTransaction, SUBSCRIPTION_CONFIRMATION_CHANNEL};
 
 #[tracing::instrument(skip_all)]
 pub async fn insert_subscription_confirmation_task(
@@ -17,18 +17,19 @@ pub async fn insert_subscription_confirmation_task(
         r#"
         INSERT INTO subscription_confirmation_delivery_queue (subscriber_id)
         VALUES ($1)
+        ON CONFLICT (subscriber_id) DO UPDATE SET execute_after = now()
         "#,
         subscriber_id
     )
     .execute(pool)
     .await?;
-
+    notify_channel(pool, SUBSCRIPTION_CONFIRMATION_CHANNEL).await?;
     Ok(())
 }
 
 #[tracing::instrument(skip_all)]
 pub async fn delete_subscription_confirmation_task(
-    mut transaction: PgTransaction<'_>,
+    transaction: &mut PgTransaction<'_>,
     user: Uuid,
 ) -> Result<(), anyhow::Error> {
     sqlx::query!(
@@ -39,106 +40,126 @@ pub async fn delete_subscription_confirmation_task(
         "#,
         user,
     )
-    .execute(&mut transaction)
+    .execute(&mut *transaction)
     .await?;
-    transaction.commit().await?;
     Ok(())
 }
 
-pub async fn dequeue_subscription_confirmation_task_and_parse(
-    pool: &PgPool,
-) -> Result<Option<(PgTransaction, SubscriptionConfirmationTask)>, anyhow::Error> {
-    if let Some((transaction, raw_task)) = dequeue_task(pool).await? {
-        let email = SubscriberEmail::parse(raw_task.email.clone());
-        if let Err(e) = &email {
-            tracing::error!(
-                error.cause_chain = ?e,
-                error.message = %e,
-                "Skipping a confirmed subscriber because their email was not validated",
-            );
-        }
-        let name = SubscriberName::parse(raw_task.name.clone());
-        if let Err(e) = &name {
-            tracing::error!(
-                error.cause_chain = ?e,
-                error.message = %e,
-                "Skipping a confirmed subscriber because their name was not validated",
-            );
-        }
-        match (name, email) {
-            (Ok(name), Ok(email)) => {
-                let new_subscriber = NewSubscriber { email, name };
-                Ok(Some((
-                    transaction,
-                    SubscriptionConfirmationTask {
-                        subscriber_id: raw_task.user,
-                        subscriber: new_subscriber,
-                        confirmation_token: raw_task.token,
-                    },
-                )))
-            }
-            _ => {
-                delete_subscription_confirmation_task(transaction, raw_task.user).await?;
-                Ok(None)
-            }
-        }
-    } else {
-        Ok(None)
-    }
-}
-
-#[derive(Debug)]
-struct RawTask {
+#[tracing::instrument(skip_all)]
+pub async fn retry_subscription_confirmation_task(
+    transaction: &mut PgTransaction<'_>,
     user: Uuid,
-    email: String,
-    name: String,
-    token: String,
+    backoff: Duration,
+) -> Result<(), anyhow::Error> {
+    let execute_after = Utc::now() + chrono::Duration::from_std(backoff)?;
+    sqlx::query!(
+        r#"
+        UPDATE subscription_confirmation_delivery_queue
+        SET
+            n_retries = n_retries + 1,
+            execute_after = $2
+        WHERE
+            subscriber_id = $1
+        "#,
+        user,
+        execute_after
+    )
+    .execute(&mut *transaction)
+    .await?;
+    Ok(())
 }
 
-async fn dequeue_task(pool: &PgPool) -> Result<Option<(PgTransaction, RawTask)>, anyhow::Error> {
-    let mut transaction = pool.begin().await?;
-    let r = sqlx::query!(
+#[tracing::instrument(skip_all)]
+pub async fn dead_letter_subscription_confirmation_task(
+    transaction: &mut PgTransaction<'_>,
+    user: Uuid,
+    last_error: &str,
+) -> Result<(), anyhow::Error> {
+    sqlx::query!(
         r#"
-        SELECT subscriber_id
+        INSERT INTO subscription_confirmation_dead_letters (
+            subscriber_id,
+            n_attempts,
+            last_error,
+            enqueued_at,
+            failed_at
+        )
+        SELECT subscriber_id, n_retries + 1, $2, enqueued_at, now()
         FROM subscription_confirmation_delivery_queue
-        FOR UPDATE
-        SKIP LOCKED
-        LIMIT 1
-        "#
+        WHERE
+            subscriber_id = $1
+        ON CONFLICT (subscriber_id) DO UPDATE
+        SET
+            n_attempts = EXCLUDED.n_attempts,
+            last_error = EXCLUDED.last_error,
+            failed_at = EXCLUDED.failed_at
+        "#,
+        user,
+        last_error
     )
-    .fetch_optional(&mut transaction)
+    .execute(&mut *transaction)
     .await?;
+    delete_subscription_confirmation_task(transaction, user).await
+}
 
-    if r.is_none() {
-        return Ok(None);
-    }
-    let subscriber_id = r.unwrap().subscriber_id;
-
-    let r = sqlx::query!(
+#[tracing::instrument(skip_all)]
+pub async fn dequeue_subscription_confirmation_tasks(
+    transaction: &mut PgTransaction<'_>,
+    batch_size: i64,
+) -> Result<Vec<Job<SubscriptionConfirmationTask>>, anyhow::Error> {
+    let rows = sqlx::query!(
         r#"
-        SELECT id, email, name, subscriptions_token
-        FROM subscriptions
-        JOIN subscriptions_tokens
-        ON subscriber_id = subscriptions.id
-        WHERE
-            id = $1
-        LIMIT 1
+        WITH due AS (
+            SELECT subscriber_id, n_retries
+            FROM subscription_confirmation_delivery_queue
+            WHERE execute_after <= now()
+            FOR UPDATE
+            SKIP LOCKED
+            LIMIT $1
+        )
+        SELECT
+            subscriptions.id,
+            subscriptions.email,
+            subscriptions.name,
+            subscriptions.pending_email,
+            due.n_retries,
+            COALESCE(
+                array_agg(lists.list_id ORDER BY lists.created_at, lists.name)
+                    FILTER (WHERE lists.list_id IS NOT NULL),
+                '{}'
+            ) AS "list_ids!",
+            COALESCE(
+                array_agg(lists.name ORDER BY lists.created_at, lists.name)
+                    FILTER (WHERE lists.list_id IS NOT NULL),
+                '{}'
+            ) AS "list_names!"
+        FROM due
+        JOIN subscriptions
+        ON subscriptions.id = due.subscriber_id
+        LEFT JOIN list_memberships
+        ON
+            list_memberships.subscriber_id = subscriptions.id AND
+            list_memberships.status = 'pending_confirmation'
+        LEFT JOIN lists
+        ON lists.list_id = list_memberships.list_id
+        GROUP BY subscriptions.id, due.n_retries
         "#,
-        subscriber_id
+        batch_size
     )
-    .fetch_optional(&mut transaction)
+    .fetch_all(&mut *transaction)
     .await?;
-    if let Some(r) = r {
-        Ok(Some((
-            transaction,
-            RawTask {
-                user: r.id,
-                email: r.email,
-                name: r.name,
-                token: r.subscriptions_token,
+
+    Ok(rows
+        .into_iter()
+        .map(|r| Job {
+            payload: SubscriptionConfirmationTask {
+                subscriber_id: r.id,
+                subscriber_email: r.email,
+                subscriber_name: r.name,
+                pending_email: r.pending_email,
+                pending_lists: r.list_ids.into_iter().zip(r.list_names).collect(),
             },
-        )))
-    } else {
-        Ok(None)
-    }
+            n_retries: r.n_retries,
+        })
+        .collect())
 }
@@ -0,0 +1,70 @@
+use super::PgTransaction;
+use sqlx::PgPool;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SuppressionReason {
+    Bounced,
+    Complained,
+}
+
+impl SuppressionReason {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            SuppressionReason::Bounced => "bounced",
+            SuppressionReason::Complained => "complained",
+        }
+    }
+}
+
+#[tracing::instrument(skip(transaction, provider_message_id, description))]
+pub async fn suppress_email(
+    transaction: &mut PgTransaction<'_>,
+    email: &str,
+    reason: SuppressionReason,
+    provider_message_id: Option<&str>,
+    description: Option<&str>,
+) -> Result<(), sqlx::Error> {
+    sqlx::query!(
+        r#"
+        INSERT INTO email_suppressions (
+            email,
+            reason,
+            provider_message_id,
+            description,
+            suppressed_at
+        )
+        VALUES (lower($1), $2, $3, $4, now())
+        ON CONFLICT (email) DO UPDATE
+        SET
+            reason = EXCLUDED.reason,
+            provider_message_id = EXCLUDED.provider_message_id,
+            description = EXCLUDED.description,
+            suppressed_at = EXCLUDED.suppressed_at
+        "#,
+        email,
+        reason.as_str(),
+        provider_message_id,
+        description
+    )
+    .execute(&mut *transaction)
+    .await?;
+    sqlx::query!(
+        r#"UPDATE subscriptions SET status = $2 WHERE lower(email) = lower($1)"#,
+        email,
+        reason.as_str()
+    )
+    .execute(&mut *transaction)
+    .await?;
+    Ok(())
+}
+
+#[tracing::instrument(skip(pool))]
+pub async fn is_email_suppressed(pool: &PgPool, email: &str) -> Result<bool, sqlx::Error> {
+    let row = sqlx::query!(
+        r#"SELECT email FROM email_suppressions WHERE email = lower($1)"#,
+        email
+    )
+    .fetch_optional(pool)
+    .await?;
+    Ok(row.is_some())
+}
@@ -1,3 +1,4 @@
+use crate::domain::SubscriberEmail;
 use anyhow::Context;
 use sqlx::PgPool;
 use uuid::Uuid;
@@ -17,3 +18,40 @@ pub async fn get_username(user_id: Uuid, pool: &PgPool) -> Result<String, anyhow
     .context("Failed to perform query to get username.")?;
     Ok(row.username)
 }
+
+#[tracing::instrument(name = "Get user email", skip(pool))]
+pub async fn get_user_email(user_id: Uuid, pool: &PgPool) -> Result<Option<String>, anyhow::Error> {
+    let row = sqlx::query!(
+        r#"
+        SELECT email
+        FROM users
+        where user_id = $1
+        "#,
+        user_id
+    )
+    .fetch_one(pool)
+    .await
+    .context("Failed to perform query to get the user email.")?;
+    Ok(row.email)
+}
+
+#[tracing::instrument(name = "Change user email", skip(email, pool))]
+pub async fn change_user_email(
+    user_id: Uuid,
+    email: &SubscriberEmail,
+    pool: &PgPool,
+) -> Result<(), anyhow::Error> {
+    sqlx::query!(
+        r#"
+        UPDATE users
+        SET email = $2
+        WHERE user_id = $1
+        "#,
+        user_id,
+        email.as_ref()
+    )
+    .execute(pool)
+    .await
+    .context("Failed to change the user email.")?;
+    Ok(())
+}
@@ -0,0 +1,67 @@
+use rand::Rng;
+use serde_aux::field_attributes::deserialize_number_from_string;
+use std::time::Duration;
+
+#[derive(serde::Deserialize, Clone, Debug)]
+pub struct RetryPolicy {
+    #[serde(deserialize_with = "deserialize_number_from_string")]
+    pub max_attempts: i32,
+    #[serde(deserialize_with = "deserialize_number_from_string")]
+    pub base_delay_milliseconds: u64,
+    #[serde(deserialize_with = "deserialize_number_from_string")]
+    pub max_delay_milliseconds: u64,
+}
+
+impl RetryPolicy {
+    pub fn is_exhausted(&self, n_retries: i32) -> bool {
+        n_retries + 1 >= self.max_attempts
+    }
+
+    pub fn backoff(&self, n_retries: i32) -> Duration {
+        let exponent = n_retries.clamp(0, 32) as u32;
+        let delay = self
+            .base_delay_milliseconds
+            .saturating_mul(2u64.saturating_pow(exponent))
+            .min(self.max_delay_milliseconds);
+        let jittered = rand::thread_rng().gen_range(delay / 2..=delay);
+        Duration::from_millis(jittered)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::RetryPolicy;
+    use std::time::Duration;
+
+    fn policy() -> RetryPolicy {
+        RetryPolicy {
+            max_attempts: 3,
+            base_delay_milliseconds: 1000,
+            max_delay_milliseconds: 10_000,
+        }
+    }
+
+    #[test]
+    fn backoff_grows_exponentially_with_jitter() {
+        for n_retries in 0..3 {
+            let expected = 1000 * 2u64.pow(n_retries as u32);
+            let backoff = policy().backoff(n_retries);
+            assert!(backoff >= Duration::from_millis(expected / 2));
+            assert!(backoff <= Duration::from_millis(expected));
+        }
+    }
+
+    #[test]
+    fn backoff_is_capped_at_the_max_delay() {
+        let backoff = policy().backoff(1000);
+        assert!(backoff >= Duration::from_millis(5_000));
+        assert!(backoff <= Duration::from_millis(10_000));
+    }
+
+    #[test]
+    fn policy_is_exhausted_after_max_attempts() {
+        assert!(!policy().is_exhausted(0));
+        assert!(!policy().is_exhausted(1));
+        assert!(policy().is_exhausted(2));
+    }
+}
@@ -0,0 +1,25 @@
+use crate::{
+    persistence::{fetch_confirmation_dead_letters, fetch_newsletter_dead_letters},
+    templates::{render_failed_deliveries_template, GlobalContext, TemplateRegistry},
+    utils::e500,
+};
+use actix_web::{http::header::ContentType, web, HttpResponse};
+use actix_web_flash_messages::IncomingFlashMessages;
+use sqlx::PgPool;
+
+pub async fn get_failed_deliveries(
+    template_registry: web::Data<TemplateRegistry<'_>>,
+    flash_messages: IncomingFlashMessages,
+    pool: web::Data<PgPool>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let newsletter_dead_letters = fetch_newsletter_dead_letters(&pool).await.map_err(e500)?;
+    let confirmation_dead_letters = fetch_confirmation_dead_letters(&pool).await.map_err(e500)?;
+    Ok(HttpResponse::Ok().content_type(ContentType::html()).body(
+        render_failed_deliveries_template(
+            &template_registry,
+            &GlobalContext::from_incoming(flash_messages),
+            &newsletter_dead_letters,
+            &confirmation_dead_letters,
+        ),
+    ))
+}
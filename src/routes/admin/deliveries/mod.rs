@@ -0,0 +1,5 @@
+mod get;
+pub use get::get_failed_deliveries;
+
+mod post;
+pub use post::{requeue_confirmation_delivery, requeue_newsletter_deliveries};
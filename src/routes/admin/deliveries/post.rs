@@ -0,0 +1,56 @@
+use crate::persistence::{
+    requeue_confirmation_dead_letter, requeue_newsletter_dead_letter,
+    requeue_newsletter_dead_letters_for_issue,
+};
+use crate::utils::{e500, see_other};
+use actix_web::{web, HttpResponse};
+use actix_web_flash_messages::FlashMessage;
+use sqlx::PgPool;
+use uuid::Uuid;
+
+#[derive(Debug, serde::Deserialize)]
+pub struct NewsletterFormData {
+    newsletter_issue_id: Uuid,
+    subscriber_id: Option<Uuid>,
+}
+
+#[tracing::instrument(name = "Requeue failed newsletter deliveries", skip(pool))]
+pub async fn requeue_newsletter_deliveries(
+    form: web::Form<NewsletterFormData>,
+    pool: web::Data<PgPool>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let NewsletterFormData {
+        newsletter_issue_id,
+        subscriber_id,
+    } = form.0;
+    let n_requeued = match subscriber_id {
+        Some(subscriber_id) => {
+            requeue_newsletter_dead_letter(&pool, newsletter_issue_id, subscriber_id).await
+        }
+        None => requeue_newsletter_dead_letters_for_issue(&pool, newsletter_issue_id).await,
+    }
+    .map_err(e500)?;
+    requeued_message(n_requeued).send();
+    Ok(see_other("/admin/deliveries/failed"))
+}
+
+#[derive(Debug, serde::Deserialize)]
+pub struct ConfirmationFormData {
+    subscriber_id: Uuid,
+}
+
+#[tracing::instrument(name = "Requeue a failed confirmation delivery", skip(pool))]
+pub async fn requeue_confirmation_delivery(
+    form: web::Form<ConfirmationFormData>,
+    pool: web::Data<PgPool>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let n_requeued = requeue_confirmation_dead_letter(&pool, form.subscriber_id)
+        .await
+        .map_err(e500)?;
+    requeued_message(n_requeued).send();
+    Ok(see_other("/admin/deliveries/failed"))
+}
+
+fn requeued_message(n_requeued: u64) -> FlashMessage {
+    FlashMessage::info(format!("Requeued {} failed deliveries.", n_requeued))
+}
@@ -0,0 +1,25 @@
+use crate::authentication::UserId;
+use crate::persistence::get_user_email;
+use crate::templates::{render_email_template, GlobalContext, TemplateRegistry};
+use crate::utils::e500;
+use actix_web::{http::header::ContentType, web, HttpResponse};
+use actix_web_flash_messages::IncomingFlashMessages;
+use sqlx::PgPool;
+
+pub async fn change_email_form(
+    template_registry: web::Data<TemplateRegistry<'_>>,
+    flash_messages: IncomingFlashMessages,
+    pool: web::Data<PgPool>,
+    user_id: web::ReqData<UserId>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let email = get_user_email(*user_id.into_inner(), &pool)
+        .await
+        .map_err(e500)?;
+    Ok(HttpResponse::Ok()
+        .content_type(ContentType::html())
+        .body(render_email_template(
+            &template_registry,
+            &GlobalContext::from_incoming(flash_messages),
+            email.as_deref(),
+        )))
+}
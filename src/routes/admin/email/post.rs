@@ -0,0 +1,34 @@
+use crate::{
+    authentication::UserId,
+    domain::SubscriberEmail,
+    persistence::change_user_email,
+    utils::{e500, see_other},
+};
+use actix_web::{web, HttpResponse};
+use actix_web_flash_messages::FlashMessage;
+use sqlx::PgPool;
+
+#[derive(serde::Deserialize)]
+pub struct FormData {
+    email: String,
+}
+
+pub async fn change_email(
+    form: web::Form<FormData>,
+    pool: web::Data<PgPool>,
+    user_id: web::ReqData<UserId>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let user_id = user_id.into_inner();
+    let email = match SubscriberEmail::parse(form.0.email) {
+        Ok(email) => email,
+        Err(e) => {
+            FlashMessage::error(e).send();
+            return Ok(see_other("/admin/email"));
+        }
+    };
+    change_user_email(*user_id, &email, &pool)
+        .await
+        .map_err(e500)?;
+    FlashMessage::info("Your email address has been changed.").send();
+    Ok(see_other("/admin/email"))
+}
@@ -0,0 +1,21 @@
+use crate::persistence::fetch_list_summaries;
+use crate::templates::{render_lists_template, GlobalContext, TemplateRegistry};
+use crate::utils::e500;
+use actix_web::{http::header::ContentType, web, HttpResponse};
+use actix_web_flash_messages::IncomingFlashMessages;
+use sqlx::PgPool;
+
+pub async fn get_lists(
+    template_registry: web::Data<TemplateRegistry<'_>>,
+    flash_messages: IncomingFlashMessages,
+    pool: web::Data<PgPool>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let lists = fetch_list_summaries(&pool).await.map_err(e500)?;
+    Ok(HttpResponse::Ok()
+        .content_type(ContentType::html())
+        .body(render_lists_template(
+            &template_registry,
+            &GlobalContext::from_incoming(flash_messages),
+            &lists,
+        )))
+}
@@ -0,0 +1,5 @@
+mod get;
+pub use get::get_lists;
+
+mod post;
+pub use post::create_list;
@@ -0,0 +1,31 @@
+use crate::persistence::insert_list;
+use crate::utils::{e500, see_other};
+use actix_web::{web, HttpResponse};
+use actix_web_flash_messages::FlashMessage;
+use sqlx::PgPool;
+
+#[derive(Debug, serde::Deserialize)]
+pub struct FormData {
+    name: String,
+}
+
+#[tracing::instrument(name = "Create a mailing list", skip(pool))]
+pub async fn create_list(
+    form: web::Form<FormData>,
+    pool: web::Data<PgPool>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let name = form.0.name.trim().to_owned();
+    if name.is_empty() {
+        FlashMessage::error("The list must have a name.").send();
+        return Ok(see_other("/admin/lists"));
+    }
+    if name.contains("{{") || name.contains("}}") {
+        FlashMessage::error("A list name cannot contain {{ or }}.").send();
+        return Ok(see_other("/admin/lists"));
+    }
+    match insert_list(&pool, &name).await.map_err(e500)? {
+        Some(_) => FlashMessage::info(format!("The list {} has been created.", name)).send(),
+        None => FlashMessage::error(format!("A list named {} already exists.", name)).send(),
+    }
+    Ok(see_other("/admin/lists"))
+}
@@ -4,5 +4,14 @@ pub use dashboard::*;
 mod password;
 pub use password::*;
 
+mod email;
+pub use email::*;
+
+mod lists;
+pub use lists::*;
+
 mod newsletters;
 pub use newsletters::*;
+
+mod deliveries;
+pub use deliveries::*;
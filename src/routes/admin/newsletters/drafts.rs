@@ -0,0 +1,133 @@
+use super::post::{
+    draft_location, draft_not_found_message, newsletter_issue_from_form, publish, success_message,
+};
+use crate::authentication::UserId;
+use crate::idempotency::{try_processing, IdempotencyKey, NextAction};
+use crate::persistence::{
+    delete_newsletter_draft, fetch_newsletter_draft, fetch_newsletter_drafts,
+};
+use crate::templates::{render_newsletter_drafts_template, GlobalContext, TemplateRegistry};
+use crate::utils::{e400, e500, see_other};
+use actix_web::{http::header::ContentType, web, HttpResponse};
+use actix_web_flash_messages::{FlashMessage, IncomingFlashMessages};
+use sqlx::PgPool;
+use uuid::Uuid;
+
+pub async fn get_newsletter_drafts(
+    template_registry: web::Data<TemplateRegistry<'_>>,
+    flash_messages: IncomingFlashMessages,
+    pool: web::Data<PgPool>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let drafts = fetch_newsletter_drafts(&pool)
+        .await
+        .map_err(e500)?
+        .into_iter()
+        .map(|draft| {
+            let idempotency_key: IdempotencyKey = Uuid::new_v4().to_string().try_into().unwrap();
+            (draft, idempotency_key)
+        })
+        .collect::<Vec<_>>();
+    Ok(HttpResponse::Ok().content_type(ContentType::html()).body(
+        render_newsletter_drafts_template(
+            &template_registry,
+            &GlobalContext::from_incoming(flash_messages),
+            &drafts,
+        ),
+    ))
+}
+
+#[derive(Debug, serde::Deserialize)]
+pub struct PublishFormData {
+    newsletter_issue_id: Uuid,
+    idempotency_key: String,
+}
+
+#[tracing::instrument(
+    name = "Publish a newsletter draft",
+    skip_all,
+    fields(user_id=%&*user_id, newsletter_issue_id=%form.newsletter_issue_id)
+)]
+pub async fn publish_draft(
+    form: web::Form<PublishFormData>,
+    pool: web::Data<PgPool>,
+    user_id: web::ReqData<UserId>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let user_id = user_id.into_inner();
+    let PublishFormData {
+        newsletter_issue_id,
+        idempotency_key,
+    } = form.0;
+    let idempotency_key: IdempotencyKey = idempotency_key.try_into().map_err(e400)?;
+    let draft = match fetch_newsletter_draft(&pool, newsletter_issue_id)
+        .await
+        .map_err(e500)?
+    {
+        Some(draft) => draft,
+        None => return publish_missing_draft(&pool, *user_id, &idempotency_key).await,
+    };
+    let newsletter_issue = match newsletter_issue_from_form(
+        draft.title,
+        draft.text_content,
+        draft.html_content,
+        draft.markdown_content.unwrap_or_default(),
+    ) {
+        Ok(newsletter_issue) => newsletter_issue,
+        Err(validation_msgs) => {
+            for m in validation_msgs.iter() {
+                FlashMessage::error(m.to_owned()).send();
+            }
+            return Ok(see_other(&draft_location(newsletter_issue_id)));
+        }
+    };
+    publish(
+        &pool,
+        *user_id,
+        &idempotency_key,
+        &newsletter_issue,
+        draft.list_id,
+        None,
+        Some(newsletter_issue_id),
+    )
+    .await
+}
+
+async fn publish_missing_draft(
+    pool: &PgPool,
+    user_id: Uuid,
+    idempotency_key: &IdempotencyKey,
+) -> Result<HttpResponse, actix_web::Error> {
+    match try_processing(pool, idempotency_key, user_id)
+        .await
+        .map_err(e500)?
+    {
+        NextAction::ReturnSavedResponse(saved_response) => {
+            success_message().send();
+            Ok(saved_response)
+        }
+        NextAction::StartProcessing(_) => {
+            draft_not_found_message().send();
+            Ok(see_other("/admin/newsletters/drafts"))
+        }
+    }
+}
+
+#[derive(Debug, serde::Deserialize)]
+pub struct DeleteFormData {
+    newsletter_issue_id: Uuid,
+}
+
+#[tracing::instrument(name = "Delete a newsletter draft", skip(pool))]
+pub async fn delete_draft(
+    form: web::Form<DeleteFormData>,
+    pool: web::Data<PgPool>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let n_deleted = delete_newsletter_draft(&pool, form.newsletter_issue_id)
+        .await
+        .map_err(e500)?;
+    if n_deleted == 0 {
+        draft_not_found_message().send();
+    } else {
+        FlashMessage::info("The draft has been deleted.").send();
+    }
+    Ok(see_other("/admin/newsletters/drafts"))
+}
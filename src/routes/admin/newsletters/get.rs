@@ -1,14 +1,37 @@
+use super::post::draft_not_found_message;
 use crate::{
     idempotency::IdempotencyKey,
+    persistence::{fetch_lists, fetch_newsletter_draft},
     templates::{render_newsletters_template, GlobalContext, TemplateRegistry},
+    utils::{e500, see_other},
 };
 use actix_web::{http::header::ContentType, web, HttpResponse};
 use actix_web_flash_messages::IncomingFlashMessages;
+use sqlx::PgPool;
+use uuid::Uuid;
+
+#[derive(serde::Deserialize)]
+pub struct QueryParams {
+    draft: Option<Uuid>,
+}
 
 pub async fn get_newsletters_form(
     template_registry: web::Data<TemplateRegistry<'_>>,
     flash_messages: IncomingFlashMessages,
+    query: web::Query<QueryParams>,
+    pool: web::Data<PgPool>,
 ) -> Result<HttpResponse, actix_web::Error> {
+    let draft = match query.draft {
+        Some(id) => match fetch_newsletter_draft(&pool, id).await.map_err(e500)? {
+            Some(draft) => Some(draft),
+            None => {
+                draft_not_found_message().send();
+                return Ok(see_other("/admin/newsletters/drafts"));
+            }
+        },
+        None => None,
+    };
+    let lists = fetch_lists(&pool).await.map_err(e500)?;
     let idempotency_key: IdempotencyKey = uuid::Uuid::new_v4().to_string().try_into().unwrap();
     Ok(HttpResponse::Ok()
         .content_type(ContentType::html())
@@ -16,5 +39,7 @@ pub async fn get_newsletters_form(
             &template_registry,
             &GlobalContext::from_incoming(flash_messages),
             idempotency_key,
+            &lists,
+            draft.as_ref(),
         )))
 }
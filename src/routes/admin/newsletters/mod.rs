@@ -3,3 +3,14 @@ pub use post::publish_newsletter;
 
 mod get;
 pub use get::get_newsletters_form;
+
+mod scheduled;
+pub use scheduled::{
+    cancel_scheduled_newsletter, get_scheduled_newsletters, reschedule_newsletter,
+};
+
+mod drafts;
+pub use drafts::{delete_draft, get_newsletter_drafts, publish_draft};
+
+mod preview;
+pub use preview::preview_newsletter;
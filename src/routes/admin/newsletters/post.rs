@@ -1,13 +1,25 @@
 use crate::authentication::UserId;
-use crate::domain::newsletter_issue::NewsletterIssue;
+use crate::domain::merge_tags::EXAMPLE_NAME;
+use crate::domain::newsletter_issue::{NewsletterIssue, NewsletterValidationError};
+use crate::domain::{PublicationSchedule, SubscriberEmail};
+use crate::email_client::EmailTransport;
 use crate::idempotency::{save_response, try_processing, IdempotencyKey, NextAction};
+use crate::issue_delivery_worker::personalized_email;
 use crate::persistence::newsletter_delivery_task::enqueue_newsletter_delivery_tasks;
-use crate::persistence::newsletter_issue::insert_newsletter_issue;
+use crate::persistence::newsletter_issue::{
+    insert_newsletter_draft, insert_newsletter_issue, insert_scheduled_newsletter_issue,
+    publish_newsletter_draft, update_newsletter_draft,
+};
+use crate::persistence::{fetch_list_or_default, get_user_email};
+use crate::startup::{ApplicationBaseUrl, HmacSecret};
 use crate::utils::{e400, e500, see_other};
 use actix_web::{web, HttpResponse};
 use actix_web_flash_messages::FlashMessage;
 use anyhow::Context;
+use chrono::Utc;
+use secrecy::Secret;
 use sqlx::PgPool;
+use uuid::Uuid;
 
 #[derive(Debug, serde::Deserialize)]
 pub struct FormData {
@@ -15,12 +27,33 @@ pub struct FormData {
     #[serde(flatten)]
     content: Content,
     idempotency_key: String,
+    #[serde(default)]
+    scheduled_for: String,
+    #[serde(default)]
+    timezone: String,
+    #[serde(default)]
+    newsletter_issue_id: Option<Uuid>,
+    #[serde(default)]
+    list_id: Option<Uuid>,
+    #[serde(default)]
+    action: FormAction,
 }
 
 #[derive(Debug, serde::Deserialize)]
 pub struct Content {
     html: String,
     text: String,
+    #[serde(default)]
+    markdown: String,
+}
+
+#[derive(Debug, Default, serde::Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FormAction {
+    #[default]
+    Publish,
+    SaveDraft,
+    SendTest,
 }
 
 #[tracing::instrument(
@@ -31,30 +64,125 @@ pub struct Content {
 pub async fn publish_newsletter(
     form: web::Form<FormData>,
     pool: web::Data<PgPool>,
+    email_client: web::Data<dyn EmailTransport>,
+    base_url: web::Data<ApplicationBaseUrl>,
+    hmac_secret: web::Data<HmacSecret>,
     user_id: web::ReqData<UserId>,
 ) -> Result<HttpResponse, actix_web::Error> {
     let user_id = user_id.into_inner();
     let FormData {
         title,
-        content: Content {
-            html: html_content,
-            text: text_content,
-        },
+        content,
         idempotency_key,
+        scheduled_for,
+        timezone,
+        newsletter_issue_id,
+        list_id,
+        action,
     } = form.0;
 
-    let newsletter_issue = match NewsletterIssue::validate_new(title, text_content, html_content) {
-        Ok(newsletter_issue) => newsletter_issue,
-        Err(validation_msgs) => {
-            for m in validation_msgs.iter() {
-                FlashMessage::error(m.to_owned()).send();
+    let form_location = match newsletter_issue_id {
+        Some(id) => draft_location(id),
+        None => "/admin/newsletters".to_owned(),
+    };
+    let list = match fetch_list_or_default(&pool, list_id).await.map_err(e500)? {
+        Some(list) => list,
+        None => {
+            FlashMessage::error("The selected list does not exist.").send();
+            return Ok(see_other(&form_location));
+        }
+    };
+
+    if let FormAction::SaveDraft | FormAction::SendTest = action {
+        let newsletter_issue_id =
+            match upsert_draft(&pool, newsletter_issue_id, &title, &content, list.list_id).await? {
+                Some(id) => id,
+                None => {
+                    draft_not_found_message().send();
+                    return Ok(see_other("/admin/newsletters/drafts"));
+                }
+            };
+        if let FormAction::SaveDraft = action {
+            FlashMessage::info("The draft has been saved.").send();
+            return Ok(see_other(&draft_location(newsletter_issue_id)));
+        }
+        return send_test(
+            &pool,
+            email_client.as_ref(),
+            &base_url.0,
+            &hmac_secret.0,
+            *user_id,
+            newsletter_issue_id,
+            title,
+            content,
+        )
+        .await;
+    }
+    let Content {
+        html: html_content,
+        text: text_content,
+        markdown: markdown_content,
+    } = content;
+
+    if let Some(id) = newsletter_issue_id {
+        update_newsletter_draft(
+            &pool,
+            id,
+            &title,
+            &text_content,
+            &html_content,
+            &markdown_content,
+            list.list_id,
+        )
+        .await
+        .context("Failed to save the draft")
+        .map_err(e500)?;
+    }
+    let newsletter_issue =
+        newsletter_issue_from_form(title, text_content, html_content, markdown_content);
+    let schedule = if scheduled_for.is_empty() {
+        Ok(None)
+    } else {
+        PublicationSchedule::parse(&scheduled_for, &timezone, Utc::now()).map(Some)
+    };
+    let (newsletter_issue, schedule) = match (newsletter_issue, schedule) {
+        (Ok(newsletter_issue), Ok(schedule)) => (newsletter_issue, schedule),
+        (newsletter_issue, schedule) => {
+            if let Err(validation_msgs) = newsletter_issue {
+                for m in validation_msgs.iter() {
+                    FlashMessage::error(m.to_owned()).send();
+                }
+            }
+            if let Err(e) = schedule {
+                FlashMessage::error(e).send();
             }
-            return Ok(see_other("/admin/newsletters"));
+            return Ok(see_other(&form_location));
         }
     };
 
     let idempotency_key: IdempotencyKey = idempotency_key.try_into().map_err(e400)?;
-    let mut transaction = match try_processing(&pool, &idempotency_key, *user_id)
+    publish(
+        &pool,
+        *user_id,
+        &idempotency_key,
+        &newsletter_issue,
+        list.list_id,
+        schedule,
+        newsletter_issue_id,
+    )
+    .await
+}
+
+pub(super) async fn publish(
+    pool: &PgPool,
+    user_id: Uuid,
+    idempotency_key: &IdempotencyKey,
+    newsletter_issue: &NewsletterIssue,
+    list_id: Uuid,
+    schedule: Option<PublicationSchedule>,
+    draft_id: Option<Uuid>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let mut transaction = match try_processing(pool, idempotency_key, user_id)
         .await
         .map_err(e500)?
     {
@@ -64,22 +192,180 @@ pub async fn publish_newsletter(
             return Ok(saved_response);
         }
     };
-    let newsletter_issue_id = insert_newsletter_issue(&mut transaction, &newsletter_issue)
-        .await
-        .context("Failed to store newsletter details")
-        .map_err(e500)?;
-    enqueue_newsletter_delivery_tasks(&mut transaction, newsletter_issue_id)
-        .await
-        .context("Failed to enqueue delivery tasks")
-        .map_err(e500)?;
+    let newsletter_issue_id = match (draft_id, &schedule) {
+        (Some(draft_id), schedule) => {
+            let n_published = publish_newsletter_draft(
+                &mut transaction,
+                draft_id,
+                newsletter_issue,
+                list_id,
+                schedule.as_ref(),
+            )
+            .await
+            .context("Failed to publish the draft")
+            .map_err(e500)?;
+            if n_published == 0 {
+                draft_not_found_message().send();
+                return Ok(see_other("/admin/newsletters/drafts"));
+            }
+            draft_id
+        }
+        (None, Some(schedule)) => {
+            insert_scheduled_newsletter_issue(&mut transaction, newsletter_issue, list_id, schedule)
+                .await
+                .context("Failed to store newsletter details")
+                .map_err(e500)?
+        }
+        (None, None) => insert_newsletter_issue(&mut transaction, newsletter_issue, list_id)
+            .await
+            .context("Failed to store newsletter details")
+            .map_err(e500)?,
+    };
+    if schedule.is_none() {
+        enqueue_newsletter_delivery_tasks(&mut transaction, newsletter_issue_id)
+            .await
+            .context("Failed to enqueue delivery tasks")
+            .map_err(e500)?;
+    }
     let response = see_other("/admin/dashboard");
-    let response = save_response(transaction, &idempotency_key, *user_id, response)
+    let response = save_response(transaction, idempotency_key, user_id, response)
         .await
         .map_err(e500)?;
-    success_message().send();
+    match schedule {
+        Some(schedule) => scheduled_message(&schedule).send(),
+        None => success_message().send(),
+    }
     Ok(response)
 }
 
-fn success_message() -> FlashMessage {
+#[allow(clippy::too_many_arguments)]
+#[tracing::instrument(name = "Send a test copy of a newsletter issue", skip_all)]
+async fn send_test(
+    pool: &PgPool,
+    email_client: &dyn EmailTransport,
+    base_url: &str,
+    hmac_secret: &Secret<String>,
+    user_id: Uuid,
+    newsletter_issue_id: Uuid,
+    title: String,
+    content: Content,
+) -> Result<HttpResponse, actix_web::Error> {
+    let location = draft_location(newsletter_issue_id);
+    let newsletter_issue =
+        match newsletter_issue_from_form(title, content.text, content.html, content.markdown) {
+            Ok(newsletter_issue) => newsletter_issue,
+            Err(validation_msgs) => {
+                for m in validation_msgs.iter() {
+                    FlashMessage::error(m.to_owned()).send();
+                }
+                return Ok(see_other(&location));
+            }
+        };
+    let recipient = match get_user_email(user_id, pool).await.map_err(e500)? {
+        Some(email) => SubscriberEmail::parse(email).map_err(e500)?,
+        None => {
+            FlashMessage::error("Add an email address to your account to receive test copies.")
+                .send();
+            return Ok(see_other(&location));
+        }
+    };
+    let email = personalized_email(
+        recipient,
+        &newsletter_issue,
+        base_url,
+        Uuid::nil(),
+        EXAMPLE_NAME,
+        hmac_secret,
+    )
+    .map_err(e500)?;
+    match email_client
+        .send_email_with_headers(&email.recipient, &email.newsletter_issue, &email.headers)
+        .await
+    {
+        Ok(_) => FlashMessage::info(format!(
+            "A test copy has been sent to {}.",
+            email.recipient.as_ref()
+        ))
+        .send(),
+        Err(e) => {
+            tracing::warn!(
+                error.cause_chain = ?e,
+                error.message = %e,
+                "Failed to send a test copy"
+            );
+            FlashMessage::error(format!("Failed to send the test copy: {}", e)).send();
+        }
+    }
+    Ok(see_other(&location))
+}
+
+async fn upsert_draft(
+    pool: &PgPool,
+    newsletter_issue_id: Option<Uuid>,
+    title: &str,
+    content: &Content,
+    list_id: Uuid,
+) -> Result<Option<Uuid>, actix_web::Error> {
+    match newsletter_issue_id {
+        Some(id) => {
+            let n_updated = update_newsletter_draft(
+                pool,
+                id,
+                title,
+                &content.text,
+                &content.html,
+                &content.markdown,
+                list_id,
+            )
+            .await
+            .context("Failed to save the draft")
+            .map_err(e500)?;
+            Ok((n_updated > 0).then_some(id))
+        }
+        None => insert_newsletter_draft(
+            pool,
+            title,
+            &content.text,
+            &content.html,
+            &content.markdown,
+            list_id,
+        )
+        .await
+        .context("Failed to save the draft")
+        .map(Some)
+        .map_err(e500),
+    }
+}
+
+pub(super) fn newsletter_issue_from_form(
+    title: String,
+    text_content: String,
+    html_content: String,
+    markdown_content: String,
+) -> Result<NewsletterIssue, NewsletterValidationError> {
+    if markdown_content.trim().is_empty() {
+        return NewsletterIssue::validate_new(title, text_content, html_content);
+    }
+    let html_override = (!html_content.trim().is_empty()).then_some(html_content);
+    let text_override = (!text_content.trim().is_empty()).then_some(text_content);
+    NewsletterIssue::from_markdown(title, markdown_content, html_override, text_override)
+}
+
+pub(super) fn draft_location(newsletter_issue_id: Uuid) -> String {
+    format!("/admin/newsletters?draft={}", newsletter_issue_id)
+}
+
+pub(super) fn draft_not_found_message() -> FlashMessage {
+    FlashMessage::error("The draft could not be found, it may already have been sent.")
+}
+
+pub(super) fn success_message() -> FlashMessage {
     FlashMessage::info("The newsletter has been accepted.")
 }
+
+fn scheduled_message(schedule: &PublicationSchedule) -> FlashMessage {
+    FlashMessage::info(format!(
+        "The newsletter has been scheduled for {}.",
+        schedule.describe()
+    ))
+}
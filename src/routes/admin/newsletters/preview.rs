@@ -0,0 +1,47 @@
+use super::post::newsletter_issue_from_form;
+use crate::domain::merge_tags::{EXAMPLE_EMAIL, EXAMPLE_NAME};
+use crate::issue_delivery_worker::personalize_newsletter;
+use crate::startup::{ApplicationBaseUrl, HmacSecret};
+use crate::templates::{render_newsletter_preview_template, GlobalContext, TemplateRegistry};
+use crate::utils::e500;
+use actix_web::{http::header::ContentType, web, HttpResponse};
+use uuid::Uuid;
+
+#[derive(Debug, serde::Deserialize)]
+pub struct FormData {
+    title: String,
+    html: String,
+    text: String,
+    #[serde(default)]
+    markdown: String,
+}
+
+#[tracing::instrument(name = "Preview a newsletter issue", skip_all)]
+pub async fn preview_newsletter(
+    form: web::Form<FormData>,
+    template_registry: web::Data<TemplateRegistry<'_>>,
+    base_url: web::Data<ApplicationBaseUrl>,
+    hmac_secret: web::Data<HmacSecret>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let FormData {
+        title,
+        html,
+        text,
+        markdown,
+    } = form.0;
+    let preview = match newsletter_issue_from_form(title, text, html, markdown) {
+        Ok(issue) => Ok(personalize_newsletter(
+            &issue,
+            &base_url.0,
+            Uuid::nil(),
+            EXAMPLE_NAME,
+            EXAMPLE_EMAIL,
+            &hmac_secret.0,
+        )
+        .map_err(e500)?),
+        Err(e) => Err(e),
+    };
+    Ok(HttpResponse::Ok().content_type(ContentType::html()).body(
+        render_newsletter_preview_template(&template_registry, &GlobalContext::default(), &preview),
+    ))
+}
@@ -0,0 +1,90 @@
+use crate::domain::PublicationSchedule;
+use crate::persistence::{
+    cancel_scheduled_newsletter_issue, fetch_scheduled_newsletter_issues,
+    reschedule_newsletter_issue,
+};
+use crate::templates::{render_scheduled_newsletters_template, GlobalContext, TemplateRegistry};
+use crate::utils::{e500, see_other};
+use actix_web::{http::header::ContentType, web, HttpResponse};
+use actix_web_flash_messages::{FlashMessage, IncomingFlashMessages};
+use chrono::Utc;
+use sqlx::PgPool;
+use uuid::Uuid;
+
+pub async fn get_scheduled_newsletters(
+    template_registry: web::Data<TemplateRegistry<'_>>,
+    flash_messages: IncomingFlashMessages,
+    pool: web::Data<PgPool>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let scheduled_issues = fetch_scheduled_newsletter_issues(&pool)
+        .await
+        .map_err(e500)?;
+    Ok(HttpResponse::Ok().content_type(ContentType::html()).body(
+        render_scheduled_newsletters_template(
+            &template_registry,
+            &GlobalContext::from_incoming(flash_messages),
+            &scheduled_issues,
+        ),
+    ))
+}
+
+#[derive(Debug, serde::Deserialize)]
+pub struct RescheduleFormData {
+    newsletter_issue_id: Uuid,
+    scheduled_for: String,
+    timezone: String,
+}
+
+#[tracing::instrument(name = "Reschedule a newsletter issue", skip(pool))]
+pub async fn reschedule_newsletter(
+    form: web::Form<RescheduleFormData>,
+    pool: web::Data<PgPool>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let schedule = match PublicationSchedule::parse(&form.scheduled_for, &form.timezone, Utc::now())
+    {
+        Ok(schedule) => schedule,
+        Err(e) => {
+            FlashMessage::error(e).send();
+            return Ok(see_other("/admin/newsletters/scheduled"));
+        }
+    };
+    let n_updated = reschedule_newsletter_issue(&pool, form.newsletter_issue_id, &schedule)
+        .await
+        .map_err(e500)?;
+    if n_updated == 0 {
+        already_sent_message().send();
+    } else {
+        FlashMessage::info(format!(
+            "The newsletter has been rescheduled for {}.",
+            schedule.describe()
+        ))
+        .send();
+    }
+    Ok(see_other("/admin/newsletters/scheduled"))
+}
+
+#[derive(Debug, serde::Deserialize)]
+pub struct CancelFormData {
+    newsletter_issue_id: Uuid,
+}
+
+#[tracing::instrument(name = "Cancel a scheduled newsletter issue", skip(pool))]
+pub async fn cancel_scheduled_newsletter(
+    form: web::Form<CancelFormData>,
+    pool: web::Data<PgPool>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let n_cancelled = cancel_scheduled_newsletter_issue(&pool, form.newsletter_issue_id)
+        .await
+        .map_err(e500)?;
+    if n_cancelled == 0 {
+        already_sent_message().send();
+    } else {
+        FlashMessage::info("The scheduled newsletter has been cancelled and moved back to drafts.")
+            .send();
+    }
+    Ok(see_other("/admin/newsletters/scheduled"))
+}
+
+fn already_sent_message() -> FlashMessage {
+    FlashMessage::error("The newsletter is no longer scheduled, it may already have been sent.")
+}
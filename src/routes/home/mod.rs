@@ -1,15 +1,21 @@
+use crate::persistence::fetch_lists;
 use crate::templates::{render_home_template, GlobalContext, TemplateRegistry};
+use crate::utils::e500;
 use actix_web::{http::header::ContentType, web, HttpResponse};
 use actix_web_flash_messages::IncomingFlashMessages;
+use sqlx::PgPool;
 
 pub async fn home(
     template_registry: web::Data<TemplateRegistry<'_>>,
     flash_messages: IncomingFlashMessages,
-) -> HttpResponse {
-    HttpResponse::Ok()
+    pool: web::Data<PgPool>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let lists = fetch_lists(&pool).await.map_err(e500)?;
+    Ok(HttpResponse::Ok()
         .content_type(ContentType::html())
         .body(render_home_template(
             &template_registry,
             &GlobalContext::from_incoming(flash_messages),
-        ))
+            &lists,
+        )))
 }
@@ -4,6 +4,9 @@ mod home;
 mod login;
 mod subscriptions;
 mod subscriptions_confirm;
+mod subscriptions_preferences;
+mod subscriptions_unsubscribe;
+mod webhooks;
 
 pub use admin::*;
 pub use health_check::*;
@@ -11,3 +14,6 @@ pub use home::*;
 pub use login::*;
 pub use subscriptions::*;
 pub use subscriptions_confirm::*;
+pub use subscriptions_preferences::*;
+pub use subscriptions_unsubscribe::*;
+pub use webhooks::*;
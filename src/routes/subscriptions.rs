@@ -1,18 +1,24 @@
+use crate::configuration::SubscriptionSettings;
 use crate::domain::{NewSubscriber, SubscriberEmail, SubscriberName};
 
-use crate::workflows::complete_new_subscriber_workflow;
+use crate::persistence::fetch_list_or_default;
+use crate::workflows::{complete_new_subscriber_workflow, NewSubscriberOutcome};
 use actix_web::http::StatusCode;
 use actix_web::ResponseError;
 use actix_web::{web, HttpResponse};
+use anyhow::Context;
 
 use actix_web_flash_messages::FlashMessage;
 use reqwest::header::LOCATION;
 use sqlx::PgPool;
+use uuid::Uuid;
 
 #[derive(serde::Deserialize)]
 pub struct FormData {
     email: String,
     name: String,
+    #[serde(default)]
+    list_id: Option<Uuid>,
 }
 
 impl TryFrom<FormData> for NewSubscriber {
@@ -27,7 +33,7 @@ impl TryFrom<FormData> for NewSubscriber {
 
 #[tracing::instrument(
     name = "Adding a new subscriber",
-    skip(form, pool)
+    skip(form, pool, settings)
     fields(
         subscriber_email = %form.email,
         subscriber_name = %form.name
@@ -36,10 +42,37 @@ impl TryFrom<FormData> for NewSubscriber {
 pub async fn subscribe(
     form: web::Form<FormData>,
     pool: web::Data<PgPool>,
+    settings: web::Data<SubscriptionSettings>,
 ) -> Result<HttpResponse, SubscribeError> {
+    let list = fetch_list_or_default(&pool, form.list_id)
+        .await
+        .context("Failed to fetch the mailing list.")?
+        .ok_or_else(|| {
+            SubscribeError::ValidationError("The selected list does not exist.".into())
+        })?;
     let new_subscriber = form.0.try_into().map_err(SubscribeError::ValidationError)?;
-    complete_new_subscriber_workflow(&pool, new_subscriber).await?;
-    FlashMessage::info("Successfully created subscription.").send();
+    let outcome = complete_new_subscriber_workflow(
+        &pool,
+        new_subscriber,
+        list.list_id,
+        settings.confirmation_resend_interval(),
+    )
+    .await?;
+    match outcome {
+        NewSubscriberOutcome::Subscribed => {
+            FlashMessage::info("Successfully created subscription.").send()
+        }
+        NewSubscriberOutcome::ConfirmationResent => {
+            FlashMessage::info("We have sent you a new confirmation email.").send()
+        }
+        NewSubscriberOutcome::ConfirmationRecentlySent => {
+            FlashMessage::info("A confirmation email was sent recently. Please check your inbox.")
+                .send()
+        }
+        NewSubscriberOutcome::AlreadyConfirmed => {
+            FlashMessage::info(format!("You are already subscribed to {}.", list.name)).send()
+        }
+    }
     Ok(HttpResponse::SeeOther()
         .insert_header((LOCATION, "/"))
         .finish())
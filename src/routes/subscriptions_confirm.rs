@@ -1,8 +1,14 @@
-use crate::persistence::subscriber::{confirm_subscriber, get_subscriber_id_from_token};
+use crate::configuration::SubscriptionSettings;
+use crate::persistence::{confirm_subscriber, fetch_confirmation_token};
+use crate::templates::{
+    render_confirmation_template, ConfirmationPage, GlobalContext, TemplateRegistry,
+};
+use actix_web::http::header::ContentType;
 use actix_web::http::StatusCode;
 use actix_web::ResponseError;
 use actix_web::{web, HttpResponse};
 use anyhow::Context;
+use chrono::Utc;
 use sqlx::PgPool;
 
 #[derive(serde::Deserialize)]
@@ -10,23 +16,69 @@ pub struct Parameters {
     subscription_token: String,
 }
 
-#[tracing::instrument(name = "Confirm a pending registration", skip(parameters, pool))]
+#[tracing::instrument(
+    name = "Confirm a pending registration",
+    skip(parameters, pool, settings, template_registry)
+)]
 pub async fn confirm(
     parameters: web::Query<Parameters>,
     pool: web::Data<PgPool>,
+    settings: web::Data<SubscriptionSettings>,
+    template_registry: web::Data<TemplateRegistry<'_>>,
 ) -> Result<HttpResponse, ConfirmError> {
-    let id = get_subscriber_id_from_token(&pool, &parameters.subscription_token)
+    let token = match fetch_confirmation_token(&pool, &parameters.subscription_token)
         .await
-        .context("Failed to execute db query.")?;
-    match id {
-        None => Err(ConfirmError::InvalidToken),
-        Some(subscriber_id) => {
-            confirm_subscriber(&pool, subscriber_id)
-                .await
-                .context("Failed to update db")?;
-            Ok(HttpResponse::Ok().finish())
+        .context("Failed to execute db query.")?
+    {
+        Some(token) => token,
+        None => {
+            return Ok(confirmation_response(
+                &template_registry,
+                StatusCode::NOT_FOUND,
+                ConfirmationPage::Unknown,
+                "",
+            ))
         }
-    }
+    };
+    let expiry = chrono::Duration::from_std(settings.confirmation_token_expiry())
+        .context("Invalid confirmation token expiry.")?;
+    let (status, page) = if token.consumed_at.is_some() {
+        (StatusCode::GONE, ConfirmationPage::AlreadyUsed)
+    } else if token.created_at + expiry <= Utc::now() {
+        (StatusCode::GONE, ConfirmationPage::Expired)
+    } else if confirm_subscriber(&pool, &parameters.subscription_token)
+        .await
+        .context("Failed to update db")?
+    {
+        match token.new_email {
+            Some(_) => (StatusCode::OK, ConfirmationPage::EmailChanged),
+            None => (StatusCode::OK, ConfirmationPage::Confirmed),
+        }
+    } else {
+        (StatusCode::GONE, ConfirmationPage::AlreadyUsed)
+    };
+    Ok(confirmation_response(
+        &template_registry,
+        status,
+        page,
+        token.list_name.as_deref().unwrap_or_default(),
+    ))
+}
+
+fn confirmation_response(
+    template_registry: &TemplateRegistry,
+    status: StatusCode,
+    page: ConfirmationPage,
+    list_name: &str,
+) -> HttpResponse {
+    HttpResponse::build(status)
+        .content_type(ContentType::html())
+        .body(render_confirmation_template(
+            template_registry,
+            &GlobalContext::default(),
+            page,
+            list_name,
+        ))
 }
 
 #[derive(Debug)]
@@ -42,8 +94,6 @@ impl std::fmt::Display for InvalidToken {
 
 #[derive(Debug, thiserror::Error)]
 pub enum ConfirmError {
-    #[error("Invalid confirmation token")]
-    InvalidToken,
     #[error(transparent)]
     UnexpectedError(#[from] anyhow::Error),
 }
@@ -51,7 +101,6 @@ pub enum ConfirmError {
 impl ResponseError for ConfirmError {
     fn status_code(&self) -> StatusCode {
         match self {
-            ConfirmError::InvalidToken => StatusCode::UNAUTHORIZED,
             ConfirmError::UnexpectedError(_) => StatusCode::INTERNAL_SERVER_ERROR,
         }
     }
@@ -0,0 +1,230 @@
+use crate::domain::{PreferencesToken, SubscriberEmail, SubscriberName};
+use crate::persistence::{
+    fetch_list_memberships, fetch_list_or_default, fetch_subscriber_profile, leave_list,
+    pause_subscriber,
+};
+use crate::startup::HmacSecret;
+use crate::templates::{render_preferences_template, GlobalContext, TemplateRegistry};
+use crate::utils::see_other;
+use crate::workflows::{join_list_workflow, update_profile_workflow, ProfileUpdateOutcome};
+use actix_web::http::header::ContentType;
+use actix_web::http::StatusCode;
+use actix_web::ResponseError;
+use actix_web::{web, HttpResponse};
+use actix_web_flash_messages::{FlashMessage, IncomingFlashMessages};
+use anyhow::Context;
+use chrono::{Duration, Utc};
+use sqlx::PgPool;
+use uuid::Uuid;
+
+const PAUSE_DAYS: i64 = 30;
+
+#[derive(serde::Deserialize)]
+pub struct PreferencesParameters {
+    subscriber_id: Uuid,
+    token: String,
+}
+
+#[derive(serde::Deserialize)]
+pub struct PreferencesFormData {
+    subscriber_id: Uuid,
+    token: String,
+    action: PreferencesAction,
+    #[serde(default)]
+    name: String,
+    #[serde(default)]
+    email: String,
+    #[serde(default)]
+    list_id: Option<Uuid>,
+}
+
+#[derive(Debug, serde::Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PreferencesAction {
+    UpdateProfile,
+    JoinList,
+    LeaveList,
+    Pause,
+    Resume,
+}
+
+fn verify(
+    subscriber_id: Uuid,
+    token: String,
+    hmac_secret: &HmacSecret,
+) -> Result<PreferencesToken, PreferencesError> {
+    let token = PreferencesToken::from(token);
+    token
+        .verify(subscriber_id, &hmac_secret.0)
+        .map_err(PreferencesError::InvalidToken)?;
+    Ok(token)
+}
+
+fn preferences_location(subscriber_id: Uuid, token: &PreferencesToken) -> String {
+    format!(
+        "/subscriptions/preferences?subscriber_id={}&token={}",
+        subscriber_id,
+        token.as_ref()
+    )
+}
+
+#[tracing::instrument(
+    name = "Show the preference center",
+    skip(parameters, pool, hmac_secret, template_registry, flash_messages)
+)]
+pub async fn preferences_form(
+    parameters: web::Query<PreferencesParameters>,
+    pool: web::Data<PgPool>,
+    hmac_secret: web::Data<HmacSecret>,
+    template_registry: web::Data<TemplateRegistry<'_>>,
+    flash_messages: IncomingFlashMessages,
+) -> Result<HttpResponse, PreferencesError> {
+    let PreferencesParameters {
+        subscriber_id,
+        token,
+    } = parameters.0;
+    let token = verify(subscriber_id, token, &hmac_secret)?;
+    let profile = fetch_subscriber_profile(&pool, subscriber_id)
+        .await
+        .context("Failed to fetch the subscriber.")?
+        .ok_or(PreferencesError::UnknownSubscriber)?;
+    let lists = fetch_list_memberships(&pool, subscriber_id)
+        .await
+        .context("Failed to fetch the list memberships.")?;
+    Ok(HttpResponse::Ok()
+        .content_type(ContentType::html())
+        .body(render_preferences_template(
+            &template_registry,
+            &GlobalContext::from_incoming(flash_messages),
+            subscriber_id,
+            &token,
+            &profile,
+            &lists,
+        )))
+}
+
+#[tracing::instrument(
+    name = "Update subscriber preferences",
+    skip(form, pool, hmac_secret),
+    fields(subscriber_id = %form.subscriber_id, action = ?form.action)
+)]
+pub async fn update_preferences(
+    form: web::Form<PreferencesFormData>,
+    pool: web::Data<PgPool>,
+    hmac_secret: web::Data<HmacSecret>,
+) -> Result<HttpResponse, PreferencesError> {
+    let PreferencesFormData {
+        subscriber_id,
+        token,
+        action,
+        name,
+        email,
+        list_id,
+    } = form.0;
+    let token = verify(subscriber_id, token, &hmac_secret)?;
+    let location = preferences_location(subscriber_id, &token);
+    let profile = fetch_subscriber_profile(&pool, subscriber_id)
+        .await
+        .context("Failed to fetch the subscriber.")?
+        .ok_or(PreferencesError::UnknownSubscriber)?;
+
+    match action {
+        PreferencesAction::UpdateProfile => {
+            let (name, email) = match SubscriberName::parse(name)
+                .and_then(|name| SubscriberEmail::parse(email).map(|email| (name, email)))
+            {
+                Ok(parsed) => parsed,
+                Err(e) => {
+                    FlashMessage::error(e).send();
+                    return Ok(see_other(&location));
+                }
+            };
+            let new_email = Some(&email).filter(|email| email.as_ref() != profile.email);
+            match update_profile_workflow(&pool, subscriber_id, &name, new_email).await? {
+                ProfileUpdateOutcome::Updated => {
+                    FlashMessage::info("Your preferences have been updated.").send()
+                }
+                ProfileUpdateOutcome::ConfirmationRequired => FlashMessage::info(format!(
+                    "Check {} to confirm your new email address.",
+                    email.as_ref()
+                ))
+                .send(),
+                ProfileUpdateOutcome::EmailTaken => {
+                    FlashMessage::error("That email address is already subscribed.").send()
+                }
+            }
+        }
+        PreferencesAction::JoinList | PreferencesAction::LeaveList => {
+            let list = match list_id {
+                Some(list_id) => fetch_list_or_default(&pool, Some(list_id))
+                    .await
+                    .context("Failed to fetch the mailing list.")?,
+                None => None,
+            };
+            let list = match list {
+                Some(list) => list,
+                None => {
+                    FlashMessage::error("The selected list does not exist.").send();
+                    return Ok(see_other(&location));
+                }
+            };
+            if let PreferencesAction::JoinList = action {
+                if join_list_workflow(&pool, subscriber_id, list.list_id).await? {
+                    FlashMessage::info(format!(
+                        "Check your inbox to confirm your subscription to {}.",
+                        list.name
+                    ))
+                    .send();
+                } else {
+                    FlashMessage::info(format!("You are already subscribed to {}.", list.name))
+                        .send();
+                }
+            } else if leave_list(&pool, list.list_id, subscriber_id)
+                .await
+                .context("Failed to leave the list.")?
+            {
+                FlashMessage::info(format!("You have left {}.", list.name)).send();
+            } else {
+                FlashMessage::info(format!("You are not subscribed to {}.", list.name)).send();
+            }
+        }
+        PreferencesAction::Pause => {
+            let paused_until = Utc::now() + Duration::days(PAUSE_DAYS);
+            pause_subscriber(&pool, subscriber_id, Some(paused_until))
+                .await
+                .context("Failed to pause delivery.")?;
+            FlashMessage::info(format!(
+                "Delivery is paused until {}.",
+                paused_until.format("%Y-%m-%d")
+            ))
+            .send();
+        }
+        PreferencesAction::Resume => {
+            pause_subscriber(&pool, subscriber_id, None)
+                .await
+                .context("Failed to resume delivery.")?;
+            FlashMessage::info("Delivery has been resumed.").send();
+        }
+    }
+    Ok(see_other(&location))
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum PreferencesError {
+    #[error("Invalid preferences token")]
+    InvalidToken(#[source] anyhow::Error),
+    #[error("Unknown subscriber")]
+    UnknownSubscriber,
+    #[error(transparent)]
+    UnexpectedError(#[from] anyhow::Error),
+}
+
+impl ResponseError for PreferencesError {
+    fn status_code(&self) -> StatusCode {
+        match self {
+            PreferencesError::InvalidToken(_) => StatusCode::UNAUTHORIZED,
+            PreferencesError::UnknownSubscriber => StatusCode::NOT_FOUND,
+            PreferencesError::UnexpectedError(_) => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+}
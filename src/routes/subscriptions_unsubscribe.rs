@@ -0,0 +1,113 @@
+use crate::domain::UnsubscribeToken;
+use crate::persistence::subscriber::unsubscribe_subscriber;
+use crate::startup::HmacSecret;
+use crate::templates::{render_unsubscribe_template, GlobalContext, TemplateRegistry};
+use crate::utils::see_other;
+use actix_web::http::header::ContentType;
+use actix_web::http::StatusCode;
+use actix_web::ResponseError;
+use actix_web::{web, HttpResponse};
+use actix_web_flash_messages::{FlashMessage, IncomingFlashMessages};
+use anyhow::Context;
+use sqlx::PgPool;
+use uuid::Uuid;
+
+#[derive(serde::Deserialize)]
+pub struct UnsubscribeParameters {
+    subscriber_id: Uuid,
+    token: String,
+}
+
+impl UnsubscribeParameters {
+    fn verify(
+        self,
+        hmac_secret: &HmacSecret,
+    ) -> Result<(Uuid, UnsubscribeToken), UnsubscribeError> {
+        let token = UnsubscribeToken::from(self.token);
+        token
+            .verify(self.subscriber_id, &hmac_secret.0)
+            .map_err(UnsubscribeError::InvalidToken)?;
+        Ok((self.subscriber_id, token))
+    }
+}
+
+#[tracing::instrument(
+    name = "Show the unsubscribe form",
+    skip(parameters, hmac_secret, template_registry, flash_messages)
+)]
+pub async fn unsubscribe_form(
+    parameters: web::Query<UnsubscribeParameters>,
+    hmac_secret: web::Data<HmacSecret>,
+    template_registry: web::Data<TemplateRegistry<'_>>,
+    flash_messages: IncomingFlashMessages,
+) -> Result<HttpResponse, UnsubscribeError> {
+    let (subscriber_id, token) = parameters.0.verify(&hmac_secret)?;
+    Ok(HttpResponse::Ok()
+        .content_type(ContentType::html())
+        .body(render_unsubscribe_template(
+            &template_registry,
+            &GlobalContext::from_incoming(flash_messages),
+            subscriber_id,
+            &token,
+        )))
+}
+
+#[tracing::instrument(name = "Unsubscribe a subscriber", skip(form, pool, hmac_secret))]
+pub async fn unsubscribe(
+    form: web::Form<UnsubscribeParameters>,
+    pool: web::Data<PgPool>,
+    hmac_secret: web::Data<HmacSecret>,
+) -> Result<HttpResponse, UnsubscribeError> {
+    let (subscriber_id, _) = form.0.verify(&hmac_secret)?;
+    unsubscribe_subscriber(&pool, subscriber_id)
+        .await
+        .context("Failed to update db")?;
+    FlashMessage::info("You have been unsubscribed from our newsletter.").send();
+    Ok(see_other("/"))
+}
+
+#[derive(serde::Deserialize)]
+pub struct OneClickUnsubscribeBody {
+    #[serde(rename = "List-Unsubscribe")]
+    list_unsubscribe: String,
+}
+
+#[tracing::instrument(
+    name = "Unsubscribe a subscriber with one click",
+    skip(parameters, body, pool, hmac_secret)
+)]
+pub async fn one_click_unsubscribe(
+    parameters: web::Query<UnsubscribeParameters>,
+    body: web::Form<OneClickUnsubscribeBody>,
+    pool: web::Data<PgPool>,
+    hmac_secret: web::Data<HmacSecret>,
+) -> Result<HttpResponse, UnsubscribeError> {
+    if body.list_unsubscribe != "One-Click" {
+        return Err(UnsubscribeError::InvalidRequest);
+    }
+    let (subscriber_id, _) = parameters.0.verify(&hmac_secret)?;
+    unsubscribe_subscriber(&pool, subscriber_id)
+        .await
+        .context("Failed to update db")?;
+    Ok(HttpResponse::Ok().finish())
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum UnsubscribeError {
+    #[error("Invalid one-click unsubscribe request")]
+    InvalidRequest,
+    #[error("Invalid unsubscribe token")]
+    InvalidToken(#[source] anyhow::Error),
+    #[error(transparent)]
+    UnexpectedError(#[from] anyhow::Error),
+}
+
+impl ResponseError for UnsubscribeError {
+    fn status_code(&self) -> StatusCode {
+        match self {
+            UnsubscribeError::InvalidRequest => StatusCode::BAD_REQUEST,
+            UnsubscribeError::InvalidToken(_) => StatusCode::UNAUTHORIZED,
+            UnsubscribeError::UnexpectedError(_) => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+}
@@ -0,0 +1,151 @@
+use crate::authentication::Credentials;
+use crate::persistence::{suppress_email, SuppressionReason};
+use crate::routes::error_chain_fmt;
+use crate::startup::WebhookCredentials;
+use actix_web::http::header::{HeaderMap, HeaderValue};
+use actix_web::http::{header, StatusCode};
+use actix_web::{web, HttpRequest, HttpResponse, ResponseError};
+use anyhow::Context;
+use base64::Engine;
+use secrecy::{ExposeSecret, Secret};
+use sha2::{Digest, Sha256};
+use sqlx::PgPool;
+use subtle::ConstantTimeEq;
+
+#[derive(serde::Deserialize)]
+#[serde(tag = "RecordType")]
+pub enum EmailWebhook {
+    Bounce(BounceRecord),
+    SpamComplaint(BounceRecord),
+}
+
+#[derive(serde::Deserialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct BounceRecord {
+    #[serde(rename = "MessageID")]
+    message_id: Option<String>,
+    r#type: String,
+    email: String,
+    #[serde(default)]
+    inactive: bool,
+    description: Option<String>,
+}
+
+#[tracing::instrument(
+    name = "Handle an email bounce webhook",
+    skip(request, payload, pool, webhook_credentials)
+)]
+pub async fn email_bounce_webhook(
+    request: HttpRequest,
+    payload: web::Json<EmailWebhook>,
+    pool: web::Data<PgPool>,
+    webhook_credentials: web::Data<WebhookCredentials>,
+) -> Result<HttpResponse, WebhookError> {
+    let credentials = basic_authentication(request.headers()).map_err(WebhookError::AuthError)?;
+    verify_credentials(&credentials, &webhook_credentials).map_err(WebhookError::AuthError)?;
+
+    let (reason, record) = match payload.0 {
+        EmailWebhook::Bounce(record) if record.inactive => (SuppressionReason::Bounced, record),
+        EmailWebhook::Bounce(record) => {
+            tracing::info!(
+                bounce_type = %record.r#type,
+                "Ignoring a bounce that did not deactivate the recipient"
+            );
+            return Ok(HttpResponse::Ok().finish());
+        }
+        EmailWebhook::SpamComplaint(record) => (SuppressionReason::Complained, record),
+    };
+    let mut transaction = pool
+        .begin()
+        .await
+        .context("Failed to acquire a Postgres connection from the pool")?;
+    suppress_email(
+        &mut transaction,
+        &record.email,
+        reason,
+        record.message_id.as_deref(),
+        record.description.as_deref(),
+    )
+    .await
+    .context("Failed to add the address to the suppression list")?;
+    transaction
+        .commit()
+        .await
+        .context("Failed to commit the suppression")?;
+    Ok(HttpResponse::Ok().finish())
+}
+
+fn basic_authentication(headers: &HeaderMap) -> Result<Credentials, anyhow::Error> {
+    let header_value = headers
+        .get(header::AUTHORIZATION)
+        .context("The 'Authorization' header was missing")?
+        .to_str()
+        .context("The 'Authorization' header was not a valid UTF8 string.")?;
+    let base64encoded_segment = header_value
+        .strip_prefix("Basic ")
+        .context("The authorization scheme was not 'Basic'.")?;
+    let decoded_bytes = base64::engine::general_purpose::STANDARD
+        .decode(base64encoded_segment)
+        .context("Failed to base64-decode 'Basic' credentials.")?;
+    let decoded_credentials = String::from_utf8(decoded_bytes)
+        .context("The decoded credential string is not valid UTF8.")?;
+    let (username, password) = decoded_credentials
+        .split_once(':')
+        .context("The 'Basic' credentials were not of the form username:password.")?;
+    Ok(Credentials {
+        username: username.to_owned(),
+        password: Secret::new(password.to_owned()),
+    })
+}
+
+fn verify_credentials(
+    credentials: &Credentials,
+    expected: &WebhookCredentials,
+) -> Result<(), anyhow::Error> {
+    // Compare fixed-length digests in constant time so neither the username nor the
+    // password can be recovered from response timings.
+    let digest = |value: &str| Sha256::digest(value.as_bytes());
+    let username_matches = digest(&credentials.username)
+        .as_slice()
+        .ct_eq(digest(&expected.username).as_slice());
+    let password_matches = digest(credentials.password.expose_secret())
+        .as_slice()
+        .ct_eq(digest(expected.password.expose_secret()).as_slice());
+    if bool::from(username_matches & password_matches) {
+        Ok(())
+    } else {
+        Err(anyhow::anyhow!("Invalid webhook credentials"))
+    }
+}
+
+#[derive(thiserror::Error)]
+pub enum WebhookError {
+    #[error("Authentication failed")]
+    AuthError(#[source] anyhow::Error),
+    #[error(transparent)]
+    UnexpectedError(#[from] anyhow::Error),
+}
+
+impl std::fmt::Debug for WebhookError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        error_chain_fmt(self, f)
+    }
+}
+
+impl ResponseError for WebhookError {
+    fn error_response(&self) -> HttpResponse {
+        match self {
+            WebhookError::AuthError(_) => {
+                let mut response = HttpResponse::new(StatusCode::UNAUTHORIZED);
+                let header_value = HeaderValue::from_str(r#"Basic realm="webhooks""#).unwrap();
+                response
+                    .headers_mut()
+                    .insert(header::WWW_AUTHENTICATE, header_value);
+                response
+            }
+            WebhookError::UnexpectedError(_) => {
+                HttpResponse::new(StatusCode::INTERNAL_SERVER_ERROR)
+            }
+        }
+    }
+}
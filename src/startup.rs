@@ -1,9 +1,14 @@
 use crate::authentication::reject_anonymous_users;
-use crate::configuration::{DatabaseSettings, Settings};
-use crate::email_client::EmailClient;
+use crate::configuration::{DatabaseSettings, Settings, SubscriptionSettings};
+use crate::email_client::{EmailClient, EmailTransport};
 use crate::routes::{
-    admin_dashboard, change_password, change_password_form, confirm, get_newsletters_form,
-    health_check, home, log_out, login, login_form, publish_newsletter, subscribe,
+    admin_dashboard, cancel_scheduled_newsletter, change_email, change_email_form, change_password,
+    change_password_form, confirm, create_list, delete_draft, email_bounce_webhook,
+    get_failed_deliveries, get_lists, get_newsletter_drafts, get_newsletters_form,
+    get_scheduled_newsletters, health_check, home, log_out, login, login_form,
+    one_click_unsubscribe, preferences_form, preview_newsletter, publish_draft, publish_newsletter,
+    requeue_confirmation_delivery, requeue_newsletter_deliveries, reschedule_newsletter, subscribe,
+    unsubscribe, unsubscribe_form, update_preferences,
 };
 use crate::templates::register_templates;
 use actix_session::storage::RedisSessionStore;
@@ -17,6 +22,7 @@ use secrecy::{ExposeSecret, Secret};
 use sqlx::postgres::PgPoolOptions;
 use sqlx::PgPool;
 use std::net::TcpListener;
+use tokio_util::sync::CancellationToken;
 use tracing_actix_web::TracingLogger;
 
 pub struct Application {
@@ -27,6 +33,10 @@ pub struct Application {
 impl Application {
     pub async fn build(configuration: Settings) -> Result<Self, anyhow::Error> {
         let connection_pool = get_connection_pool(&configuration.database);
+        let webhook_credentials = WebhookCredentials {
+            username: configuration.email_client.webhook_username.clone(),
+            password: configuration.email_client.webhook_password.clone(),
+        };
         let email_client = configuration.email_client.client();
         let address = format!(
             "{}:{}",
@@ -40,7 +50,10 @@ impl Application {
             email_client,
             configuration.application.base_url,
             configuration.application.hmac_secret,
+            webhook_credentials,
             configuration.redis_uri,
+            configuration.subscriptions,
+            configuration.application.drain_timeout_seconds,
         )
         .await?;
 
@@ -51,7 +64,15 @@ impl Application {
         self.port
     }
 
-    pub async fn run_until_stopped(self) -> Result<(), std::io::Error> {
+    pub async fn run_until_stopped(
+        self,
+        shutdown: CancellationToken,
+    ) -> Result<(), std::io::Error> {
+        let server_handle = self.server.handle();
+        tokio::spawn(async move {
+            shutdown.cancelled().await;
+            server_handle.stop(true).await;
+        });
         self.server.await
     }
 }
@@ -62,20 +83,33 @@ pub fn get_connection_pool(configuration: &DatabaseSettings) -> PgPool {
         .connect_lazy_with(configuration.with_db())
 }
 
+pub fn get_worker_connection_pool(configuration: &DatabaseSettings, concurrency: usize) -> PgPool {
+    PgPoolOptions::new()
+        .acquire_timeout(std::time::Duration::from_secs(2))
+        .max_connections(2 * concurrency as u32 + 1)
+        .connect_lazy_with(configuration.with_db())
+}
+
 #[derive(Debug)]
 pub struct ApplicationBaseUrl(pub String);
 
+#[allow(clippy::too_many_arguments)]
 pub async fn run(
     listener: TcpListener,
     db_pool: PgPool,
     email_client: EmailClient,
     base_url: String,
     hmac_secret: Secret<String>,
+    webhook_credentials: WebhookCredentials,
     redis_uri: Secret<String>,
+    subscription_settings: SubscriptionSettings,
+    drain_timeout_seconds: u64,
 ) -> Result<Server, anyhow::Error> {
     let db_pool = Data::new(db_pool);
-    let email_client = Data::new(email_client);
+    let email_client: Data<dyn EmailTransport> = Data::from(email_client);
     let base_url = Data::new(ApplicationBaseUrl(base_url));
+    let webhook_credentials = Data::new(webhook_credentials);
+    let subscription_settings = Data::new(subscription_settings);
     let secret_key = Key::from(hmac_secret.expose_secret().as_bytes());
     let message_store = CookieMessageStore::builder(secret_key.clone()).build();
     let message_framework = FlashMessagesFramework::builder(message_store).build();
@@ -95,6 +129,27 @@ pub async fn run(
             .route("/health_check", web::get().to(health_check))
             .route("/subscriptions", web::post().to(subscribe))
             .route("/subscriptions/confirm", web::get().to(confirm))
+            .route(
+                "/subscriptions/preferences",
+                web::get().to(preferences_form),
+            )
+            .route(
+                "/subscriptions/preferences",
+                web::post().to(update_preferences),
+            )
+            .route(
+                "/subscriptions/unsubscribe",
+                web::get().to(unsubscribe_form),
+            )
+            .route("/subscriptions/unsubscribe", web::post().to(unsubscribe))
+            .route(
+                "/subscriptions/unsubscribe/one-click",
+                web::post().to(one_click_unsubscribe),
+            )
+            .route(
+                "/webhooks/email/bounce",
+                web::post().to(email_bounce_webhook),
+            )
             .service(
                 web::scope("/admin")
                     .wrap(from_fn(reject_anonymous_users))
@@ -102,19 +157,57 @@ pub async fn run(
                     .route("/logout", web::post().to(log_out))
                     .route("/newsletters", web::get().to(get_newsletters_form))
                     .route("/newsletters", web::post().to(publish_newsletter))
+                    .route("/newsletters/preview", web::post().to(preview_newsletter))
+                    .route("/newsletters/drafts", web::get().to(get_newsletter_drafts))
+                    .route("/newsletters/drafts/publish", web::post().to(publish_draft))
+                    .route("/newsletters/drafts/delete", web::post().to(delete_draft))
+                    .route(
+                        "/newsletters/scheduled",
+                        web::get().to(get_scheduled_newsletters),
+                    )
+                    .route(
+                        "/newsletters/scheduled/reschedule",
+                        web::post().to(reschedule_newsletter),
+                    )
+                    .route(
+                        "/newsletters/scheduled/cancel",
+                        web::post().to(cancel_scheduled_newsletter),
+                    )
+                    .route("/lists", web::get().to(get_lists))
+                    .route("/lists", web::post().to(create_list))
+                    .route("/deliveries/failed", web::get().to(get_failed_deliveries))
+                    .route(
+                        "/deliveries/failed/newsletters/requeue",
+                        web::post().to(requeue_newsletter_deliveries),
+                    )
+                    .route(
+                        "/deliveries/failed/confirmations/requeue",
+                        web::post().to(requeue_confirmation_delivery),
+                    )
                     .route("/password", web::get().to(change_password_form))
-                    .route("/password", web::post().to(change_password)),
+                    .route("/password", web::post().to(change_password))
+                    .route("/email", web::get().to(change_email_form))
+                    .route("/email", web::post().to(change_email)),
             )
             .app_data(db_pool.clone())
             .app_data(email_client.clone())
             .app_data(base_url.clone())
             .app_data(Data::new(HmacSecret(hmac_secret.clone())))
             .app_data(template_registry.clone())
+            .app_data(webhook_credentials.clone())
+            .app_data(subscription_settings.clone())
     })
     .listen(listener)?
+    .disable_signals()
+    .shutdown_timeout(drain_timeout_seconds)
     .run();
     Ok(server)
 }
 
 #[derive(Clone)]
 pub struct HmacSecret(pub Secret<String>);
+
+pub struct WebhookCredentials {
+    pub username: String,
+    pub password: Secret<String>,
+}
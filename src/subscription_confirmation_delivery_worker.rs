@@ -1,99 +1,292 @@
 use crate::{
     configuration::Settings,
-    domain::{NewSubscriber, NewsletterIssue},
-    email_client::EmailClient,
-    persistence::subscription_confirmation_task::{
-        delete_subscription_confirmation_task, dequeue_subscription_confirmation_task_and_parse,
+    domain::{
+        new_subscriber::generate_confirmation_token,
+        tasks::{Job, SubscriptionConfirmationTask},
+        NewSubscriber, NewsletterIssue, SubscriberEmail, SubscriberName,
     },
-    startup::{get_connection_pool, ApplicationBaseUrl},
+    email_client::{EmailClient, EmailError},
+    persistence::{
+        delete_email_change_tokens, delete_tokens, is_email_suppressed, store_email_change_token,
+        store_token,
+        subscription_confirmation_task::{
+            dead_letter_subscription_confirmation_task, delete_subscription_confirmation_task,
+            dequeue_subscription_confirmation_tasks, retry_subscription_confirmation_task,
+        },
+        PgTransaction, SUBSCRIPTION_CONFIRMATION_CHANNEL,
+    },
+    startup::{get_worker_connection_pool, ApplicationBaseUrl},
+    task_queue::{self, Task, TaskError},
 };
-use anyhow::Context;
+use anyhow::{anyhow, Context};
+use handlebars::html_escape;
 use sqlx::PgPool;
 use std::time::Duration;
+use tokio_util::sync::CancellationToken;
+use uuid::Uuid;
 
-pub enum ExecutionOutcome {
-    TaskComplete,
-    EmptyQueue,
+pub struct SubscriptionConfirmationDelivery {
+    pub email_client: EmailClient,
+    pub base_url: ApplicationBaseUrl,
 }
 
-#[tracing::instrument(skip_all)]
-pub async fn try_execute_task(
-    pool: &PgPool,
-    email_client: &EmailClient,
-    base_url: &ApplicationBaseUrl,
-) -> Result<ExecutionOutcome, anyhow::Error> {
-    match dequeue_subscription_confirmation_task_and_parse(pool).await? {
-        Some((transaction, task)) => {
-            send_confirmation_email(
-                email_client,
-                task.subscriber,
-                &base_url.0,
-                &task.confirmation_token,
-            )
-            .await
-            .context("Failed to send confirmation email.")?;
-            delete_subscription_confirmation_task(transaction, task.subscriber_id).await?;
-            Ok(ExecutionOutcome::TaskComplete)
+#[async_trait::async_trait]
+impl Task for SubscriptionConfirmationDelivery {
+    type Payload = SubscriptionConfirmationTask;
+    type Output = ();
+
+    fn name(&self) -> &'static str {
+        "subscription_confirmation_delivery"
+    }
+
+    fn channel(&self) -> &'static str {
+        SUBSCRIPTION_CONFIRMATION_CHANNEL
+    }
+
+    async fn dequeue(
+        &self,
+        transaction: &mut PgTransaction<'_>,
+        batch_size: usize,
+    ) -> Result<Vec<Job<SubscriptionConfirmationTask>>, anyhow::Error> {
+        dequeue_subscription_confirmation_tasks(transaction, batch_size as i64).await
+    }
+
+    async fn execute(
+        &self,
+        pool: &PgPool,
+        tasks: &[&SubscriptionConfirmationTask],
+    ) -> Result<Vec<Result<(), TaskError>>, TaskError> {
+        let mut outcomes = Vec::with_capacity(tasks.len());
+        for task in tasks {
+            outcomes.push(self.deliver(pool, task).await);
         }
-        None => Ok(ExecutionOutcome::EmptyQueue),
+        Ok(outcomes)
+    }
+
+    async fn complete(
+        &self,
+        transaction: &mut PgTransaction<'_>,
+        task: &SubscriptionConfirmationTask,
+        _output: (),
+    ) -> Result<(), anyhow::Error> {
+        delete_subscription_confirmation_task(transaction, task.subscriber_id).await
+    }
+
+    async fn retry(
+        &self,
+        transaction: &mut PgTransaction<'_>,
+        task: &SubscriptionConfirmationTask,
+        _error: &anyhow::Error,
+        backoff: Duration,
+    ) -> Result<(), anyhow::Error> {
+        retry_subscription_confirmation_task(transaction, task.subscriber_id, backoff).await
+    }
+
+    async fn dead_letter(
+        &self,
+        transaction: &mut PgTransaction<'_>,
+        task: &SubscriptionConfirmationTask,
+        error: &anyhow::Error,
+    ) -> Result<(), anyhow::Error> {
+        dead_letter_subscription_confirmation_task(
+            transaction,
+            task.subscriber_id,
+            &error.to_string(),
+        )
+        .await
     }
 }
 
-async fn worker_loop(
-    pool: PgPool,
-    email_client: EmailClient,
-    base_url: &ApplicationBaseUrl,
-) -> Result<(), anyhow::Error> {
-    loop {
-        match try_execute_task(&pool, &email_client, base_url).await {
-            Ok(ExecutionOutcome::EmptyQueue) => {
-                tokio::time::sleep(Duration::from_secs(10)).await;
-            }
-            Ok(ExecutionOutcome::TaskComplete) => {}
-            Err(_) => {
-                tokio::time::sleep(Duration::from_secs(1)).await;
-            }
+impl SubscriptionConfirmationDelivery {
+    #[tracing::instrument(skip_all, fields(subscriber_id=%task.subscriber_id))]
+    async fn deliver(
+        &self,
+        pool: &PgPool,
+        task: &SubscriptionConfirmationTask,
+    ) -> Result<(), TaskError> {
+        if let Some(pending_email) = &task.pending_email {
+            self.deliver_email_change(pool, task, pending_email).await?;
+        }
+        if is_email_suppressed(pool, &task.subscriber_email)
+            .await
+            .context("Failed to check the suppression list")?
+        {
+            tracing::info!("Skipping a confirmation email to a suppressed address");
+            return Ok(());
+        }
+        if task.pending_lists.is_empty() {
+            tracing::info!("Skipping a confirmation email without pending lists");
+            return Ok(());
         }
+        let email = SubscriberEmail::parse(task.subscriber_email.clone())
+            .map_err(|e| TaskError::Fatal(anyhow!(e)))?;
+        let name = SubscriberName::parse(task.subscriber_name.clone())
+            .map_err(|e| TaskError::Fatal(anyhow!(e)))?;
+        let confirmations = issue_confirmation_tokens(pool, task)
+            .await
+            .context("Failed to issue confirmation tokens")?;
+        send_confirmation_email(
+            &self.email_client,
+            NewSubscriber { email, name },
+            &self.base_url.0,
+            &confirmations,
+        )
+        .await?;
+        Ok(())
     }
+
+    /// Asks the subscriber to confirm a new address; the old one stays in use
+    /// until the link is clicked.
+    async fn deliver_email_change(
+        &self,
+        pool: &PgPool,
+        task: &SubscriptionConfirmationTask,
+        pending_email: &str,
+    ) -> Result<(), TaskError> {
+        if is_email_suppressed(pool, pending_email)
+            .await
+            .context("Failed to check the suppression list")?
+        {
+            tracing::info!("Skipping an email change confirmation to a suppressed address");
+            return Ok(());
+        }
+        let email = SubscriberEmail::parse(pending_email.to_owned())
+            .map_err(|e| TaskError::Fatal(anyhow!(e)))?;
+        let confirmation_token = issue_email_change_token(pool, task.subscriber_id, pending_email)
+            .await
+            .context("Failed to issue an email change token")?;
+        send_email_change_confirmation(
+            &self.email_client,
+            &email,
+            &self.base_url.0,
+            &confirmation_token,
+        )
+        .await?;
+        Ok(())
+    }
+}
+
+async fn issue_email_change_token(
+    pool: &PgPool,
+    subscriber_id: Uuid,
+    new_email: &str,
+) -> Result<String, anyhow::Error> {
+    let mut transaction = pool.begin().await?;
+    delete_email_change_tokens(&mut transaction, subscriber_id).await?;
+    let confirmation_token = generate_confirmation_token();
+    store_email_change_token(
+        &mut transaction,
+        subscriber_id,
+        new_email,
+        &confirmation_token,
+    )
+    .await?;
+    transaction.commit().await?;
+    Ok(confirmation_token)
+}
+
+async fn issue_confirmation_tokens(
+    pool: &PgPool,
+    task: &SubscriptionConfirmationTask,
+) -> Result<Vec<(String, String)>, anyhow::Error> {
+    let mut transaction = pool.begin().await?;
+    let mut confirmations = Vec::with_capacity(task.pending_lists.len());
+    for (list_id, list_name) in &task.pending_lists {
+        delete_tokens(&mut transaction, task.subscriber_id, Some(*list_id)).await?;
+        let confirmation_token = generate_confirmation_token();
+        store_token(
+            &mut transaction,
+            task.subscriber_id,
+            *list_id,
+            &confirmation_token,
+        )
+        .await?;
+        confirmations.push((list_name.clone(), confirmation_token));
+    }
+    transaction.commit().await?;
+    Ok(confirmations)
 }
 
-pub async fn run_worker_until_stopped(configuration: Settings) -> Result<(), anyhow::Error> {
-    let connection_pool = get_connection_pool(&configuration.database);
-    let email_client = configuration.email_client.client();
-    let base_url = ApplicationBaseUrl(configuration.application.base_url);
-    worker_loop(connection_pool, email_client, &base_url).await
+pub async fn run_worker_until_stopped(
+    configuration: Settings,
+    shutdown: CancellationToken,
+) -> Result<(), anyhow::Error> {
+    let options = configuration.workers.subscription_confirmation.options();
+    let connection_pool = get_worker_connection_pool(&configuration.database, options.concurrency);
+    let task = SubscriptionConfirmationDelivery {
+        email_client: configuration.email_client.client(),
+        base_url: ApplicationBaseUrl(configuration.application.base_url),
+    };
+    task_queue::run_until_stopped(connection_pool, task, options, shutdown).await
 }
 
 #[tracing::instrument(
     name = "Send confirmation email to a new subscriber",
-    skip(new_subscriber, email_client, base_url, confirmation_token)
+    skip(new_subscriber, email_client, base_url, confirmations)
 )]
 async fn send_confirmation_email(
     email_client: &EmailClient,
     new_subscriber: NewSubscriber,
     base_url: &str,
+    confirmations: &[(String, String)],
+) -> Result<(), EmailError> {
+    let mut plain_body = String::from("Welcome to our newsletter!");
+    let mut html_body = String::from("Welcome to our newsletter!");
+    for (list_name, confirmation_token) in confirmations {
+        let confirmation_link = format!(
+            "{}/subscriptions/confirm?subscription_token={}",
+            base_url, confirmation_token
+        );
+        plain_body.push_str(&format!(
+            "\nVisit {} to confirm your subscription to {}.",
+            confirmation_link, list_name
+        ));
+        html_body.push_str(&format!(
+            "<br />Click <a href=\"{}\">here</a> to confirm your subscription to {}.",
+            confirmation_link,
+            html_escape(list_name)
+        ));
+    }
+    // System emails carry no merge tags, so the body is sent as-is rather
+    // than validated as a newsletter.
+    let newsletter_issue =
+        NewsletterIssue::from_stored("Welcome!".to_owned(), plain_body, html_body, None);
+    email_client
+        .send_email(&new_subscriber.email, &newsletter_issue)
+        .await?;
+    Ok(())
+}
+
+#[tracing::instrument(
+    name = "Send email change confirmation to a subscriber",
+    skip(email_client, email, base_url, confirmation_token)
+)]
+async fn send_email_change_confirmation(
+    email_client: &EmailClient,
+    email: &SubscriberEmail,
+    base_url: &str,
     confirmation_token: &str,
-) -> Result<(), reqwest::Error> {
+) -> Result<(), EmailError> {
     let confirmation_link = format!(
         "{}/subscriptions/confirm?subscription_token={}",
         base_url, confirmation_token
     );
     let plain_body = format!(
-        "Welcome to our newsletter!\n\
-                Visit {} to confirm your subscription.",
+        "Visit {} to confirm your new email address.",
         confirmation_link
     );
     let html_body = format!(
-        "Welcome to our newsletter!<br />\
-                Click <a href=\"{}\">here</a> to confirm your subscription.",
+        "Click <a href=\"{}\">here</a> to confirm your new email address.",
         confirmation_link
     );
-    let newsletter_issue =
-        NewsletterIssue::validate_new("Welcome!".to_owned(), plain_body, html_body)
-            .expect("invalid newsletter");
-    email_client
-        .send_email(&new_subscriber.email, &newsletter_issue)
-        .await
+    let newsletter_issue = NewsletterIssue::from_stored(
+        "Confirm your new email address".to_owned(),
+        plain_body,
+        html_body,
+        None,
+    );
+    email_client.send_email(email, &newsletter_issue).await?;
+    Ok(())
 }
 
 #[cfg(test)]
@@ -109,6 +302,7 @@ mod test {
     use crate::{
         configuration::{DatabaseSettings, Environment},
         domain::{SubscriberEmail, SubscriberName},
+        persistence::fetch_list_or_default,
         workflows::complete_new_subscriber_workflow,
     };
 
@@ -183,21 +377,35 @@ mod test {
             email: SubscriberEmail::parse("test@test.com".to_owned()).unwrap(),
             name: SubscriberName::parse("Joe Test".to_owned()).unwrap(),
         };
-        assert!(complete_new_subscriber_workflow(&pool, new_subscriber)
+        let list = fetch_list_or_default(&pool, None)
             .await
-            .is_ok());
+            .expect("db problem")
+            .expect("no default list");
+        assert!(complete_new_subscriber_workflow(
+            &pool,
+            new_subscriber,
+            list.list_id,
+            std::time::Duration::from_secs(300)
+        )
+        .await
+        .is_ok());
 
-        let task = dequeue_subscription_confirmation_task_and_parse(&pool)
+        let mut transaction = pool.begin().await.expect("db problem");
+        let mut jobs = dequeue_subscription_confirmation_tasks(&mut transaction, 1)
             .await
             .expect("db problem");
-        assert!(task.is_some());
-        let (transaction, task) = task.unwrap();
-        let r = delete_subscription_confirmation_task(transaction, task.subscriber_id).await;
+        assert_eq!(jobs.len(), 1);
+        let job = jobs.pop().unwrap();
+        assert_eq!(job.payload.pending_lists, vec![(list.list_id, list.name)]);
+        let r = delete_subscription_confirmation_task(&mut transaction, job.payload.subscriber_id)
+            .await;
         assert!(r.is_ok());
+        transaction.commit().await.expect("db problem");
 
-        let task = dequeue_subscription_confirmation_task_and_parse(&pool)
+        let mut transaction = pool.begin().await.expect("db problem");
+        let jobs = dequeue_subscription_confirmation_tasks(&mut transaction, 1)
             .await
             .expect("db problem");
-        assert!(task.is_none());
+        assert!(jobs.is_empty());
     }
 }
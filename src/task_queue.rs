@@ -0,0 +1,263 @@
+use crate::{
+    domain::tasks::Job, email_client::EmailError, persistence::PgTransaction,
+    retry_policy::RetryPolicy,
+};
+use sqlx::{postgres::PgListener, PgPool};
+use std::{sync::Arc, time::Duration};
+use tokio::{sync::Notify, task::JoinSet};
+use tokio_util::sync::CancellationToken;
+use tracing::Span;
+
+#[async_trait::async_trait]
+pub trait Task: Send + Sync + 'static {
+    type Payload: Send + Sync;
+    type Output: Send;
+
+    fn name(&self) -> &'static str;
+
+    fn channel(&self) -> &'static str;
+
+    async fn dequeue(
+        &self,
+        transaction: &mut PgTransaction<'_>,
+        batch_size: usize,
+    ) -> Result<Vec<Job<Self::Payload>>, anyhow::Error>;
+
+    async fn execute(
+        &self,
+        pool: &PgPool,
+        payloads: &[&Self::Payload],
+    ) -> Result<Vec<Result<Self::Output, TaskError>>, TaskError>;
+
+    async fn complete(
+        &self,
+        transaction: &mut PgTransaction<'_>,
+        payload: &Self::Payload,
+        output: Self::Output,
+    ) -> Result<(), anyhow::Error>;
+
+    async fn retry(
+        &self,
+        transaction: &mut PgTransaction<'_>,
+        payload: &Self::Payload,
+        error: &anyhow::Error,
+        backoff: Duration,
+    ) -> Result<(), anyhow::Error>;
+
+    async fn dead_letter(
+        &self,
+        transaction: &mut PgTransaction<'_>,
+        payload: &Self::Payload,
+        error: &anyhow::Error,
+    ) -> Result<(), anyhow::Error>;
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum TaskError {
+    #[error(transparent)]
+    Fatal(anyhow::Error),
+    #[error(transparent)]
+    Transient(#[from] anyhow::Error),
+}
+
+impl From<EmailError> for TaskError {
+    fn from(error: EmailError) -> Self {
+        if error.is_permanent() {
+            TaskError::Fatal(error.into())
+        } else {
+            TaskError::Transient(error.into())
+        }
+    }
+}
+
+pub enum ExecutionOutcome {
+    TaskComplete,
+    EmptyQueue,
+}
+
+#[derive(Clone, Debug)]
+pub struct WorkerOptions {
+    pub concurrency: usize,
+    pub batch_size: usize,
+    pub empty_queue_sleep: Duration,
+    pub error_sleep: Duration,
+    pub retry_policy: RetryPolicy,
+}
+
+#[tracing::instrument(
+    skip_all,
+    fields(task = task.name(), batch_size = tracing::field::Empty),
+    err
+)]
+pub async fn try_execute_task<T: Task>(
+    pool: &PgPool,
+    task: &T,
+    options: &WorkerOptions,
+) -> Result<ExecutionOutcome, anyhow::Error> {
+    let mut transaction = pool.begin().await?;
+    let jobs = task.dequeue(&mut transaction, options.batch_size).await?;
+    if jobs.is_empty() {
+        transaction.commit().await?;
+        return Ok(ExecutionOutcome::EmptyQueue);
+    }
+    Span::current().record("batch_size", jobs.len());
+    let payloads: Vec<&T::Payload> = jobs.iter().map(|job| &job.payload).collect();
+    match task.execute(pool, &payloads).await {
+        Ok(outcomes) => {
+            anyhow::ensure!(
+                outcomes.len() == jobs.len(),
+                "Expected {} outcomes, got {}",
+                jobs.len(),
+                outcomes.len()
+            );
+            for (job, outcome) in jobs.iter().zip(outcomes) {
+                match outcome {
+                    Ok(output) => {
+                        task.complete(&mut transaction, &job.payload, output)
+                            .await?;
+                    }
+                    Err(e) => {
+                        handle_failure(&mut transaction, task, &options.retry_policy, job, &e)
+                            .await?;
+                    }
+                }
+            }
+        }
+        Err(e) => {
+            for job in &jobs {
+                handle_failure(&mut transaction, task, &options.retry_policy, job, &e).await?;
+            }
+        }
+    }
+    transaction.commit().await?;
+    Ok(ExecutionOutcome::TaskComplete)
+}
+
+async fn handle_failure<T: Task>(
+    transaction: &mut PgTransaction<'_>,
+    task: &T,
+    retry_policy: &RetryPolicy,
+    job: &Job<T::Payload>,
+    error: &TaskError,
+) -> Result<(), anyhow::Error> {
+    match error {
+        TaskError::Fatal(e) => {
+            tracing::error!(
+                error.cause_chain = ?e,
+                error.message = %e,
+                "Task cannot succeed, giving up",
+            );
+            task.dead_letter(transaction, &job.payload, e).await
+        }
+        TaskError::Transient(e) if retry_policy.is_exhausted(job.n_retries) => {
+            tracing::error!(
+                error.cause_chain = ?e,
+                error.message = %e,
+                n_retries = job.n_retries,
+                "Task failed, giving up",
+            );
+            task.dead_letter(transaction, &job.payload, e).await
+        }
+        TaskError::Transient(e) => {
+            let backoff = retry_policy.backoff(job.n_retries);
+            tracing::warn!(
+                error.cause_chain = ?e,
+                error.message = %e,
+                n_retries = job.n_retries,
+                "Task failed, retrying in {:?}",
+                backoff
+            );
+            task.retry(transaction, &job.payload, e, backoff).await
+        }
+    }
+}
+
+async fn worker_loop<T: Task>(
+    pool: PgPool,
+    task: Arc<T>,
+    options: Arc<WorkerOptions>,
+    wake_up: Arc<Notify>,
+    shutdown: CancellationToken,
+) -> Result<(), anyhow::Error> {
+    while !shutdown.is_cancelled() {
+        let notified = wake_up.notified();
+        match try_execute_task(&pool, task.as_ref(), &options).await {
+            Ok(ExecutionOutcome::EmptyQueue) => {
+                tokio::select! {
+                    _ = notified => {}
+                    _ = tokio::time::sleep(options.empty_queue_sleep) => {}
+                    _ = shutdown.cancelled() => {}
+                }
+            }
+            Ok(ExecutionOutcome::TaskComplete) => {}
+            Err(_) => {
+                tokio::select! {
+                    _ = tokio::time::sleep(options.error_sleep) => {}
+                    _ = shutdown.cancelled() => {}
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+async fn listen_loop(
+    pool: PgPool,
+    channel: &'static str,
+    error_sleep: Duration,
+    wake_up: Arc<Notify>,
+) {
+    loop {
+        if let Err(e) = listen(&pool, channel, &wake_up).await {
+            tracing::warn!(
+                error.cause_chain = ?e,
+                error.message = %e,
+                "Lost the connection listening on {}, falling back to polling",
+                channel
+            );
+            tokio::time::sleep(error_sleep).await;
+        }
+    }
+}
+
+async fn listen(pool: &PgPool, channel: &str, wake_up: &Notify) -> Result<(), sqlx::Error> {
+    let mut listener = PgListener::connect_with(pool).await?;
+    listener.listen(channel).await?;
+    wake_up.notify_waiters();
+    loop {
+        listener.recv().await?;
+        wake_up.notify_waiters();
+    }
+}
+
+pub async fn run_until_stopped<T: Task>(
+    pool: PgPool,
+    task: T,
+    options: WorkerOptions,
+    shutdown: CancellationToken,
+) -> Result<(), anyhow::Error> {
+    let wake_up = Arc::new(Notify::new());
+    let listener = tokio::spawn(listen_loop(
+        pool.clone(),
+        task.channel(),
+        options.error_sleep,
+        wake_up.clone(),
+    ));
+    let task = Arc::new(task);
+    let options = Arc::new(options);
+    let mut workers = JoinSet::new();
+    for _ in 0..options.concurrency {
+        workers.spawn(worker_loop(
+            pool.clone(),
+            task.clone(),
+            options.clone(),
+            wake_up.clone(),
+            shutdown.clone(),
+        ));
+    }
+    while let Some(outcome) = workers.join_next().await {
+        outcome??;
+    }
+    listener.abort();
+    Ok(())
+}
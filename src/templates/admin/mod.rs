@@ -1,4 +1,10 @@
+use crate::domain::newsletter_issue::NewsletterValidationError;
+use crate::domain::{timezone_names, NewsletterIssue};
 use crate::idempotency::IdempotencyKey;
+use crate::persistence::{
+    ConfirmationDeadLetter, MailingList, MailingListSummary, NewsletterDeadLetter, NewsletterDraft,
+    ScheduledNewsletterIssue,
+};
 
 use super::{GlobalContext, TemplateRegistry};
 
@@ -9,12 +15,44 @@ pub fn render_password_template(
     template_registry.render_with_default_layout("password", "Change Password", global_context)
 }
 
+pub fn render_email_template(
+    template_registry: &TemplateRegistry,
+    global_context: &GlobalContext,
+    email: Option<&str>,
+) -> String {
+    let data = serde_json::json!({ "email": email });
+    template_registry.render_data_with_default_layout(
+        "email",
+        "Change Email",
+        global_context,
+        &data,
+    )
+}
+
 pub fn render_newsletters_template(
     template_registry: &TemplateRegistry,
     global_context: &GlobalContext,
     idempotency_key: IdempotencyKey,
+    lists: &[MailingList],
+    draft: Option<&NewsletterDraft>,
 ) -> String {
-    let data = serde_json::json!({"idempotency_key": idempotency_key});
+    let lists = lists
+        .iter()
+        .map(|list| {
+            serde_json::json!({
+                "list_id": list.list_id,
+                "name": list.name,
+                "selected": draft.map(|d| d.list_id) == Some(list.list_id),
+            })
+        })
+        .collect::<Vec<_>>();
+    let data = serde_json::json!({
+        "idempotency_key": idempotency_key,
+        "lists": lists,
+        "timezones": timezone_names(),
+        "draft": draft,
+        "timezone": draft.and_then(|d| d.timezone.as_deref()).unwrap_or("UTC"),
+    });
     template_registry.render_data_with_default_layout(
         "newsletters",
         "Create newsletter",
@@ -23,6 +61,20 @@ pub fn render_newsletters_template(
     )
 }
 
+pub fn render_lists_template(
+    template_registry: &TemplateRegistry,
+    global_context: &GlobalContext,
+    lists: &[MailingListSummary],
+) -> String {
+    let data = serde_json::json!({ "lists": lists });
+    template_registry.render_data_with_default_layout(
+        "lists",
+        "Mailing lists",
+        global_context,
+        &data,
+    )
+}
+
 pub fn render_admin_dashboard(
     template_registry: &TemplateRegistry,
     global_context: &GlobalContext,
@@ -36,3 +88,96 @@ pub fn render_admin_dashboard(
         &data,
     )
 }
+
+pub fn render_failed_deliveries_template(
+    template_registry: &TemplateRegistry,
+    global_context: &GlobalContext,
+    newsletter_dead_letters: &[NewsletterDeadLetter],
+    confirmation_dead_letters: &[ConfirmationDeadLetter],
+) -> String {
+    let mut newsletter_issues: Vec<serde_json::Value> = vec![];
+    for dead_letter in newsletter_dead_letters {
+        let issue = serde_json::json!({
+            "newsletter_issue_id": dead_letter.newsletter_issue_id,
+            "title": dead_letter.issue_title,
+        });
+        if !newsletter_issues.contains(&issue) {
+            newsletter_issues.push(issue);
+        }
+    }
+    let data = serde_json::json!({
+        "newsletter_issues": newsletter_issues,
+        "newsletter_dead_letters": newsletter_dead_letters,
+        "confirmation_dead_letters": confirmation_dead_letters,
+    });
+    template_registry.render_data_with_default_layout(
+        "failed_deliveries",
+        "Failed deliveries",
+        global_context,
+        &data,
+    )
+}
+
+pub fn render_scheduled_newsletters_template(
+    template_registry: &TemplateRegistry,
+    global_context: &GlobalContext,
+    scheduled_issues: &[ScheduledNewsletterIssue],
+) -> String {
+    let data = serde_json::json!({
+        "scheduled_issues": scheduled_issues,
+        "timezones": timezone_names(),
+    });
+    template_registry.render_data_with_default_layout(
+        "scheduled_newsletters",
+        "Scheduled newsletters",
+        global_context,
+        &data,
+    )
+}
+
+pub fn render_newsletter_drafts_template(
+    template_registry: &TemplateRegistry,
+    global_context: &GlobalContext,
+    drafts: &[(NewsletterDraft, IdempotencyKey)],
+) -> String {
+    let drafts = drafts
+        .iter()
+        .map(|(draft, idempotency_key)| {
+            serde_json::json!({
+                "newsletter_issue_id": draft.newsletter_issue_id,
+                "title": draft.title,
+                "idempotency_key": idempotency_key,
+            })
+        })
+        .collect::<Vec<_>>();
+    let data = serde_json::json!({ "drafts": drafts });
+    template_registry.render_data_with_default_layout(
+        "newsletter_drafts",
+        "Newsletter drafts",
+        global_context,
+        &data,
+    )
+}
+
+pub fn render_newsletter_preview_template(
+    template_registry: &TemplateRegistry,
+    global_context: &GlobalContext,
+    preview: &Result<NewsletterIssue, NewsletterValidationError>,
+) -> String {
+    let data = match preview {
+        Ok(issue) => serde_json::json!({
+            "subject": issue.title(),
+            "html": issue.html(),
+            "text": issue.text(),
+        }),
+        Err(validation_msgs) => serde_json::json!({
+            "errors": validation_msgs.iter().collect::<Vec<_>>(),
+        }),
+    };
+    template_registry.render_data_with_default_layout(
+        "newsletter_preview",
+        "Newsletter preview",
+        global_context,
+        &data,
+    )
+}
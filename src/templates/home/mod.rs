@@ -1,8 +1,11 @@
 use super::{GlobalContext, TemplateRegistry};
+use crate::persistence::MailingList;
 
 pub fn render_home_template(
     template_registry: &TemplateRegistry,
     global_context: &GlobalContext,
+    lists: &[MailingList],
 ) -> String {
-    template_registry.render_with_default_layout("home", "Home", global_context)
+    let data = serde_json::json!({ "lists": lists });
+    template_registry.render_data_with_default_layout("home", "Home", global_context, &data)
 }
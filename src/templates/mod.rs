@@ -10,6 +10,9 @@ pub use login::*;
 mod home;
 pub use home::*;
 
+mod subscriptions;
+pub use subscriptions::*;
+
 #[cfg(test)]
 mod test_helpers;
 #[cfg(test)]
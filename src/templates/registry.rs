@@ -86,42 +86,94 @@ pub struct FlashMessagePresentation {
 
 pub fn register_templates<'reg>() -> TemplateRegistry<'reg> {
     let mut handlebars = Handlebars::new();
+    handlebars.set_prevent_indent(true);
     handlebars
         .register_template_file(
             "default_layout",
-            &template_root(&["layouts", "default.html"]),
+            template_root(&["layouts", "default.html"]),
         )
         .expect("Failed to load template");
     handlebars
-        .register_template_file("home", &template_root(&["home", "home.html"]))
+        .register_template_file("home", template_root(&["home", "home.html"]))
         .expect("Failed to load template");
     handlebars
         .register_template_file(
             "admin_dashboard",
-            &template_root(&["admin", "dashboard", "get.html"]),
+            template_root(&["admin", "dashboard", "get.html"]),
         )
         .expect("Failed to load template");
     handlebars
         .register_template_file(
             "password",
-            &template_root(&["admin", "password", "get.html"]),
+            template_root(&["admin", "password", "get.html"]),
         )
         .expect("Failed to load template");
+    handlebars
+        .register_template_file("email", template_root(&["admin", "email", "get.html"]))
+        .expect("Failed to load template");
     handlebars
         .register_template_file(
             "newsletters",
-            &template_root(&["admin", "newsletters", "get.html"]),
+            template_root(&["admin", "newsletters", "get.html"]),
+        )
+        .expect("Failed to load template");
+    handlebars
+        .register_template_file(
+            "failed_deliveries",
+            template_root(&["admin", "deliveries", "get.html"]),
+        )
+        .expect("Failed to load template");
+    handlebars
+        .register_template_file(
+            "scheduled_newsletters",
+            template_root(&["admin", "newsletters", "scheduled.html"]),
+        )
+        .expect("Failed to load template");
+    handlebars
+        .register_template_file(
+            "newsletter_preview",
+            template_root(&["admin", "newsletters", "preview.html"]),
+        )
+        .expect("Failed to load template");
+    handlebars
+        .register_template_file("lists", template_root(&["admin", "lists", "get.html"]))
+        .expect("Failed to load template");
+    handlebars
+        .register_template_file(
+            "newsletter_drafts",
+            template_root(&["admin", "newsletters", "drafts.html"]),
         )
         .expect("Failed to load template");
     handlebars
-        .register_template_file("login", &template_root(&["login", "get.html"]))
+        .register_template_file("login", template_root(&["login", "get.html"]))
+        .expect("Failed to load template");
+    handlebars
+        .register_template_file(
+            "subscription_confirmation",
+            template_root(&["subscriptions", "confirm.html"]),
+        )
+        .expect("Failed to load template");
+    handlebars
+        .register_template_file(
+            "preferences",
+            template_root(&["subscriptions", "preferences.html"]),
+        )
+        .expect("Failed to load template");
+    handlebars
+        .register_template_file(
+            "unsubscribe",
+            template_root(&["subscriptions", "unsubscribe.html"]),
+        )
         .expect("Failed to load template");
     handlebars
         .register_template_file(
             "flash_messages",
-            &template_root(&["partials", "flash_messages.html"]),
+            template_root(&["partials", "flash_messages.html"]),
         )
         .expect("Failed to load template");
+    handlebars
+        .register_template_file("timezones", template_root(&["partials", "timezones.html"]))
+        .expect("Failed to load template");
     handlebars
         .register_template_string("blank", "")
         .expect("Failed to load template");
@@ -157,6 +209,6 @@ mod test {
         let html = Html::parse_fragment(&html);
         let p = assert_and_get_element(&html.root_element(), "p");
         let i = assert_and_get_element(&p, "i");
-        assert_eq!(i.inner_html(), flash_messages.get(0).unwrap().content());
+        assert_eq!(i.inner_html(), flash_messages.first().unwrap().content());
     }
 }
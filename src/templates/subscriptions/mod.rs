@@ -0,0 +1,98 @@
+use super::{GlobalContext, TemplateRegistry};
+use crate::domain::{PreferencesToken, UnsubscribeToken};
+use crate::persistence::{ListMembership, SubscriberProfile};
+use uuid::Uuid;
+
+pub fn render_unsubscribe_template(
+    template_registry: &TemplateRegistry,
+    global_context: &GlobalContext,
+    subscriber_id: Uuid,
+    token: &UnsubscribeToken,
+) -> String {
+    let data = serde_json::json!({
+        "subscriber_id": subscriber_id,
+        "token": token.as_ref(),
+    });
+    template_registry.render_data_with_default_layout(
+        "unsubscribe",
+        "Unsubscribe",
+        global_context,
+        &data,
+    )
+}
+
+pub fn render_preferences_template(
+    template_registry: &TemplateRegistry,
+    global_context: &GlobalContext,
+    subscriber_id: Uuid,
+    token: &PreferencesToken,
+    profile: &SubscriberProfile,
+    lists: &[ListMembership],
+) -> String {
+    let lists = lists
+        .iter()
+        .map(|list| {
+            let status = match list.status.as_deref() {
+                Some("confirmed") => "Subscribed",
+                Some("pending_confirmation") => "Awaiting confirmation",
+                _ => "Not subscribed",
+            };
+            serde_json::json!({
+                "list_id": list.list_id,
+                "name": list.name,
+                "status": status,
+                "member": matches!(list.status.as_deref(), Some("confirmed" | "pending_confirmation")),
+            })
+        })
+        .collect::<Vec<_>>();
+    let paused_until = profile
+        .paused_until
+        .filter(|until| *until > chrono::Utc::now())
+        .map(|until| until.format("%Y-%m-%d").to_string());
+    let data = serde_json::json!({
+        "subscriber_id": subscriber_id,
+        "token": token.as_ref(),
+        "profile": {
+            "name": profile.name,
+            "email": profile.email,
+        },
+        "lists": lists,
+        "paused_until": paused_until,
+    });
+    template_registry.render_data_with_default_layout(
+        "preferences",
+        "Preferences",
+        global_context,
+        &data,
+    )
+}
+
+pub enum ConfirmationPage {
+    Confirmed,
+    EmailChanged,
+    Expired,
+    AlreadyUsed,
+    Unknown,
+}
+
+pub fn render_confirmation_template(
+    template_registry: &TemplateRegistry,
+    global_context: &GlobalContext,
+    page: ConfirmationPage,
+    list_name: &str,
+) -> String {
+    let data = serde_json::json!({
+        "list_name": list_name,
+        "confirmed": matches!(page, ConfirmationPage::Confirmed),
+        "email_changed": matches!(page, ConfirmationPage::EmailChanged),
+        "expired": matches!(page, ConfirmationPage::Expired),
+        "used": matches!(page, ConfirmationPage::AlreadyUsed),
+        "unknown": matches!(page, ConfirmationPage::Unknown),
+    });
+    template_registry.render_data_with_default_layout(
+        "subscription_confirmation",
+        "Confirm your subscription",
+        global_context,
+        &data,
+    )
+}
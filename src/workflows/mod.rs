@@ -1,2 +1,4 @@
 pub mod new_subscriber;
 pub use new_subscriber::*;
+pub mod preferences;
+pub use preferences::*;
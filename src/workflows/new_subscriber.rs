@@ -1,22 +1,60 @@
 use crate::{
-    domain::{new_subscriber::generate_confirmation_token, NewSubscriber},
-    persistence::{insert_subscriber, insert_subscription_confirmation_task, store_token},
+    domain::NewSubscriber,
+    persistence::{
+        claim_confirmation_resend, fetch_list_membership_status, insert_list_membership,
+        insert_subscriber, insert_subscription_confirmation_task, rejoin_list,
+    },
 };
 use anyhow::Context;
 use sqlx::PgPool;
+use std::time::Duration;
+use uuid::Uuid;
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum NewSubscriberOutcome {
+    Subscribed,
+    ConfirmationResent,
+    ConfirmationRecentlySent,
+    AlreadyConfirmed,
+}
 
 pub async fn complete_new_subscriber_workflow(
     pool: &PgPool,
     new_subscriber: NewSubscriber,
-) -> Result<(), anyhow::Error> {
+    list_id: Uuid,
+    resend_interval: Duration,
+) -> Result<NewSubscriberOutcome, anyhow::Error> {
     let mut transaction = pool.begin().await.context("Failed to connect to db pool")?;
     let subscriber_id = insert_subscriber(&mut transaction, &new_subscriber)
         .await
         .context("Failed to insert subscriber into db.")?;
-    let confirmation_token = generate_confirmation_token();
-    store_token(&mut transaction, subscriber_id, &confirmation_token)
+    let status = fetch_list_membership_status(&mut transaction, list_id, subscriber_id)
         .await
-        .context("Failed to store the confirmation token for a new subscription.")?;
+        .context("Failed to fetch the list membership.")?;
+    let outcome = match status.as_deref() {
+        None => {
+            insert_list_membership(&mut transaction, list_id, subscriber_id)
+                .await
+                .context("Failed to add the subscriber to the list.")?;
+            NewSubscriberOutcome::Subscribed
+        }
+        Some("confirmed") => return Ok(NewSubscriberOutcome::AlreadyConfirmed),
+        Some("pending_confirmation") => {
+            if !claim_confirmation_resend(&mut transaction, subscriber_id, resend_interval)
+                .await
+                .context("Failed to rate-limit the confirmation email.")?
+            {
+                return Ok(NewSubscriberOutcome::ConfirmationRecentlySent);
+            }
+            NewSubscriberOutcome::ConfirmationResent
+        }
+        Some(_) => {
+            rejoin_list(&mut transaction, list_id, subscriber_id)
+                .await
+                .context("Failed to add the subscriber to the list.")?;
+            NewSubscriberOutcome::Subscribed
+        }
+    };
     transaction
         .commit()
         .await
@@ -24,5 +62,5 @@ pub async fn complete_new_subscriber_workflow(
     insert_subscription_confirmation_task(subscriber_id, pool)
         .await
         .context("Failed to enqueue subscriber confirmation task")?;
-    Ok(())
+    Ok(outcome)
 }
@@ -0,0 +1,78 @@
+use crate::{
+    domain::{SubscriberEmail, SubscriberName},
+    persistence::{
+        delete_email_change_tokens, insert_subscription_confirmation_task, rejoin_list,
+        request_subscriber_email_change, update_subscriber_name,
+    },
+};
+use anyhow::Context;
+use sqlx::PgPool;
+use uuid::Uuid;
+
+pub async fn join_list_workflow(
+    pool: &PgPool,
+    subscriber_id: Uuid,
+    list_id: Uuid,
+) -> Result<bool, anyhow::Error> {
+    let mut transaction = pool.begin().await.context("Failed to connect to db pool")?;
+    let joined = rejoin_list(&mut transaction, list_id, subscriber_id)
+        .await
+        .context("Failed to add the subscriber to the list.")?;
+    if !joined {
+        return Ok(false);
+    }
+    transaction
+        .commit()
+        .await
+        .context("Failed to commit db transaction.")?;
+    insert_subscription_confirmation_task(subscriber_id, pool)
+        .await
+        .context("Failed to enqueue subscriber confirmation task")?;
+    Ok(true)
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum ProfileUpdateOutcome {
+    Updated,
+    ConfirmationRequired,
+    EmailTaken,
+}
+
+pub async fn update_profile_workflow(
+    pool: &PgPool,
+    subscriber_id: Uuid,
+    name: &SubscriberName,
+    new_email: Option<&SubscriberEmail>,
+) -> Result<ProfileUpdateOutcome, anyhow::Error> {
+    let mut transaction = pool.begin().await.context("Failed to connect to db pool")?;
+    update_subscriber_name(&mut transaction, subscriber_id, name)
+        .await
+        .context("Failed to update the subscriber name.")?;
+    let email = match new_email {
+        Some(email) => email,
+        None => {
+            transaction
+                .commit()
+                .await
+                .context("Failed to commit db transaction.")?;
+            return Ok(ProfileUpdateOutcome::Updated);
+        }
+    };
+    if !request_subscriber_email_change(&mut transaction, subscriber_id, email)
+        .await
+        .context("Failed to record the email change.")?
+    {
+        return Ok(ProfileUpdateOutcome::EmailTaken);
+    }
+    delete_email_change_tokens(&mut transaction, subscriber_id)
+        .await
+        .context("Failed to delete stale email change tokens.")?;
+    transaction
+        .commit()
+        .await
+        .context("Failed to commit db transaction.")?;
+    insert_subscription_confirmation_task(subscriber_id, pool)
+        .await
+        .context("Failed to enqueue subscriber confirmation task")?;
+    Ok(ProfileUpdateOutcome::ConfirmationRequired)
+}
@@ -0,0 +1,47 @@
+use crate::helpers::{assert_is_redirect_to_, spawn_app, spawn_app_logged_in};
+
+#[tokio::test]
+async fn you_must_be_logged_in_to_change_your_email() {
+    let app = spawn_app().await;
+
+    let response = app.get_change_email().await;
+
+    assert_is_redirect_to_(&response, "/login");
+}
+
+#[tokio::test]
+async fn the_form_shows_the_current_email() {
+    let app = spawn_app_logged_in().await;
+
+    let html = app.get_change_email_html().await;
+
+    assert!(html.contains(&app.test_user.email));
+}
+
+#[tokio::test]
+async fn changing_email_works() {
+    let app = spawn_app_logged_in().await;
+
+    let response = app
+        .post_change_email(&serde_json::json!({ "email": "editor@example.com" }))
+        .await;
+    assert_is_redirect_to_(&response, "/admin/email");
+
+    let html = app.get_change_email_html().await;
+    assert!(html.contains("Your email address has been changed."));
+    assert!(html.contains("editor@example.com"));
+}
+
+#[tokio::test]
+async fn an_invalid_email_is_rejected() {
+    let app = spawn_app_logged_in().await;
+
+    let response = app
+        .post_change_email(&serde_json::json!({ "email": "not-an-email" }))
+        .await;
+    assert_is_redirect_to_(&response, "/admin/email");
+
+    let html = app.get_change_email_html().await;
+    assert!(html.contains("not-an-email"));
+    assert!(html.contains(&app.test_user.email));
+}
@@ -0,0 +1,162 @@
+use crate::helpers::{
+    assert_is_redirect_to_, create_confirmed_subscriber, spawn_app, spawn_app_logged_in,
+    PostmarkAccepted, TestApp,
+};
+use uuid::Uuid;
+use wiremock::{matchers::any, Mock, ResponseTemplate};
+
+async fn dead_letter_a_newsletter_delivery(app: &mut TestApp) -> Uuid {
+    app.newsletter_worker_options.retry_policy.max_attempts = 1;
+    let _mock_guard = Mock::given(any())
+        .respond_with(ResponseTemplate::new(500))
+        .expect(1)
+        .mount_as_scoped(&app.email_server)
+        .await;
+    app.publish_newsletter().await;
+    app.dispatch_all_pending_emails().await;
+
+    sqlx::query!("SELECT newsletter_issue_id FROM issue_delivery_dead_letters")
+        .fetch_one(&app.connection_pool)
+        .await
+        .expect("Failed to fetch query")
+        .newsletter_issue_id
+}
+
+async fn issue_status(app: &TestApp) -> String {
+    sqlx::query!("SELECT status FROM newsletter_issues")
+        .fetch_one(&app.connection_pool)
+        .await
+        .expect("Failed to fetch query")
+        .status
+}
+
+#[tokio::test]
+async fn you_must_be_logged_in_to_see_failed_deliveries() {
+    let app = spawn_app().await;
+
+    let response = app.get_failed_deliveries().await;
+
+    assert_is_redirect_to_(&response, "/login");
+}
+
+#[tokio::test]
+async fn dead_lettered_newsletter_deliveries_are_listed() {
+    let mut app = spawn_app_logged_in().await;
+    create_confirmed_subscriber(&app).await;
+    dead_letter_a_newsletter_delivery(&mut app).await;
+
+    let html = app.get_failed_deliveries_html().await;
+
+    assert!(html.contains("Newsletter title"));
+    assert!(html.contains("500 Internal Server Error"));
+    assert!(html.contains("Requeue all for Newsletter title"));
+}
+
+#[tokio::test]
+async fn requeueing_a_newsletter_delivery_delivers_it_again() {
+    let mut app = spawn_app_logged_in().await;
+    create_confirmed_subscriber(&app).await;
+    let newsletter_issue_id = dead_letter_a_newsletter_delivery(&mut app).await;
+    let subscriber_id = sqlx::query!("SELECT id FROM subscriptions")
+        .fetch_one(&app.connection_pool)
+        .await
+        .expect("Failed to fetch query")
+        .id;
+
+    let response = app
+        .post_requeue_newsletter_deliveries(&serde_json::json!({
+            "newsletter_issue_id": newsletter_issue_id,
+            "subscriber_id": subscriber_id,
+        }))
+        .await;
+    assert_is_redirect_to_(&response, "/admin/deliveries/failed");
+
+    let html = app.get_failed_deliveries_html().await;
+    assert!(html.contains("Requeued 1 failed deliveries."));
+    assert!(html.contains("There are no failed newsletter deliveries."));
+    assert_eq!(issue_status(&app).await, "sending");
+
+    Mock::given(any())
+        .respond_with(PostmarkAccepted)
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+    app.dispatch_all_pending_emails().await;
+    assert_eq!(issue_status(&app).await, "sent");
+}
+
+#[tokio::test]
+async fn requeueing_all_deliveries_for_an_issue_empties_its_dead_letters() {
+    let mut app = spawn_app_logged_in().await;
+    create_confirmed_subscriber(&app).await;
+    create_confirmed_subscriber(&app).await;
+    app.newsletter_worker_options.retry_policy.max_attempts = 1;
+    let _mock_guard = Mock::given(any())
+        .respond_with(ResponseTemplate::new(500))
+        .expect(1)
+        .mount_as_scoped(&app.email_server)
+        .await;
+    app.publish_newsletter().await;
+    app.dispatch_all_pending_emails().await;
+    let newsletter_issue_id = sqlx::query!("SELECT newsletter_issue_id FROM newsletter_issues")
+        .fetch_one(&app.connection_pool)
+        .await
+        .expect("Failed to fetch query")
+        .newsletter_issue_id;
+
+    let response = app
+        .post_requeue_newsletter_deliveries(&serde_json::json!({
+            "newsletter_issue_id": newsletter_issue_id,
+        }))
+        .await;
+    assert_is_redirect_to_(&response, "/admin/deliveries/failed");
+
+    let html = app.get_failed_deliveries_html().await;
+    assert!(html.contains("Requeued 2 failed deliveries."));
+    let queued = sqlx::query!("SELECT COUNT(*) AS \"count!\" FROM issue_delievery_queue")
+        .fetch_one(&app.connection_pool)
+        .await
+        .expect("Failed to fetch query");
+    assert_eq!(queued.count, 2);
+}
+
+#[tokio::test]
+async fn dead_lettered_confirmation_deliveries_can_be_requeued() {
+    let mut app = spawn_app_logged_in().await;
+    app.confirmation_worker_options.retry_policy.max_attempts = 1;
+    let mock_guard = Mock::given(any())
+        .respond_with(ResponseTemplate::new(500))
+        .expect(1)
+        .mount_as_scoped(&app.email_server)
+        .await;
+    app.post_subscriptions("name=le%20guin&email=ursula_le_guin%40gmail.com".into())
+        .await;
+    app.dispatch_all_pending_emails().await;
+    drop(mock_guard);
+
+    let html = app.get_failed_deliveries_html().await;
+    assert!(html.contains("ursula_le_guin@gmail.com"));
+
+    let subscriber_id =
+        sqlx::query!("SELECT subscriber_id FROM subscription_confirmation_dead_letters")
+            .fetch_one(&app.connection_pool)
+            .await
+            .expect("Failed to fetch query")
+            .subscriber_id;
+    let response = app
+        .post_requeue_confirmation_delivery(&serde_json::json!({
+            "subscriber_id": subscriber_id,
+        }))
+        .await;
+    assert_is_redirect_to_(&response, "/admin/deliveries/failed");
+
+    let html = app.get_failed_deliveries_html().await;
+    assert!(html.contains("There are no failed confirmation deliveries."));
+
+    Mock::given(any())
+        .respond_with(PostmarkAccepted)
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+    app.dispatch_all_pending_emails().await;
+}
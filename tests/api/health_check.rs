@@ -6,7 +6,7 @@ async fn health_check_succeeds() {
     let client = reqwest::Client::new();
 
     let response = client
-        .get(&format!("{}/health_check", &app.address))
+        .get(format!("{}/health_check", &app.address))
         .send()
         .await
         .expect("Fail to send");
@@ -1,14 +1,27 @@
 use argon2::password_hash::SaltString;
 use argon2::{Algorithm, Argon2, Params, PasswordHasher, Version};
+use fake::{
+    faker::{internet::en::SafeEmail, name::en::Name},
+    Fake,
+};
 use once_cell::sync::Lazy;
+use secrecy::ExposeSecret;
 use sqlx::{Connection, Executor, PgConnection, PgPool};
+use tokio_util::sync::CancellationToken;
 use uuid::Uuid;
-use wiremock::MockServer;
+use wiremock::{
+    matchers::{method, path},
+    Mock, MockServer, ResponseTemplate,
+};
 use zero2prod::configuration::{get_configuration, DatabaseSettings};
-use zero2prod::email_client::EmailClient;
-use zero2prod::issue_delivery_worker;
-use zero2prod::startup::{get_connection_pool, Application, ApplicationBaseUrl};
-use zero2prod::subscription_confirmation_delivery_worker;
+use zero2prod::issue_delivery_worker::IssueDelivery;
+use zero2prod::newsletter_scheduler::publish_due_newsletters;
+use zero2prod::pending_subscription_cleanup::purge_stale_subscriptions;
+use zero2prod::startup::{
+    get_connection_pool, Application, ApplicationBaseUrl, HmacSecret, WebhookCredentials,
+};
+use zero2prod::subscription_confirmation_delivery_worker::SubscriptionConfirmationDelivery;
+use zero2prod::task_queue::{try_execute_task, ExecutionOutcome, WorkerOptions};
 use zero2prod::telemetry::{get_subscriber, init_subscriber};
 
 static TRACING: Lazy<()> = Lazy::new(|| {
@@ -23,6 +36,26 @@ static TRACING: Lazy<()> = Lazy::new(|| {
     }
 });
 
+pub struct PostmarkAccepted;
+
+impl wiremock::Respond for PostmarkAccepted {
+    fn respond(&self, request: &wiremock::Request) -> ResponseTemplate {
+        let accepted = || {
+            serde_json::json!({
+                "ErrorCode": 0,
+                "Message": "OK",
+                "MessageID": Uuid::new_v4().to_string(),
+            })
+        };
+        let body: serde_json::Value = serde_json::from_slice(&request.body).unwrap_or_default();
+        let response = match body.as_array() {
+            Some(batch) => batch.iter().map(|_| accepted()).collect(),
+            None => accepted(),
+        };
+        ResponseTemplate::new(200).set_body_json(response)
+    }
+}
+
 pub struct ConfirmationLinks {
     pub html: reqwest::Url,
     pub plain_text: reqwest::Url,
@@ -35,13 +68,19 @@ pub struct TestApp {
     pub email_server: MockServer,
     pub test_user: TestUser,
     pub app_client: reqwest::Client,
-    pub email_client: EmailClient,
+    pub issue_delivery: IssueDelivery,
+    pub confirmation_delivery: SubscriptionConfirmationDelivery,
+    pub newsletter_worker_options: WorkerOptions,
+    pub confirmation_worker_options: WorkerOptions,
+    pub webhook_credentials: WebhookCredentials,
+    pub confirmation_token_expiry: std::time::Duration,
 }
 
 pub struct TestUser {
     pub user_id: Uuid,
     pub username: String,
     pub password: String,
+    pub email: String,
 }
 
 impl TestUser {
@@ -50,6 +89,7 @@ impl TestUser {
             user_id: Uuid::new_v4(),
             username: Uuid::new_v4().to_string(),
             password: Uuid::new_v4().to_string(),
+            email: SafeEmail().fake(),
         }
     }
 
@@ -64,11 +104,12 @@ impl TestUser {
         .unwrap()
         .to_string();
         sqlx::query!(
-            "INSERT INTO users (user_id, username, password_hash)
-            VALUES ($1, $2, $3)",
+            "INSERT INTO users (user_id, username, password_hash, email)
+            VALUES ($1, $2, $3, $4)",
             self.user_id,
             self.username,
             password_hash,
+            self.email,
         )
         .execute(pool)
         .await
@@ -79,7 +120,7 @@ impl TestUser {
 impl TestApp {
     pub async fn post_subscriptions(&self, body: String) -> reqwest::Response {
         self.app_client
-            .post(&format!("{}/subscriptions", &self.address))
+            .post(format!("{}/subscriptions", &self.address))
             .header("Content-Type", "application/x-www-form-urlencoded")
             .body(body)
             .send()
@@ -89,7 +130,7 @@ impl TestApp {
 
     pub async fn get_newsletters(&self) -> reqwest::Response {
         self.app_client
-            .get(&format!("{}/admin/newsletters", &self.address))
+            .get(format!("{}/admin/newsletters", &self.address))
             .send()
             .await
             .expect("Failed request")
@@ -104,20 +145,142 @@ impl TestApp {
         Body: serde::Serialize,
     {
         self.app_client
-            .post(&format!("{}/admin/newsletters", &self.address))
+            .post(format!("{}/admin/newsletters", &self.address))
+            .form(&body)
+            .send()
+            .await
+            .expect("Failed to execute request")
+    }
+
+    pub async fn post_newsletter_preview<Body>(&self, body: &Body) -> reqwest::Response
+    where
+        Body: serde::Serialize,
+    {
+        self.app_client
+            .post(format!("{}/admin/newsletters/preview", &self.address))
             .form(&body)
             .send()
             .await
             .expect("Failed to execute request")
     }
 
+    pub async fn publish_newsletter(&self) {
+        let newsletter_request_body = serde_json::json!({
+            "title": "Newsletter title",
+            "text": "Newsletter plain text content",
+            "html": "<p>Newsletter HTML content.</p>",
+            "idempotency_key": uuid::Uuid::new_v4().to_string(),
+        });
+        let response = self.post_newsletters(&newsletter_request_body).await;
+        assert_is_redirect_to_(&response, "/admin/dashboard");
+    }
+
+    pub async fn get_newsletter_drafts_html(&self) -> String {
+        self.app_client
+            .get(format!("{}/admin/newsletters/drafts", &self.address))
+            .send()
+            .await
+            .expect("Failed request")
+            .text()
+            .await
+            .unwrap()
+    }
+
+    pub async fn post_publish_newsletter_draft<Body>(&self, body: &Body) -> reqwest::Response
+    where
+        Body: serde::Serialize,
+    {
+        self.app_client
+            .post(format!(
+                "{}/admin/newsletters/drafts/publish",
+                &self.address
+            ))
+            .form(body)
+            .send()
+            .await
+            .expect("Failed to execute request")
+    }
+
+    pub async fn post_delete_newsletter_draft<Body>(&self, body: &Body) -> reqwest::Response
+    where
+        Body: serde::Serialize,
+    {
+        self.app_client
+            .post(format!("{}/admin/newsletters/drafts/delete", &self.address))
+            .form(body)
+            .send()
+            .await
+            .expect("Failed to execute request")
+    }
+
+    pub async fn get_scheduled_newsletters(&self) -> reqwest::Response {
+        self.app_client
+            .get(format!("{}/admin/newsletters/scheduled", &self.address))
+            .send()
+            .await
+            .expect("Failed request")
+    }
+
+    pub async fn get_scheduled_newsletters_html(&self) -> String {
+        self.get_scheduled_newsletters().await.text().await.unwrap()
+    }
+
+    pub async fn post_reschedule_newsletter<Body>(&self, body: &Body) -> reqwest::Response
+    where
+        Body: serde::Serialize,
+    {
+        self.app_client
+            .post(format!(
+                "{}/admin/newsletters/scheduled/reschedule",
+                &self.address
+            ))
+            .form(body)
+            .send()
+            .await
+            .expect("Failed to execute request")
+    }
+
+    pub async fn post_cancel_scheduled_newsletter<Body>(&self, body: &Body) -> reqwest::Response
+    where
+        Body: serde::Serialize,
+    {
+        self.app_client
+            .post(format!(
+                "{}/admin/newsletters/scheduled/cancel",
+                &self.address
+            ))
+            .form(body)
+            .send()
+            .await
+            .expect("Failed to execute request")
+    }
+
+    pub async fn purge_stale_subscriptions(&self) -> usize {
+        purge_stale_subscriptions(&self.connection_pool, self.confirmation_token_expiry)
+            .await
+            .unwrap()
+    }
+
+    pub async fn publish_due_newsletters(&self) -> usize {
+        publish_due_newsletters(&self.connection_pool)
+            .await
+            .unwrap()
+    }
+
     pub fn get_confirmation_links(&self, email_request: &wiremock::Request) -> ConfirmationLinks {
         let body: serde_json::Value = serde_json::from_slice(&email_request.body).unwrap();
+        self.get_links(&body)
+    }
 
+    pub fn get_links(&self, body: &serde_json::Value) -> ConfirmationLinks {
+        self.get_links_matching(body, "")
+    }
+
+    pub fn get_links_matching(&self, body: &serde_json::Value, pattern: &str) -> ConfirmationLinks {
         let get_link = |s: &str| {
             let links: Vec<_> = linkify::LinkFinder::new()
                 .links(s)
-                .filter(|l| *l.kind() == linkify::LinkKind::Url)
+                .filter(|l| *l.kind() == linkify::LinkKind::Url && l.as_str().contains(pattern))
                 .collect();
             assert_eq!(links.len(), 1);
             let raw_link = links[0].as_str().to_owned();
@@ -138,7 +301,7 @@ impl TestApp {
         Body: serde::Serialize,
     {
         self.app_client
-            .post(&format!("{}/login", &self.address))
+            .post(format!("{}/login", &self.address))
             .form(body)
             .send()
             .await
@@ -155,7 +318,7 @@ impl TestApp {
 
     pub async fn post_logout(&self) -> reqwest::Response {
         self.app_client
-            .post(&format!("{}/admin/logout", &self.address))
+            .post(format!("{}/admin/logout", &self.address))
             .send()
             .await
             .expect("Failed to execute request.")
@@ -163,7 +326,7 @@ impl TestApp {
 
     pub async fn get_login_html(&self) -> String {
         self.app_client
-            .get(&format!("{}/login", &self.address))
+            .get(format!("{}/login", &self.address))
             .send()
             .await
             .expect("Failed request")
@@ -174,7 +337,7 @@ impl TestApp {
 
     pub async fn get_admin_dashboard(&self) -> reqwest::Response {
         self.app_client
-            .get(&format!("{}/admin/dashboard", &self.address))
+            .get(format!("{}/admin/dashboard", &self.address))
             .send()
             .await
             .expect("Failed request")
@@ -184,9 +347,33 @@ impl TestApp {
         self.get_admin_dashboard().await.text().await.unwrap()
     }
 
+    pub async fn get_change_email(&self) -> reqwest::Response {
+        self.app_client
+            .get(format!("{}/admin/email", &self.address))
+            .send()
+            .await
+            .expect("Failed request")
+    }
+
+    pub async fn get_change_email_html(&self) -> String {
+        self.get_change_email().await.text().await.unwrap()
+    }
+
+    pub async fn post_change_email<Body>(&self, body: &Body) -> reqwest::Response
+    where
+        Body: serde::Serialize,
+    {
+        self.app_client
+            .post(format!("{}/admin/email", &self.address))
+            .form(body)
+            .send()
+            .await
+            .expect("Failed to execute request")
+    }
+
     pub async fn get_change_password(&self) -> reqwest::Response {
         self.app_client
-            .get(&format!("{}/admin/password", &self.address))
+            .get(format!("{}/admin/password", &self.address))
             .send()
             .await
             .expect("Failed request")
@@ -201,7 +388,73 @@ impl TestApp {
         Body: serde::Serialize,
     {
         self.app_client
-            .post(&format!("{}/admin/password", &self.address))
+            .post(format!("{}/admin/password", &self.address))
+            .form(body)
+            .send()
+            .await
+            .expect("Failed to execute request")
+    }
+
+    pub async fn get_lists(&self) -> reqwest::Response {
+        self.app_client
+            .get(format!("{}/admin/lists", &self.address))
+            .send()
+            .await
+            .expect("Failed request")
+    }
+
+    pub async fn get_lists_html(&self) -> String {
+        self.get_lists().await.text().await.unwrap()
+    }
+
+    pub async fn post_lists<Body>(&self, body: &Body) -> reqwest::Response
+    where
+        Body: serde::Serialize,
+    {
+        self.app_client
+            .post(format!("{}/admin/lists", &self.address))
+            .form(body)
+            .send()
+            .await
+            .expect("Failed request")
+    }
+
+    pub async fn get_failed_deliveries(&self) -> reqwest::Response {
+        self.app_client
+            .get(format!("{}/admin/deliveries/failed", &self.address))
+            .send()
+            .await
+            .expect("Failed request")
+    }
+
+    pub async fn get_failed_deliveries_html(&self) -> String {
+        self.get_failed_deliveries().await.text().await.unwrap()
+    }
+
+    pub async fn post_requeue_newsletter_deliveries<Body>(&self, body: &Body) -> reqwest::Response
+    where
+        Body: serde::Serialize,
+    {
+        self.app_client
+            .post(format!(
+                "{}/admin/deliveries/failed/newsletters/requeue",
+                &self.address
+            ))
+            .form(body)
+            .send()
+            .await
+            .expect("Failed to execute request")
+    }
+
+    pub async fn post_requeue_confirmation_delivery<Body>(&self, body: &Body) -> reqwest::Response
+    where
+        Body: serde::Serialize,
+    {
+        self.app_client
+            .post(format!(
+                "{}/admin/deliveries/failed/confirmations/requeue",
+                &self.address
+            ))
             .form(body)
             .send()
             .await
@@ -210,7 +463,7 @@ impl TestApp {
 
     pub async fn get_home(&self) -> reqwest::Response {
         self.app_client
-            .get(&format!("{}/", &self.address))
+            .get(format!("{}/", &self.address))
             .send()
             .await
             .expect("Failed request")
@@ -220,29 +473,90 @@ impl TestApp {
         self.get_home().await.text().await.unwrap()
     }
 
+    pub async fn get_preferences(&self, link: &reqwest::Url) -> reqwest::Response {
+        self.app_client
+            .get(link.clone())
+            .send()
+            .await
+            .expect("Failed request")
+    }
+
+    pub async fn post_preferences<Body>(&self, body: &Body) -> reqwest::Response
+    where
+        Body: serde::Serialize,
+    {
+        self.app_client
+            .post(format!("{}/subscriptions/preferences", &self.address))
+            .form(body)
+            .send()
+            .await
+            .expect("Failed request")
+    }
+
+    pub async fn get_unsubscribe(&self, link: reqwest::Url) -> reqwest::Response {
+        self.app_client
+            .get(link)
+            .send()
+            .await
+            .expect("Failed request")
+    }
+
+    pub async fn post_unsubscribe<Body>(&self, body: &Body) -> reqwest::Response
+    where
+        Body: serde::Serialize,
+    {
+        self.app_client
+            .post(format!("{}/subscriptions/unsubscribe", &self.address))
+            .form(body)
+            .send()
+            .await
+            .expect("Failed to execute request")
+    }
+
+    pub async fn post_one_click_unsubscribe(
+        &self,
+        link: reqwest::Url,
+        body: &'static str,
+    ) -> reqwest::Response {
+        self.app_client
+            .post(link)
+            .header("Content-Type", "application/x-www-form-urlencoded")
+            .body(body)
+            .send()
+            .await
+            .expect("Failed to execute request")
+    }
+
+    pub async fn post_bounce_webhook(&self, body: &serde_json::Value) -> reqwest::Response {
+        self.app_client
+            .post(format!("{}/webhooks/email/bounce", &self.address))
+            .basic_auth(
+                &self.webhook_credentials.username,
+                Some(self.webhook_credentials.password.expose_secret()),
+            )
+            .json(body)
+            .send()
+            .await
+            .expect("Failed to execute request")
+    }
+
     pub async fn dispatch_all_pending_emails(&self) {
-        loop {
-            if let issue_delivery_worker::ExecutionOutcome::EmptyQueue =
-                issue_delivery_worker::try_execute_task(&self.connection_pool, &self.email_client)
-                    .await
-                    .unwrap()
-            {
-                break;
-            }
-        }
-        loop {
-            if let subscription_confirmation_delivery_worker::ExecutionOutcome::EmptyQueue =
-                subscription_confirmation_delivery_worker::try_execute_task(
-                    &self.connection_pool,
-                    &self.email_client,
-                    &ApplicationBaseUrl(self.address.clone()),
-                )
-                .await
-                .unwrap()
-            {
-                break;
-            }
-        }
+        while let ExecutionOutcome::TaskComplete = try_execute_task(
+            &self.connection_pool,
+            &self.issue_delivery,
+            &self.newsletter_worker_options,
+        )
+        .await
+        .unwrap()
+        {}
+        while let ExecutionOutcome::TaskComplete = try_execute_task(
+            &self.connection_pool,
+            &self.confirmation_delivery,
+            &self.confirmation_worker_options,
+        )
+        .await
+        .unwrap()
+        {}
     }
 }
 
@@ -266,20 +580,37 @@ pub async fn spawn_app() -> TestApp {
         .expect("failed to build application");
 
     let application_port = application.port();
-    let _ = tokio::spawn(application.run_until_stopped());
+    tokio::spawn(application.run_until_stopped(CancellationToken::new()));
     let client = reqwest::ClientBuilder::new()
         .redirect(reqwest::redirect::Policy::none())
         .cookie_store(true)
         .build()
         .unwrap();
+    let address = format!("http://localhost:{}", application_port);
+    let webhook_credentials = WebhookCredentials {
+        username: configuration.email_client.webhook_username.clone(),
+        password: configuration.email_client.webhook_password.clone(),
+    };
     let test_app = TestApp {
-        address: format!("http://localhost:{}", application_port),
+        address: address.clone(),
         port: application_port,
         connection_pool: get_connection_pool(&configuration.database),
         email_server,
         test_user: TestUser::generate(),
         app_client: client,
-        email_client: configuration.email_client.client(),
+        issue_delivery: IssueDelivery {
+            email_client: configuration.email_client.clone().client(),
+            base_url: ApplicationBaseUrl(address.clone()),
+            hmac_secret: HmacSecret(configuration.application.hmac_secret),
+        },
+        confirmation_delivery: SubscriptionConfirmationDelivery {
+            email_client: configuration.email_client.client(),
+            base_url: ApplicationBaseUrl(address),
+        },
+        newsletter_worker_options: configuration.workers.newsletter_delivery.options(),
+        confirmation_worker_options: configuration.workers.subscription_confirmation.options(),
+        webhook_credentials,
+        confirmation_token_expiry: configuration.subscriptions.confirmation_token_expiry(),
     };
     test_app.test_user.store(&test_app.connection_pool).await;
     test_app
@@ -309,6 +640,49 @@ async fn configure_database(config: &DatabaseSettings) -> PgPool {
     connection_pool
 }
 
+pub async fn create_unconfirmed_subscriber(app: &TestApp) -> ConfirmationLinks {
+    let name: String = Name().fake();
+    let email: String = SafeEmail().fake();
+    let body = serde_urlencoded::to_string(serde_json::json!({
+        "name": name,
+        "email": email
+    }))
+    .unwrap();
+
+    let _mock_guard = Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(PostmarkAccepted)
+        .named("Create unconfirmed subscriber")
+        .expect(1)
+        .mount_as_scoped(&app.email_server)
+        .await;
+
+    app.post_subscriptions(body)
+        .await
+        .error_for_status()
+        .unwrap();
+
+    app.dispatch_all_pending_emails().await;
+
+    let email_request = &app
+        .email_server
+        .received_requests()
+        .await
+        .unwrap()
+        .pop()
+        .unwrap();
+    app.get_confirmation_links(email_request)
+}
+
+pub async fn create_confirmed_subscriber(app: &TestApp) {
+    let confirmation_links = create_unconfirmed_subscriber(app).await;
+    reqwest::get(confirmation_links.html)
+        .await
+        .unwrap()
+        .error_for_status()
+        .unwrap();
+}
+
 pub fn assert_is_redirect_to_(response: &reqwest::Response, location: &str) {
     assert_eq!(response.status().as_u16(), 303);
     assert_eq!(response.headers().get("Location").unwrap(), location);
@@ -0,0 +1,309 @@
+use crate::helpers::{
+    assert_is_redirect_to_, create_confirmed_subscriber, spawn_app, PostmarkAccepted, TestApp,
+};
+use uuid::Uuid;
+use wiremock::{
+    matchers::{method, path},
+    Mock,
+};
+
+async fn create_list(app: &TestApp, name: &str) -> Uuid {
+    let response = app.post_lists(&serde_json::json!({ "name": name })).await;
+    assert_is_redirect_to_(&response, "/admin/lists");
+    sqlx::query!("SELECT list_id FROM lists WHERE name = $1", name)
+        .fetch_one(&app.connection_pool)
+        .await
+        .unwrap()
+        .list_id
+}
+
+async fn subscribe(app: &TestApp, email: &str, list_id: Uuid) -> reqwest::Response {
+    let body = serde_urlencoded::to_string(serde_json::json!({
+        "name": "le guin",
+        "email": email,
+        "list_id": list_id,
+    }))
+    .unwrap();
+    app.post_subscriptions(body).await
+}
+
+async fn confirm_last_email(app: &TestApp) {
+    let email_request = app
+        .email_server
+        .received_requests()
+        .await
+        .unwrap()
+        .pop()
+        .unwrap();
+    let confirmation_links = app.get_confirmation_links(&email_request);
+    reqwest::get(confirmation_links.html)
+        .await
+        .unwrap()
+        .error_for_status()
+        .unwrap();
+}
+
+async fn membership_status(app: &TestApp, email: &str, list_id: Uuid) -> Option<String> {
+    sqlx::query!(
+        r#"
+        SELECT list_memberships.status
+        FROM list_memberships
+        JOIN subscriptions ON subscriptions.id = list_memberships.subscriber_id
+        WHERE subscriptions.email = $1 AND list_memberships.list_id = $2
+        "#,
+        email,
+        list_id
+    )
+    .fetch_optional(&app.connection_pool)
+    .await
+    .unwrap()
+    .map(|r| r.status)
+}
+
+#[tokio::test]
+async fn you_must_be_logged_in_to_manage_lists() {
+    let app = spawn_app().await;
+
+    let response = app.get_lists().await;
+    assert_is_redirect_to_(&response, "/login");
+
+    let response = app.post_lists(&serde_json::json!({ "name": "Rust" })).await;
+    assert_is_redirect_to_(&response, "/login");
+}
+
+#[tokio::test]
+async fn an_admin_can_create_a_list() {
+    let app = spawn_app().await;
+    app.login_test_user().await;
+
+    let response = app.post_lists(&serde_json::json!({ "name": "Rust" })).await;
+    assert_is_redirect_to_(&response, "/admin/lists");
+
+    let html = app.get_lists_html().await;
+    assert!(html.contains("The list Rust has been created."));
+    assert!(html.contains("<td>Rust</td>"));
+    assert!(html.contains("<td>Newsletter</td>"));
+}
+
+#[tokio::test]
+async fn list_names_must_be_unique_and_non_empty() {
+    let app = spawn_app().await;
+    app.login_test_user().await;
+    create_list(&app, "Rust").await;
+
+    let response = app.post_lists(&serde_json::json!({ "name": "Rust" })).await;
+    assert_is_redirect_to_(&response, "/admin/lists");
+    assert!(app
+        .get_lists_html()
+        .await
+        .contains("A list named Rust already exists."));
+
+    let response = app.post_lists(&serde_json::json!({ "name": "  " })).await;
+    assert_is_redirect_to_(&response, "/admin/lists");
+    assert!(app
+        .get_lists_html()
+        .await
+        .contains("The list must have a name."));
+}
+
+#[tokio::test]
+async fn list_names_cannot_contain_merge_tag_braces() {
+    let app = spawn_app().await;
+    app.login_test_user().await;
+
+    let response = app
+        .post_lists(&serde_json::json!({ "name": "Rust {{ name }}" }))
+        .await;
+    assert_is_redirect_to_(&response, "/admin/lists");
+
+    assert!(app
+        .get_lists_html()
+        .await
+        .contains("A list name cannot contain {{ or }}."));
+    let count = sqlx::query!("SELECT COUNT(*) AS count FROM lists WHERE name LIKE '%{{%'")
+        .fetch_one(&app.connection_pool)
+        .await
+        .unwrap()
+        .count;
+    assert_eq!(count, Some(0));
+}
+
+#[tokio::test]
+async fn confirmation_emails_are_sent_for_lists_with_braces_in_their_name() {
+    let app = spawn_app().await;
+    // Lists created before names were validated may still contain braces.
+    let list_id = sqlx::query!(
+        "INSERT INTO lists (list_id, name, created_at) VALUES ($1, $2, now()) RETURNING list_id",
+        Uuid::new_v4(),
+        "Rust {{ unknown }}",
+    )
+    .fetch_one(&app.connection_pool)
+    .await
+    .unwrap()
+    .list_id;
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(PostmarkAccepted)
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+
+    subscribe(&app, "ursula_le_guin@gmail.com", list_id)
+        .await
+        .error_for_status()
+        .unwrap();
+    app.dispatch_all_pending_emails().await;
+
+    let email_request = &app.email_server.received_requests().await.unwrap()[0];
+    let body: serde_json::Value = serde_json::from_slice(&email_request.body).unwrap();
+    assert!(body["TextBody"]
+        .as_str()
+        .unwrap()
+        .contains("Rust {{ unknown }}"));
+    confirm_last_email(&app).await;
+    assert_eq!(
+        membership_status(&app, "ursula_le_guin@gmail.com", list_id)
+            .await
+            .as_deref(),
+        Some("confirmed")
+    );
+}
+
+#[tokio::test]
+async fn subscribing_to_an_unknown_list_is_rejected() {
+    let app = spawn_app().await;
+
+    let response = subscribe(&app, "ursula_le_guin@gmail.com", Uuid::new_v4()).await;
+
+    assert_eq!(response.status().as_u16(), 400);
+}
+
+#[tokio::test]
+async fn confirming_a_subscription_only_confirms_the_chosen_list() {
+    let app = spawn_app().await;
+    app.login_test_user().await;
+    let rust = create_list(&app, "Rust").await;
+    let default_list = sqlx::query!("SELECT list_id FROM lists WHERE name = 'Newsletter'")
+        .fetch_one(&app.connection_pool)
+        .await
+        .unwrap()
+        .list_id;
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(PostmarkAccepted)
+        .mount(&app.email_server)
+        .await;
+
+    subscribe(&app, "ursula_le_guin@gmail.com", rust)
+        .await
+        .error_for_status()
+        .unwrap();
+    app.dispatch_all_pending_emails().await;
+    confirm_last_email(&app).await;
+
+    assert_eq!(
+        membership_status(&app, "ursula_le_guin@gmail.com", rust)
+            .await
+            .as_deref(),
+        Some("confirmed")
+    );
+    assert_eq!(
+        membership_status(&app, "ursula_le_guin@gmail.com", default_list).await,
+        None
+    );
+}
+
+#[tokio::test]
+async fn joining_a_second_list_requires_its_own_confirmation() {
+    let app = spawn_app().await;
+    app.login_test_user().await;
+    let rust = create_list(&app, "Rust").await;
+    let go = create_list(&app, "Go").await;
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(PostmarkAccepted)
+        .expect(2)
+        .mount(&app.email_server)
+        .await;
+    subscribe(&app, "ursula_le_guin@gmail.com", rust)
+        .await
+        .error_for_status()
+        .unwrap();
+    app.dispatch_all_pending_emails().await;
+    confirm_last_email(&app).await;
+
+    subscribe(&app, "ursula_le_guin@gmail.com", go)
+        .await
+        .error_for_status()
+        .unwrap();
+    app.dispatch_all_pending_emails().await;
+
+    assert_eq!(
+        membership_status(&app, "ursula_le_guin@gmail.com", go)
+            .await
+            .as_deref(),
+        Some("pending_confirmation")
+    );
+    assert_eq!(
+        membership_status(&app, "ursula_le_guin@gmail.com", rust)
+            .await
+            .as_deref(),
+        Some("confirmed")
+    );
+
+    confirm_last_email(&app).await;
+    assert_eq!(
+        membership_status(&app, "ursula_le_guin@gmail.com", go)
+            .await
+            .as_deref(),
+        Some("confirmed")
+    );
+}
+
+#[tokio::test]
+async fn newsletters_are_only_delivered_to_members_of_the_selected_list() {
+    let app = spawn_app().await;
+    app.login_test_user().await;
+    create_confirmed_subscriber(&app).await;
+    let rust = create_list(&app, "Rust").await;
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(PostmarkAccepted)
+        .mount(&app.email_server)
+        .await;
+    subscribe(&app, "ursula_le_guin@gmail.com", rust)
+        .await
+        .error_for_status()
+        .unwrap();
+    app.dispatch_all_pending_emails().await;
+    confirm_last_email(&app).await;
+    Mock::given(path("/email/batch"))
+        .respond_with(PostmarkAccepted)
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+
+    let response = app
+        .post_newsletters(&serde_json::json!({
+            "title": "Newsletter title",
+            "text": "Newsletter body as plain text",
+            "html": "<p>Newsletter body as HTML</p>",
+            "list_id": rust,
+            "idempotency_key": Uuid::new_v4().to_string(),
+        }))
+        .await;
+    assert_is_redirect_to_(&response, "/admin/dashboard");
+    app.dispatch_all_pending_emails().await;
+
+    let request = app
+        .email_server
+        .received_requests()
+        .await
+        .unwrap()
+        .pop()
+        .unwrap();
+    let body: serde_json::Value = serde_json::from_slice(&request.body).unwrap();
+    let messages = body.as_array().unwrap();
+    assert_eq!(messages.len(), 1);
+    assert_eq!(messages[0]["To"], "ursula_le_guin@gmail.com");
+}
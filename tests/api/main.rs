@@ -1,8 +1,20 @@
 mod admin_dashboard;
+mod change_email;
 mod change_password;
+mod failed_deliveries;
 mod health_check;
 mod helpers;
+mod lists;
 mod login;
 mod newsletter;
+mod newsletter_drafts;
+mod newsletter_markdown;
+mod newsletter_merge_tags;
+mod newsletter_preview;
+mod newsletter_test_send;
+mod scheduled_newsletters;
 mod subscriptions;
 mod subscriptions_confirm;
+mod subscriptions_preferences;
+mod subscriptions_unsubscribe;
+mod webhooks;
@@ -1,13 +1,10 @@
 use crate::helpers::{
-    assert_is_redirect_to_, spawn_app, spawn_app_logged_in, ConfirmationLinks, TestApp,
-};
-use fake::{
-    faker::{internet::en::SafeEmail, name::en::Name},
-    Fake,
+    assert_is_redirect_to_, create_confirmed_subscriber, create_unconfirmed_subscriber, spawn_app,
+    spawn_app_logged_in, PostmarkAccepted,
 };
 use std::time::Duration;
 use wiremock::{
-    matchers::{any, method, path},
+    matchers::{any, path},
     Mock, ResponseTemplate,
 };
 use zero2prod::paths::{self, Path};
@@ -18,7 +15,7 @@ async fn newsletters_are_not_delivered_to_unconfirmed_subscribers() {
     create_unconfirmed_subscriber(&app).await;
 
     Mock::given(any())
-        .respond_with(ResponseTemplate::new(200))
+        .respond_with(PostmarkAccepted)
         .expect(0)
         .mount(&app.email_server)
         .await;
@@ -45,7 +42,7 @@ async fn newsletters_are_delivered_to_confirmed_subscribers() {
     create_confirmed_subscriber(&app).await;
 
     Mock::given(any())
-        .respond_with(ResponseTemplate::new(200))
+        .respond_with(PostmarkAccepted)
         .expect(1)
         .mount(&app.email_server)
         .await;
@@ -66,47 +63,45 @@ async fn newsletters_are_delivered_to_confirmed_subscribers() {
     app.dispatch_all_pending_emails().await;
 }
 
-async fn create_unconfirmed_subscriber(app: &TestApp) -> ConfirmationLinks {
-    let name: String = Name().fake();
-    let email: String = SafeEmail().fake();
-    let body = serde_urlencoded::to_string(serde_json::json!({
-        "name": name,
-        "email": email
-    }))
-    .unwrap();
-
-    let _mock_guard = Mock::given(path("/email"))
-        .and(method("POST"))
-        .respond_with(ResponseTemplate::new(200))
-        .named("Create unconfirmed subscriber")
-        .expect(1)
-        .mount_as_scoped(&app.email_server)
+#[tokio::test]
+async fn subscribers_who_unsubscribe_while_an_issue_is_in_flight_are_skipped() {
+    let app = spawn_app_logged_in().await;
+    create_confirmed_subscriber(&app).await;
+
+    Mock::given(any())
+        .respond_with(PostmarkAccepted)
+        .expect(0)
+        .mount(&app.email_server)
         .await;
 
-    app.post_subscriptions(body)
+    app.publish_newsletter().await;
+    sqlx::query!("UPDATE subscriptions SET status = 'unsubscribed'")
+        .execute(&app.connection_pool)
         .await
-        .error_for_status()
         .unwrap();
-
     app.dispatch_all_pending_emails().await;
 
-    let email_request = &app
-        .email_server
-        .received_requests()
+    let queued = sqlx::query!("SELECT COUNT(*) AS \"count!\" FROM issue_delievery_queue")
+        .fetch_one(&app.connection_pool)
         .await
-        .unwrap()
-        .pop()
-        .unwrap();
-    app.get_confirmation_links(email_request)
-}
-
-async fn create_confirmed_subscriber(app: &TestApp) {
-    let confirmation_links = create_unconfirmed_subscriber(app).await;
-    reqwest::get(confirmation_links.html)
+        .expect("Failed to fetch query");
+    assert_eq!(queued.count, 0);
+    let issue = sqlx::query!("SELECT status FROM newsletter_issues")
+        .fetch_one(&app.connection_pool)
         .await
-        .unwrap()
-        .error_for_status()
-        .unwrap();
+        .expect("Failed to fetch query");
+    assert_eq!(issue.status, "sent");
+    let delivery =
+        sqlx::query!("SELECT status, error_message, n_attempts FROM newsletter_deliveries")
+            .fetch_one(&app.connection_pool)
+            .await
+            .expect("Failed to fetch query");
+    assert_eq!(delivery.status, "skipped");
+    assert_eq!(
+        delivery.error_message.as_deref(),
+        Some("The subscriber is no longer subscribed to the list")
+    );
+    assert_eq!(delivery.n_attempts, 0);
 }
 
 #[tokio::test]
@@ -145,7 +140,7 @@ async fn newsletter_create_is_idempotent() {
     create_confirmed_subscriber(&app).await;
 
     Mock::given(any())
-        .respond_with(ResponseTemplate::new(200))
+        .respond_with(PostmarkAccepted)
         .expect(1)
         .mount(&app.email_server)
         .await;
@@ -202,3 +197,230 @@ async fn concurrent_form_submission_is_handled_gracefully() {
 
     app.dispatch_all_pending_emails().await;
 }
+
+#[tokio::test]
+async fn failed_deliveries_are_rescheduled_with_backoff() {
+    let app = spawn_app_logged_in().await;
+    create_confirmed_subscriber(&app).await;
+
+    Mock::given(any())
+        .respond_with(ResponseTemplate::new(500))
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+
+    app.publish_newsletter().await;
+    app.dispatch_all_pending_emails().await;
+
+    let queued = sqlx::query!(
+        "SELECT n_retries, execute_after > now() AS \"is_delayed!\" FROM issue_delievery_queue"
+    )
+    .fetch_one(&app.connection_pool)
+    .await
+    .expect("Failed to fetch query");
+    assert_eq!(queued.n_retries, 1);
+    assert!(queued.is_delayed);
+    let delivery = sqlx::query!("SELECT status, n_attempts FROM newsletter_deliveries")
+        .fetch_one(&app.connection_pool)
+        .await
+        .expect("Failed to fetch query");
+    assert_eq!(delivery.status, "retrying");
+    assert_eq!(delivery.n_attempts, 1);
+}
+
+#[tokio::test]
+async fn deliveries_are_dead_lettered_after_max_attempts() {
+    let mut app = spawn_app_logged_in().await;
+    app.newsletter_worker_options.retry_policy.max_attempts = 3;
+    app.newsletter_worker_options
+        .retry_policy
+        .base_delay_milliseconds = 0;
+    create_confirmed_subscriber(&app).await;
+
+    Mock::given(any())
+        .respond_with(ResponseTemplate::new(500))
+        .expect(3)
+        .mount(&app.email_server)
+        .await;
+
+    app.publish_newsletter().await;
+    app.dispatch_all_pending_emails().await;
+
+    let queued = sqlx::query!("SELECT COUNT(*) AS \"count!\" FROM issue_delievery_queue")
+        .fetch_one(&app.connection_pool)
+        .await
+        .expect("Failed to fetch query");
+    assert_eq!(queued.count, 0);
+    let dead_letter =
+        sqlx::query!("SELECT n_attempts, last_error FROM issue_delivery_dead_letters")
+            .fetch_one(&app.connection_pool)
+            .await
+            .expect("Failed to fetch query");
+    assert_eq!(dead_letter.n_attempts, 3);
+    assert!(dead_letter.last_error.contains("500"));
+}
+
+#[tokio::test]
+async fn successful_deliveries_are_logged_with_the_provider_message_id() {
+    let app = spawn_app_logged_in().await;
+    create_confirmed_subscriber(&app).await;
+
+    Mock::given(path("/email/batch"))
+        .respond_with(
+            ResponseTemplate::new(200).set_body_json(serde_json::json!([{
+                "To": "subscriber@example.com",
+                "SubmittedAt": "2023-10-29T15:00:00Z",
+                "MessageID": "b7bc2f4a-e38e-4336-af7d-e6c392c2f817",
+                "ErrorCode": 0,
+                "Message": "OK"
+            }])),
+        )
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+
+    app.publish_newsletter().await;
+    app.dispatch_all_pending_emails().await;
+
+    let delivery = sqlx::query!(
+        r#"
+        SELECT
+            status,
+            provider_message_id,
+            error_code,
+            n_attempts,
+            sent_at IS NOT NULL AS "is_sent!"
+        FROM newsletter_deliveries
+        "#
+    )
+    .fetch_one(&app.connection_pool)
+    .await
+    .expect("Failed to fetch query");
+    assert_eq!(delivery.status, "sent");
+    assert_eq!(
+        delivery.provider_message_id.as_deref(),
+        Some("b7bc2f4a-e38e-4336-af7d-e6c392c2f817")
+    );
+    assert_eq!(delivery.error_code, None);
+    assert_eq!(delivery.n_attempts, 1);
+    assert!(delivery.is_sent);
+}
+
+#[tokio::test]
+async fn permanently_rejected_deliveries_are_dead_lettered_without_retrying() {
+    let mut app = spawn_app_logged_in().await;
+    app.newsletter_worker_options.retry_policy.max_attempts = 2;
+    app.newsletter_worker_options
+        .retry_policy
+        .base_delay_milliseconds = 0;
+    create_confirmed_subscriber(&app).await;
+
+    Mock::given(path("/email/batch"))
+        .respond_with(
+            ResponseTemplate::new(200).set_body_json(serde_json::json!([{
+                "ErrorCode": 406,
+                "Message": "You tried to send to a recipient that has been marked as inactive."
+            }])),
+        )
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+
+    app.publish_newsletter().await;
+    app.dispatch_all_pending_emails().await;
+
+    let delivery = sqlx::query!(
+        r#"
+        SELECT status, provider_message_id, error_code, error_message, n_attempts, sent_at
+        FROM newsletter_deliveries
+        "#
+    )
+    .fetch_one(&app.connection_pool)
+    .await
+    .expect("Failed to fetch query");
+    assert_eq!(delivery.status, "failed");
+    assert_eq!(delivery.provider_message_id, None);
+    assert_eq!(delivery.error_code, Some(406));
+    assert!(delivery.error_message.unwrap().contains("inactive"));
+    assert_eq!(delivery.n_attempts, 1);
+    assert_eq!(delivery.sent_at, None);
+}
+
+#[tokio::test]
+async fn newsletters_are_sent_in_a_single_batch_request() {
+    let app = spawn_app_logged_in().await;
+    for _ in 0..3 {
+        create_confirmed_subscriber(&app).await;
+    }
+
+    Mock::given(path("/email/batch"))
+        .respond_with(PostmarkAccepted)
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+
+    app.publish_newsletter().await;
+    app.dispatch_all_pending_emails().await;
+
+    let request = app
+        .email_server
+        .received_requests()
+        .await
+        .unwrap()
+        .pop()
+        .unwrap();
+    let body: serde_json::Value = serde_json::from_slice(&request.body).unwrap();
+    assert_eq!(body.as_array().unwrap().len(), 3);
+}
+
+#[tokio::test]
+async fn only_the_failed_messages_of_a_batch_are_retried() {
+    let app = spawn_app_logged_in().await;
+    create_confirmed_subscriber(&app).await;
+    create_confirmed_subscriber(&app).await;
+
+    Mock::given(path("/email/batch"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!([
+            {
+                "ErrorCode": 0,
+                "Message": "OK",
+                "MessageID": "b7bc2f4a-e38e-4336-af7d-e6c392c2f817"
+            },
+            {
+                "ErrorCode": 405,
+                "Message": "Not allowed to send: you have run out of credits."
+            }
+        ])))
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+
+    app.publish_newsletter().await;
+    app.dispatch_all_pending_emails().await;
+
+    let request = app
+        .email_server
+        .received_requests()
+        .await
+        .unwrap()
+        .pop()
+        .unwrap();
+    let body: serde_json::Value = serde_json::from_slice(&request.body).unwrap();
+    let failed_recipient = body[1]["To"].as_str().unwrap().to_owned();
+    let queued = sqlx::query!(
+        "SELECT email, n_retries FROM issue_delievery_queue \
+        JOIN subscriptions ON subscriptions.id = subscriber_id"
+    )
+    .fetch_all(&app.connection_pool)
+    .await
+    .expect("Failed to fetch query");
+    assert_eq!(queued.len(), 1);
+    assert_eq!(queued[0].email, failed_recipient);
+    assert_eq!(queued[0].n_retries, 1);
+    let statuses = sqlx::query!("SELECT status FROM newsletter_deliveries ORDER BY status DESC")
+        .fetch_all(&app.connection_pool)
+        .await
+        .expect("Failed to fetch query");
+    assert_eq!(statuses[0].status, "sent");
+    assert_eq!(statuses[1].status, "retrying");
+}
@@ -0,0 +1,264 @@
+use crate::helpers::{
+    assert_is_redirect_to_, create_confirmed_subscriber, spawn_app, spawn_app_logged_in,
+    PostmarkAccepted, TestApp,
+};
+use uuid::Uuid;
+use wiremock::{matchers::any, Mock};
+
+async fn save_draft(app: &TestApp, title: &str, html: &str, text: &str) -> Uuid {
+    let response = app
+        .post_newsletters(&serde_json::json!({
+            "title": title,
+            "html": html,
+            "text": text,
+            "idempotency_key": Uuid::new_v4().to_string(),
+            "action": "save_draft",
+        }))
+        .await;
+    let newsletter_issue_id = newsletter_status(app).await.0;
+    assert_is_redirect_to_(
+        &response,
+        &format!("/admin/newsletters?draft={}", newsletter_issue_id),
+    );
+    newsletter_issue_id
+}
+
+async fn newsletter_status(app: &TestApp) -> (Uuid, String) {
+    let issue = sqlx::query!("SELECT newsletter_issue_id, status FROM newsletter_issues")
+        .fetch_one(&app.connection_pool)
+        .await
+        .expect("Failed to fetch the newsletter issue");
+    (issue.newsletter_issue_id, issue.status)
+}
+
+async fn get_draft_form_html(app: &TestApp, newsletter_issue_id: Uuid) -> String {
+    app.app_client
+        .get(format!(
+            "{}/admin/newsletters?draft={}",
+            &app.address, newsletter_issue_id
+        ))
+        .send()
+        .await
+        .expect("Failed request")
+        .text()
+        .await
+        .unwrap()
+}
+
+#[tokio::test]
+async fn you_must_be_logged_in_to_see_drafts() {
+    let app = spawn_app().await;
+
+    let response = app
+        .app_client
+        .get(format!("{}/admin/newsletters/drafts", &app.address))
+        .send()
+        .await
+        .expect("Failed request");
+
+    assert_is_redirect_to_(&response, "/login");
+}
+
+#[tokio::test]
+async fn a_saved_draft_is_listed_and_not_delivered() {
+    let app = spawn_app_logged_in().await;
+    create_confirmed_subscriber(&app).await;
+    Mock::given(any())
+        .respond_with(PostmarkAccepted)
+        .expect(0)
+        .mount(&app.email_server)
+        .await;
+
+    let newsletter_issue_id = save_draft(&app, "Monday issue", "<p>Hello</p>", "Hello").await;
+    app.dispatch_all_pending_emails().await;
+
+    assert_eq!(newsletter_status(&app).await.1, "draft");
+    let html = get_draft_form_html(&app, newsletter_issue_id).await;
+    assert!(html.contains("The draft has been saved."));
+    assert!(html.contains(r#"value="Monday issue""#));
+    assert!(html.contains("&lt;p&gt;Hello&lt;/p&gt;"));
+    let html = app.get_newsletter_drafts_html().await;
+    assert!(html.contains("Monday issue"));
+}
+
+#[tokio::test]
+async fn incomplete_drafts_can_be_saved() {
+    let app = spawn_app_logged_in().await;
+
+    save_draft(&app, "", "", "Just an idea").await;
+
+    let html = app.get_newsletter_drafts_html().await;
+    assert!(html.contains("Untitled draft"));
+}
+
+#[tokio::test]
+async fn a_draft_can_be_edited() {
+    let app = spawn_app_logged_in().await;
+    let newsletter_issue_id = save_draft(&app, "First title", "<p>Hello</p>", "Hello").await;
+
+    let response = app
+        .post_newsletters(&serde_json::json!({
+            "title": "Second title",
+            "html": "<p>Hello again</p>",
+            "text": "Hello again",
+            "idempotency_key": Uuid::new_v4().to_string(),
+            "newsletter_issue_id": newsletter_issue_id,
+            "action": "save_draft",
+        }))
+        .await;
+    assert_is_redirect_to_(
+        &response,
+        &format!("/admin/newsletters?draft={}", newsletter_issue_id),
+    );
+
+    let draft = sqlx::query!("SELECT title, text_content FROM newsletter_issues")
+        .fetch_one(&app.connection_pool)
+        .await
+        .expect("Failed to fetch the draft");
+    assert_eq!(draft.title, "Second title");
+    assert_eq!(draft.text_content, "Hello again");
+}
+
+#[tokio::test]
+async fn publishing_a_draft_delivers_it() {
+    let app = spawn_app_logged_in().await;
+    create_confirmed_subscriber(&app).await;
+    Mock::given(any())
+        .respond_with(PostmarkAccepted)
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+    let newsletter_issue_id = save_draft(&app, "Monday issue", "<p>Hello</p>", "Hello").await;
+
+    let body = serde_json::json!({
+        "newsletter_issue_id": newsletter_issue_id,
+        "idempotency_key": Uuid::new_v4().to_string(),
+    });
+    let response = app.post_publish_newsletter_draft(&body).await;
+    assert_is_redirect_to_(&response, "/admin/dashboard");
+    assert_eq!(newsletter_status(&app).await.1, "sending");
+
+    let response = app.post_publish_newsletter_draft(&body).await;
+    assert_is_redirect_to_(&response, "/admin/dashboard");
+    let html = app.get_admin_dashboard_html().await;
+    assert!(html.contains("The newsletter has been accepted."));
+
+    app.dispatch_all_pending_emails().await;
+    assert_eq!(newsletter_status(&app).await.1, "sent");
+    let html = app.get_newsletter_drafts_html().await;
+    assert!(html.contains("There are no drafts."));
+}
+
+#[tokio::test]
+async fn an_invalid_draft_cannot_be_published() {
+    let app = spawn_app_logged_in().await;
+    let newsletter_issue_id = save_draft(&app, "Monday issue", "", "Hello").await;
+
+    let response = app
+        .post_publish_newsletter_draft(&serde_json::json!({
+            "newsletter_issue_id": newsletter_issue_id,
+            "idempotency_key": Uuid::new_v4().to_string(),
+        }))
+        .await;
+    assert_is_redirect_to_(
+        &response,
+        &format!("/admin/newsletters?draft={}", newsletter_issue_id),
+    );
+
+    let html = get_draft_form_html(&app, newsletter_issue_id).await;
+    assert!(html.contains("The newsletter must have HTML content."));
+    assert_eq!(newsletter_status(&app).await.1, "draft");
+}
+
+#[tokio::test]
+async fn a_draft_can_be_scheduled_from_the_edit_form() {
+    let app = spawn_app_logged_in().await;
+    let newsletter_issue_id = save_draft(&app, "Monday issue", "<p>Hello</p>", "Hello").await;
+
+    let response = app
+        .post_newsletters(&serde_json::json!({
+            "title": "Monday issue",
+            "html": "<p>Hello</p>",
+            "text": "Hello",
+            "idempotency_key": Uuid::new_v4().to_string(),
+            "newsletter_issue_id": newsletter_issue_id,
+            "scheduled_for": "2099-11-13T09:00",
+            "timezone": "UTC",
+            "action": "publish",
+        }))
+        .await;
+    assert_is_redirect_to_(&response, "/admin/dashboard");
+
+    assert_eq!(newsletter_status(&app).await.1, "scheduled");
+    let html = app.get_scheduled_newsletters_html().await;
+    assert!(html.contains("Monday issue"));
+}
+
+#[tokio::test]
+async fn a_cancelled_scheduled_newsletter_goes_back_to_drafts() {
+    let app = spawn_app_logged_in().await;
+    let newsletter_issue_id = save_draft(&app, "Monday issue", "<p>Hello</p>", "Hello").await;
+    app.post_newsletters(&serde_json::json!({
+        "title": "Monday issue",
+        "html": "<p>Hello</p>",
+        "text": "Hello",
+        "idempotency_key": Uuid::new_v4().to_string(),
+        "newsletter_issue_id": newsletter_issue_id,
+        "scheduled_for": "2099-11-13T09:00",
+        "timezone": "Europe/Rome",
+    }))
+    .await;
+
+    app.post_cancel_scheduled_newsletter(&serde_json::json!({
+        "newsletter_issue_id": newsletter_issue_id,
+    }))
+    .await;
+
+    assert_eq!(newsletter_status(&app).await.1, "draft");
+    let html = get_draft_form_html(&app, newsletter_issue_id).await;
+    assert!(html.contains(r#"value="Europe/Rome""#));
+}
+
+#[tokio::test]
+async fn a_draft_can_be_deleted() {
+    let app = spawn_app_logged_in().await;
+    let newsletter_issue_id = save_draft(&app, "Monday issue", "<p>Hello</p>", "Hello").await;
+
+    let response = app
+        .post_delete_newsletter_draft(&serde_json::json!({
+            "newsletter_issue_id": newsletter_issue_id,
+        }))
+        .await;
+    assert_is_redirect_to_(&response, "/admin/newsletters/drafts");
+
+    let html = app.get_newsletter_drafts_html().await;
+    assert!(html.contains("The draft has been deleted."));
+    assert!(html.contains("There are no drafts."));
+}
+
+#[tokio::test]
+async fn a_published_issue_cannot_be_edited_or_deleted() {
+    let app = spawn_app_logged_in().await;
+    app.publish_newsletter().await;
+    let (newsletter_issue_id, status) = newsletter_status(&app).await;
+    assert_eq!(status, "sent");
+
+    let response = app
+        .app_client
+        .get(format!(
+            "{}/admin/newsletters?draft={}",
+            &app.address, newsletter_issue_id
+        ))
+        .send()
+        .await
+        .expect("Failed request");
+    assert_is_redirect_to_(&response, "/admin/newsletters/drafts");
+
+    app.post_delete_newsletter_draft(&serde_json::json!({
+        "newsletter_issue_id": newsletter_issue_id,
+    }))
+    .await;
+    let html = app.get_newsletter_drafts_html().await;
+    assert!(html.contains("The draft could not be found"));
+    assert_eq!(newsletter_status(&app).await.1, "sent");
+}
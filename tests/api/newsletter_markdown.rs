@@ -0,0 +1,148 @@
+use crate::helpers::{
+    assert_is_redirect_to_, create_confirmed_subscriber, spawn_app_logged_in, PostmarkAccepted,
+    TestApp,
+};
+use uuid::Uuid;
+use wiremock::{matchers::path, Mock};
+
+fn markdown_body(html: &str, text: &str) -> serde_json::Value {
+    serde_json::json!({
+        "title": "Newsletter title",
+        "markdown": "# Hello\n\nRead [the docs](https://example.com/docs).",
+        "html": html,
+        "text": text,
+        "idempotency_key": Uuid::new_v4().to_string(),
+    })
+}
+
+async fn sent_bodies(app: &TestApp) -> (String, String) {
+    let request = app
+        .email_server
+        .received_requests()
+        .await
+        .unwrap()
+        .pop()
+        .unwrap();
+    let body: serde_json::Value = serde_json::from_slice(&request.body).unwrap();
+    (
+        body[0]["HtmlBody"].as_str().unwrap().to_owned(),
+        body[0]["TextBody"].as_str().unwrap().to_owned(),
+    )
+}
+
+#[tokio::test]
+async fn both_bodies_are_generated_from_markdown() {
+    let app = spawn_app_logged_in().await;
+    create_confirmed_subscriber(&app).await;
+    Mock::given(path("/email/batch"))
+        .respond_with(PostmarkAccepted)
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+
+    let response = app.post_newsletters(&markdown_body("", "")).await;
+    assert_is_redirect_to_(&response, "/admin/dashboard");
+    app.dispatch_all_pending_emails().await;
+
+    let (html, text) = sent_bodies(&app).await;
+    assert!(html.starts_with(
+        "<h1>Hello</h1>\n<p>Read <a href=\"https://example.com/docs\">the docs</a>.</p>"
+    ));
+    assert!(text.starts_with("Hello\n\nRead the docs (https://example.com/docs)."));
+    let issue = sqlx::query!("SELECT markdown_content, html_content FROM newsletter_issues")
+        .fetch_one(&app.connection_pool)
+        .await
+        .expect("Failed to fetch the newsletter issue");
+    assert_eq!(
+        issue.markdown_content.as_deref(),
+        Some("# Hello\n\nRead [the docs](https://example.com/docs).")
+    );
+    assert!(issue.html_content.starts_with("<h1>Hello</h1>"));
+}
+
+#[tokio::test]
+async fn a_hand_written_body_overrides_the_generated_one() {
+    let app = spawn_app_logged_in().await;
+    create_confirmed_subscriber(&app).await;
+    Mock::given(path("/email/batch"))
+        .respond_with(PostmarkAccepted)
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+
+    app.post_newsletters(&markdown_body("", "Plain text written by hand"))
+        .await;
+    app.dispatch_all_pending_emails().await;
+
+    let (html, text) = sent_bodies(&app).await;
+    assert!(html.starts_with("<h1>Hello</h1>"));
+    assert!(text.starts_with("Plain text written by hand"));
+}
+
+#[tokio::test]
+async fn drafts_keep_their_markdown_source() {
+    let app = spawn_app_logged_in().await;
+    let mut body = markdown_body("", "");
+    body["action"] = "save_draft".into();
+
+    app.post_newsletters(&body).await;
+
+    let issue = sqlx::query!("SELECT newsletter_issue_id FROM newsletter_issues")
+        .fetch_one(&app.connection_pool)
+        .await
+        .expect("Failed to fetch the newsletter issue");
+    let html = app
+        .app_client
+        .get(format!(
+            "{}/admin/newsletters?draft={}",
+            &app.address, issue.newsletter_issue_id
+        ))
+        .send()
+        .await
+        .expect("Failed request")
+        .text()
+        .await
+        .unwrap();
+    assert!(html.contains("# Hello\n\nRead [the docs](https://example.com/docs)."));
+}
+
+#[tokio::test]
+async fn a_markdown_draft_can_be_published_from_the_drafts_page() {
+    let app = spawn_app_logged_in().await;
+    create_confirmed_subscriber(&app).await;
+    Mock::given(path("/email/batch"))
+        .respond_with(PostmarkAccepted)
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+    let mut body = markdown_body("", "");
+    body["action"] = "save_draft".into();
+    app.post_newsletters(&body).await;
+    let issue = sqlx::query!("SELECT newsletter_issue_id FROM newsletter_issues")
+        .fetch_one(&app.connection_pool)
+        .await
+        .expect("Failed to fetch the newsletter issue");
+
+    let response = app
+        .post_publish_newsletter_draft(&serde_json::json!({
+            "newsletter_issue_id": issue.newsletter_issue_id,
+            "idempotency_key": Uuid::new_v4().to_string(),
+        }))
+        .await;
+    assert_is_redirect_to_(&response, "/admin/dashboard");
+    app.dispatch_all_pending_emails().await;
+
+    let (html, _) = sent_bodies(&app).await;
+    assert!(html.starts_with("<h1>Hello</h1>"));
+}
+
+#[tokio::test]
+async fn the_preview_renders_markdown() {
+    let app = spawn_app_logged_in().await;
+
+    let response = app.post_newsletter_preview(&markdown_body("", "")).await;
+
+    let html = response.text().await.unwrap();
+    assert!(html.contains("&lt;h1&gt;Hello&lt;/h1&gt;"));
+    assert!(html.contains("Read the docs (https://example.com/docs)."));
+}
@@ -0,0 +1,146 @@
+use crate::helpers::{
+    assert_is_redirect_to_, create_confirmed_subscriber, spawn_app_logged_in, PostmarkAccepted,
+};
+use uuid::Uuid;
+use wiremock::{matchers::path, Mock};
+
+fn merge_tags_body() -> serde_json::Value {
+    serde_json::json!({
+        "title": "News for {{name}}",
+        "text": "Hi {{name}} <{{email}}>, leave at {{unsubscribe_url}}",
+        "html": "<p>Hi {{name}}</p><a href=\"{{unsubscribe_url}}\">Leave</a>",
+        "idempotency_key": Uuid::new_v4().to_string(),
+    })
+}
+
+#[tokio::test]
+async fn merge_tags_are_rendered_for_each_subscriber() {
+    let app = spawn_app_logged_in().await;
+    create_confirmed_subscriber(&app).await;
+    create_confirmed_subscriber(&app).await;
+    sqlx::query!("UPDATE subscriptions SET name = 'Tom & Jerry'")
+        .execute(&app.connection_pool)
+        .await
+        .unwrap();
+    Mock::given(path("/email/batch"))
+        .respond_with(PostmarkAccepted)
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+
+    let response = app.post_newsletters(&merge_tags_body()).await;
+    assert_is_redirect_to_(&response, "/admin/dashboard");
+    app.dispatch_all_pending_emails().await;
+
+    let request = app
+        .email_server
+        .received_requests()
+        .await
+        .unwrap()
+        .pop()
+        .unwrap();
+    let body: serde_json::Value = serde_json::from_slice(&request.body).unwrap();
+    let messages = body.as_array().unwrap();
+    assert_eq!(messages.len(), 2);
+    for message in messages {
+        let email = message["To"].as_str().unwrap();
+        let text = message["TextBody"].as_str().unwrap();
+        let html = message["HtmlBody"].as_str().unwrap();
+        assert_eq!(message["Subject"], "News for Tom & Jerry");
+        assert!(text.starts_with(&format!("Hi Tom & Jerry <{}>, leave at http", email)));
+        assert!(!text.contains("&amp;"));
+        assert!(html.starts_with("<p>Hi Tom &amp; Jerry</p><a href=\"http"));
+        assert!(!html.contains("{{"));
+        let unsubscribe_url = text
+            .split("leave at ")
+            .nth(1)
+            .unwrap()
+            .lines()
+            .next()
+            .unwrap();
+        assert!(text.ends_with(&format!("visit {}", unsubscribe_url)));
+    }
+}
+
+#[tokio::test]
+async fn unknown_merge_tags_fail_validation() {
+    let app = spawn_app_logged_in().await;
+    create_confirmed_subscriber(&app).await;
+    Mock::given(path("/email/batch"))
+        .respond_with(PostmarkAccepted)
+        .expect(0)
+        .mount(&app.email_server)
+        .await;
+    let mut body = merge_tags_body();
+    body["html"] = "<p>Hi {{first_name}}</p>".into();
+
+    let response = app.post_newsletters(&body).await;
+    assert_is_redirect_to_(&response, "/admin/newsletters");
+
+    let html = app.get_newsletters_html().await;
+    assert!(html.contains("The newsletter HTML content uses an unknown or malformed merge tag."));
+    let issues = sqlx::query!("SELECT COUNT(*) AS \"count!\" FROM newsletter_issues")
+        .fetch_one(&app.connection_pool)
+        .await
+        .expect("Failed to fetch query");
+    assert_eq!(issues.count, 0);
+    app.dispatch_all_pending_emails().await;
+}
+
+#[tokio::test]
+async fn unescaped_merge_tags_fail_validation() {
+    let app = spawn_app_logged_in().await;
+    let mut body = merge_tags_body();
+    body["html"] = "<p>Hi {{{name}}}</p>".into();
+
+    let response = app.post_newsletters(&body).await;
+    assert_is_redirect_to_(&response, "/admin/newsletters");
+
+    let html = app.get_newsletters_html().await;
+    assert!(html.contains("The newsletter HTML content uses an unknown or malformed merge tag."));
+    let issues = sqlx::query!("SELECT COUNT(*) AS \"count!\" FROM newsletter_issues")
+        .fetch_one(&app.connection_pool)
+        .await
+        .expect("Failed to fetch query");
+    assert_eq!(issues.count, 0);
+}
+
+#[tokio::test]
+async fn the_preview_renders_merge_tags_with_example_values() {
+    let app = spawn_app_logged_in().await;
+
+    let response = app.post_newsletter_preview(&merge_tags_body()).await;
+
+    let html = response.text().await.unwrap();
+    assert!(html.contains("News for Ursula Le Guin"));
+    assert!(html.contains("Hi Ursula Le Guin &lt;ursula_le_guin@example.com&gt;"));
+    assert!(!html.contains("{{"));
+}
+
+#[tokio::test]
+async fn stored_issues_with_unknown_merge_tags_are_dead_lettered() {
+    let app = spawn_app_logged_in().await;
+    create_confirmed_subscriber(&app).await;
+    Mock::given(path("/email/batch"))
+        .respond_with(PostmarkAccepted)
+        .expect(0)
+        .mount(&app.email_server)
+        .await;
+    let mut body = merge_tags_body();
+    body["html"] = "<p>Hi</p>".into();
+    let response = app.post_newsletters(&body).await;
+    assert_is_redirect_to_(&response, "/admin/dashboard");
+    sqlx::query!("UPDATE newsletter_issues SET html_content = '<p>Hi {{unknown}}</p>'")
+        .execute(&app.connection_pool)
+        .await
+        .unwrap();
+
+    app.dispatch_all_pending_emails().await;
+
+    let dead_letters =
+        sqlx::query!("SELECT COUNT(*) AS \"count!\" FROM issue_delivery_dead_letters")
+            .fetch_one(&app.connection_pool)
+            .await
+            .expect("Failed to fetch query");
+    assert_eq!(dead_letters.count, 1);
+}
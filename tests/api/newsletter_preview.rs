@@ -0,0 +1,107 @@
+use crate::helpers::{assert_is_redirect_to_, spawn_app, spawn_app_logged_in, PostmarkAccepted};
+use scraper::{Html, Selector};
+use wiremock::{matchers::any, Mock};
+
+fn preview_body() -> serde_json::Value {
+    serde_json::json!({
+        "title": "Newsletter title",
+        "text": "Newsletter plain text content",
+        "html": "<p>Newsletter <b>HTML</b> content.</p><script>alert(1)</script>",
+    })
+}
+
+#[tokio::test]
+async fn you_must_be_logged_in_to_preview_a_newsletter() {
+    let app = spawn_app().await;
+
+    let response = app.post_newsletter_preview(&preview_body()).await;
+
+    assert_is_redirect_to_(&response, "/login");
+}
+
+#[tokio::test]
+async fn the_html_body_is_rendered_in_a_sandboxed_iframe() {
+    let app = spawn_app_logged_in().await;
+
+    let response = app.post_newsletter_preview(&preview_body()).await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    let html = Html::parse_document(&response.text().await.unwrap());
+    let iframe = html
+        .select(&Selector::parse("iframe").unwrap())
+        .next()
+        .expect("No iframe in the preview");
+    assert_eq!(iframe.value().attr("sandbox"), Some(""));
+    let srcdoc = iframe.value().attr("srcdoc").unwrap();
+    assert!(srcdoc.starts_with("<p>Newsletter <b>HTML</b> content.</p><script>alert(1)</script>"));
+    assert!(html
+        .select(&Selector::parse("script").unwrap())
+        .next()
+        .is_none());
+}
+
+#[tokio::test]
+async fn the_preview_uses_the_personalization_of_a_real_send() {
+    let app = spawn_app_logged_in().await;
+
+    let response = app.post_newsletter_preview(&preview_body()).await;
+
+    let html = Html::parse_document(&response.text().await.unwrap());
+    let srcdoc = html
+        .select(&Selector::parse("iframe").unwrap())
+        .next()
+        .unwrap()
+        .value()
+        .attr("srcdoc")
+        .unwrap()
+        .to_owned();
+    assert!(srcdoc.contains("/subscriptions/unsubscribe?"));
+    assert!(srcdoc.contains("/subscriptions/preferences?"));
+    assert!(srcdoc.contains("unsubscribe</a> from this newsletter."));
+    let text = html
+        .select(&Selector::parse("pre").unwrap())
+        .next()
+        .unwrap()
+        .inner_html();
+    assert!(text.starts_with("Newsletter plain text content"));
+    assert!(text.contains("To manage your preferences, visit"));
+    assert!(text.contains("To stop receiving this newsletter, visit"));
+}
+
+#[tokio::test]
+async fn validation_errors_are_shown_instead_of_a_preview() {
+    let app = spawn_app_logged_in().await;
+
+    let response = app
+        .post_newsletter_preview(&serde_json::json!({
+            "title": "",
+            "text": "Newsletter plain text content",
+            "html": "",
+        }))
+        .await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    let html = response.text().await.unwrap();
+    assert!(html.contains("The newsletter must have a title."));
+    assert!(html.contains("The newsletter must have HTML content."));
+    assert!(!html.contains("<iframe"));
+}
+
+#[tokio::test]
+async fn previewing_does_not_store_or_send_anything() {
+    let app = spawn_app_logged_in().await;
+    Mock::given(any())
+        .respond_with(PostmarkAccepted)
+        .expect(0)
+        .mount(&app.email_server)
+        .await;
+
+    app.post_newsletter_preview(&preview_body()).await;
+    app.dispatch_all_pending_emails().await;
+
+    let issues = sqlx::query!("SELECT COUNT(*) AS \"count!\" FROM newsletter_issues")
+        .fetch_one(&app.connection_pool)
+        .await
+        .expect("Failed to fetch query");
+    assert_eq!(issues.count, 0);
+}
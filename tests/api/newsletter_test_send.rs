@@ -0,0 +1,151 @@
+use crate::helpers::{
+    assert_is_redirect_to_, create_confirmed_subscriber, spawn_app_logged_in, PostmarkAccepted,
+};
+use uuid::Uuid;
+use wiremock::{
+    matchers::{any, method, path},
+    Mock, ResponseTemplate,
+};
+
+fn send_test_body() -> serde_json::Value {
+    serde_json::json!({
+        "title": "Newsletter title",
+        "text": "Newsletter plain text content",
+        "html": "<p>Newsletter HTML content.</p>",
+        "idempotency_key": Uuid::new_v4().to_string(),
+        "action": "send_test",
+    })
+}
+
+#[tokio::test]
+async fn a_test_copy_is_sent_only_to_the_logged_in_admin() {
+    let app = spawn_app_logged_in().await;
+    create_confirmed_subscriber(&app).await;
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(PostmarkAccepted)
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+
+    let response = app.post_newsletters(&send_test_body()).await;
+
+    let issue = sqlx::query!("SELECT newsletter_issue_id, status FROM newsletter_issues")
+        .fetch_one(&app.connection_pool)
+        .await
+        .expect("Failed to fetch the draft");
+    assert_eq!(issue.status, "draft");
+    assert_is_redirect_to_(
+        &response,
+        &format!("/admin/newsletters?draft={}", issue.newsletter_issue_id),
+    );
+    let request = &app.email_server.received_requests().await.unwrap()[1];
+    let body: serde_json::Value = serde_json::from_slice(&request.body).unwrap();
+    assert_eq!(body["To"], app.test_user.email);
+    assert_eq!(body["Subject"], "Newsletter title");
+
+    let queued = sqlx::query!("SELECT COUNT(*) AS \"count!\" FROM issue_delievery_queue")
+        .fetch_one(&app.connection_pool)
+        .await
+        .expect("Failed to fetch query");
+    assert_eq!(queued.count, 0);
+    app.dispatch_all_pending_emails().await;
+}
+
+#[tokio::test]
+async fn a_test_copy_matches_what_subscribers_receive() {
+    let app = spawn_app_logged_in().await;
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(PostmarkAccepted)
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+
+    app.post_newsletters(&send_test_body()).await;
+
+    let request = &app.email_server.received_requests().await.unwrap()[0];
+    let body: serde_json::Value = serde_json::from_slice(&request.body).unwrap();
+    let text = body["TextBody"].as_str().unwrap();
+    let html = body["HtmlBody"].as_str().unwrap();
+    assert!(text.contains("To manage your preferences, visit"));
+    assert!(text.contains("To stop receiving this newsletter, visit"));
+    assert!(html.contains("Manage your preferences"));
+    let headers = body["Headers"].as_array().unwrap();
+    assert_eq!(headers[0]["Name"], "List-Unsubscribe");
+    assert!(headers[0]["Value"]
+        .as_str()
+        .unwrap()
+        .contains("/subscriptions/unsubscribe/one-click?"));
+    assert_eq!(headers[1]["Name"], "List-Unsubscribe-Post");
+    assert_eq!(headers[1]["Value"], "List-Unsubscribe=One-Click");
+}
+
+#[tokio::test]
+async fn the_editor_is_told_where_the_test_copy_went() {
+    let app = spawn_app_logged_in().await;
+    Mock::given(any())
+        .respond_with(PostmarkAccepted)
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+
+    app.post_newsletters(&send_test_body()).await;
+
+    let html = app.get_newsletter_drafts_html().await;
+    assert!(html.contains(&format!(
+        "A test copy has been sent to {}.",
+        app.test_user.email
+    )));
+}
+
+#[tokio::test]
+async fn a_test_copy_requires_an_email_on_the_admin_account() {
+    let app = spawn_app_logged_in().await;
+    sqlx::query!("UPDATE users SET email = NULL")
+        .execute(&app.connection_pool)
+        .await
+        .expect("Failed to clear the admin email");
+    Mock::given(any())
+        .respond_with(PostmarkAccepted)
+        .expect(0)
+        .mount(&app.email_server)
+        .await;
+
+    app.post_newsletters(&send_test_body()).await;
+
+    let html = app.get_newsletter_drafts_html().await;
+    assert!(html.contains("Add an email address to your account to receive test copies."));
+}
+
+#[tokio::test]
+async fn an_invalid_issue_is_not_sent_as_a_test() {
+    let app = spawn_app_logged_in().await;
+    Mock::given(any())
+        .respond_with(PostmarkAccepted)
+        .expect(0)
+        .mount(&app.email_server)
+        .await;
+
+    let mut body = send_test_body();
+    body["text"] = "".into();
+    app.post_newsletters(&body).await;
+
+    let html = app.get_newsletter_drafts_html().await;
+    assert!(html.contains("The newsletter must have text content."));
+}
+
+#[tokio::test]
+async fn a_failed_test_send_is_reported() {
+    let app = spawn_app_logged_in().await;
+    Mock::given(any())
+        .respond_with(ResponseTemplate::new(500))
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+
+    app.post_newsletters(&send_test_body()).await;
+
+    let html = app.get_newsletter_drafts_html().await;
+    assert!(html.contains("Failed to send the test copy"));
+}
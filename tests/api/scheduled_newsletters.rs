@@ -0,0 +1,238 @@
+use crate::helpers::{
+    assert_is_redirect_to_, create_confirmed_subscriber, spawn_app, spawn_app_logged_in,
+    PostmarkAccepted, TestApp,
+};
+use chrono::{Duration, Utc};
+use uuid::Uuid;
+use wiremock::{matchers::any, Mock};
+
+fn in_a_day() -> String {
+    (Utc::now() + Duration::days(1))
+        .format("%Y-%m-%dT%H:%M")
+        .to_string()
+}
+
+async fn schedule_newsletter(app: &TestApp, scheduled_for: &str, timezone: &str) -> Uuid {
+    let response = app
+        .post_newsletters(&serde_json::json!({
+            "title": "Newsletter title",
+            "text": "Newsletter plain text content",
+            "html": "<p>Newsletter HTML content.</p>",
+            "idempotency_key": Uuid::new_v4().to_string(),
+            "scheduled_for": scheduled_for,
+            "timezone": timezone,
+        }))
+        .await;
+    assert_is_redirect_to_(&response, "/admin/dashboard");
+    sqlx::query!("SELECT newsletter_issue_id FROM newsletter_issues")
+        .fetch_one(&app.connection_pool)
+        .await
+        .expect("Failed to fetch the scheduled issue")
+        .newsletter_issue_id
+}
+
+async fn make_due(app: &TestApp, newsletter_issue_id: Uuid) {
+    sqlx::query!(
+        "UPDATE newsletter_issues SET scheduled_for = now() - interval '1 minute' \
+        WHERE newsletter_issue_id = $1",
+        newsletter_issue_id
+    )
+    .execute(&app.connection_pool)
+    .await
+    .expect("Failed to move the schedule into the past");
+}
+
+#[tokio::test]
+async fn you_must_be_logged_in_to_see_scheduled_newsletters() {
+    let app = spawn_app().await;
+
+    let response = app.get_scheduled_newsletters().await;
+
+    assert_is_redirect_to_(&response, "/login");
+}
+
+#[tokio::test]
+async fn scheduled_newsletters_are_not_delivered_before_their_time() {
+    let app = spawn_app_logged_in().await;
+    create_confirmed_subscriber(&app).await;
+    Mock::given(any())
+        .respond_with(PostmarkAccepted)
+        .expect(0)
+        .mount(&app.email_server)
+        .await;
+
+    schedule_newsletter(&app, "2099-11-13T09:00", "Europe/Rome").await;
+    let html = app.get_admin_dashboard_html().await;
+    assert!(
+        html.contains("The newsletter has been scheduled for 2099-11-13 09:00 CET (Europe/Rome).")
+    );
+
+    assert_eq!(app.publish_due_newsletters().await, 0);
+    app.dispatch_all_pending_emails().await;
+    let published = sqlx::query!("SELECT published_at, scheduled_for FROM newsletter_issues")
+        .fetch_one(&app.connection_pool)
+        .await
+        .expect("Failed to fetch the scheduled issue");
+    assert!(published.published_at.is_none());
+    assert_eq!(
+        published.scheduled_for.unwrap().to_rfc3339(),
+        "2099-11-13T08:00:00+00:00"
+    );
+}
+
+#[tokio::test]
+async fn scheduled_newsletters_are_delivered_once_due() {
+    let app = spawn_app_logged_in().await;
+    create_confirmed_subscriber(&app).await;
+    Mock::given(any())
+        .respond_with(PostmarkAccepted)
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+    let newsletter_issue_id = schedule_newsletter(&app, &in_a_day(), "UTC").await;
+
+    make_due(&app, newsletter_issue_id).await;
+    assert_eq!(app.publish_due_newsletters().await, 1);
+    assert_eq!(app.publish_due_newsletters().await, 0);
+    app.dispatch_all_pending_emails().await;
+
+    let html = app.get_scheduled_newsletters_html().await;
+    assert!(html.contains("There are no scheduled newsletters."));
+}
+
+#[tokio::test]
+async fn newsletters_cannot_be_scheduled_in_the_past() {
+    let app = spawn_app_logged_in().await;
+
+    let response = app
+        .post_newsletters(&serde_json::json!({
+            "title": "Newsletter title",
+            "text": "Newsletter plain text content",
+            "html": "<p>Newsletter HTML content.</p>",
+            "idempotency_key": Uuid::new_v4().to_string(),
+            "scheduled_for": "2001-01-01T09:00",
+            "timezone": "UTC",
+        }))
+        .await;
+    assert_is_redirect_to_(&response, "/admin/newsletters");
+
+    let html = app.get_newsletters_html().await;
+    assert!(html.contains("The newsletter must be scheduled in the future."));
+}
+
+#[tokio::test]
+async fn newsletters_with_an_unknown_timezone_are_rejected() {
+    let app = spawn_app_logged_in().await;
+
+    let response = app
+        .post_newsletters(&serde_json::json!({
+            "title": "",
+            "text": "Newsletter plain text content",
+            "html": "<p>Newsletter HTML content.</p>",
+            "idempotency_key": Uuid::new_v4().to_string(),
+            "scheduled_for": in_a_day(),
+            "timezone": "Mars/Olympus_Mons",
+        }))
+        .await;
+    assert_is_redirect_to_(&response, "/admin/newsletters");
+
+    let html = app.get_newsletters_html().await;
+    assert!(html.contains("The newsletter must have a title."));
+    assert!(html.contains("Mars/Olympus_Mons is not a known timezone."));
+}
+
+#[tokio::test]
+async fn scheduled_newsletters_are_listed() {
+    let app = spawn_app_logged_in().await;
+    schedule_newsletter(&app, "2099-07-01T09:00", "America/New_York").await;
+
+    let html = app.get_scheduled_newsletters_html().await;
+
+    assert!(html.contains("Newsletter title"));
+    assert!(html.contains("2099-07-01 09:00 EDT (America/New_York)"));
+    assert!(html.contains(r#"value="2099-07-01T09:00""#));
+}
+
+#[tokio::test]
+async fn a_scheduled_newsletter_can_be_rescheduled() {
+    let app = spawn_app_logged_in().await;
+    let newsletter_issue_id = schedule_newsletter(&app, &in_a_day(), "UTC").await;
+
+    let response = app
+        .post_reschedule_newsletter(&serde_json::json!({
+            "newsletter_issue_id": newsletter_issue_id,
+            "scheduled_for": "2099-01-04T09:00",
+            "timezone": "Asia/Tokyo",
+        }))
+        .await;
+    assert_is_redirect_to_(&response, "/admin/newsletters/scheduled");
+
+    let html = app.get_scheduled_newsletters_html().await;
+    assert!(
+        html.contains("The newsletter has been rescheduled for 2099-01-04 09:00 JST (Asia/Tokyo).")
+    );
+    let scheduled_for = sqlx::query!("SELECT scheduled_for FROM newsletter_issues")
+        .fetch_one(&app.connection_pool)
+        .await
+        .expect("Failed to fetch the scheduled issue")
+        .scheduled_for;
+    assert_eq!(
+        scheduled_for.unwrap().to_rfc3339(),
+        "2099-01-04T00:00:00+00:00"
+    );
+}
+
+#[tokio::test]
+async fn a_cancelled_newsletter_is_never_delivered() {
+    let app = spawn_app_logged_in().await;
+    create_confirmed_subscriber(&app).await;
+    Mock::given(any())
+        .respond_with(PostmarkAccepted)
+        .expect(0)
+        .mount(&app.email_server)
+        .await;
+    let newsletter_issue_id = schedule_newsletter(&app, &in_a_day(), "UTC").await;
+
+    let response = app
+        .post_cancel_scheduled_newsletter(&serde_json::json!({
+            "newsletter_issue_id": newsletter_issue_id,
+        }))
+        .await;
+    assert_is_redirect_to_(&response, "/admin/newsletters/scheduled");
+
+    let html = app.get_scheduled_newsletters_html().await;
+    assert!(html.contains("The scheduled newsletter has been cancelled and moved back to drafts."));
+    assert!(html.contains("There are no scheduled newsletters."));
+    assert_eq!(app.publish_due_newsletters().await, 0);
+    app.dispatch_all_pending_emails().await;
+}
+
+#[tokio::test]
+async fn a_newsletter_that_was_already_sent_cannot_be_cancelled_or_rescheduled() {
+    let app = spawn_app_logged_in().await;
+    let newsletter_issue_id = schedule_newsletter(&app, &in_a_day(), "UTC").await;
+    make_due(&app, newsletter_issue_id).await;
+    app.publish_due_newsletters().await;
+
+    app.post_cancel_scheduled_newsletter(&serde_json::json!({
+        "newsletter_issue_id": newsletter_issue_id,
+    }))
+    .await;
+    let html = app.get_scheduled_newsletters_html().await;
+    assert!(html.contains("The newsletter is no longer scheduled"));
+
+    app.post_reschedule_newsletter(&serde_json::json!({
+        "newsletter_issue_id": newsletter_issue_id,
+        "scheduled_for": in_a_day(),
+        "timezone": "UTC",
+    }))
+    .await;
+    let html = app.get_scheduled_newsletters_html().await;
+    assert!(html.contains("The newsletter is no longer scheduled"));
+    let published_at = sqlx::query!("SELECT published_at FROM newsletter_issues")
+        .fetch_one(&app.connection_pool)
+        .await
+        .expect("Failed to fetch the issue")
+        .published_at;
+    assert!(published_at.is_some());
+}
@@ -1,6 +1,9 @@
-use crate::helpers::{assert_is_redirect_to_, spawn_app};
+use crate::helpers::{assert_is_redirect_to_, spawn_app, PostmarkAccepted, TestApp};
+use sqlx::postgres::PgListener;
+use std::time::Duration;
 use wiremock::matchers::{method, path};
 use wiremock::{Mock, ResponseTemplate};
+use zero2prod::persistence::SUBSCRIPTION_CONFIRMATION_CHANNEL;
 
 #[tokio::test]
 async fn subscribe_redirects_to_home_for_valid_form_data() {
@@ -9,7 +12,7 @@ async fn subscribe_redirects_to_home_for_valid_form_data() {
 
     Mock::given(path("/email"))
         .and(method("POST"))
-        .respond_with(ResponseTemplate::new(200))
+        .respond_with(PostmarkAccepted)
         .mount(&app.email_server)
         .await;
 
@@ -27,7 +30,7 @@ async fn subscribe_persists_the_new_subscriber() {
 
     Mock::given(path("/email"))
         .and(method("POST"))
-        .respond_with(ResponseTemplate::new(200))
+        .respond_with(PostmarkAccepted)
         .mount(&app.email_server)
         .await;
 
@@ -92,7 +95,7 @@ async fn subscribe_sends_a_confirmation_email_for_valid_data() {
 
     Mock::given(path("/email"))
         .and(method("POST"))
-        .respond_with(ResponseTemplate::new(200))
+        .respond_with(PostmarkAccepted)
         .expect(1)
         .mount(&app.email_server)
         .await;
@@ -110,7 +113,7 @@ async fn subscribe_sends_a_confirmation_email_with_a_link() {
 
     Mock::given(path("/email"))
         .and(method("POST"))
-        .respond_with(ResponseTemplate::new(200))
+        .respond_with(PostmarkAccepted)
         .mount(&app.email_server)
         .await;
 
@@ -126,12 +129,42 @@ async fn subscribe_sends_a_confirmation_email_with_a_link() {
     app.dispatch_all_pending_emails().await;
 }
 
+#[tokio::test]
+async fn permanently_rejected_confirmation_emails_are_dead_lettered_without_retrying() {
+    let mut app = spawn_app().await;
+    app.confirmation_worker_options.retry_policy.max_attempts = 3;
+    app.confirmation_worker_options
+        .retry_policy
+        .base_delay_milliseconds = 0;
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(422).set_body_json(serde_json::json!({
+            "ErrorCode": 406,
+            "Message": "You tried to send to a recipient that has been marked as inactive."
+        })))
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+
+    app.post_subscriptions("name=le%20guin&email=ursula_le_guin%40gmail.com".into())
+        .await;
+    app.dispatch_all_pending_emails().await;
+
+    let dead_letter =
+        sqlx::query!("SELECT n_attempts, last_error FROM subscription_confirmation_dead_letters")
+            .fetch_one(&app.connection_pool)
+            .await
+            .expect("Failed to fetch query");
+    assert_eq!(dead_letter.n_attempts, 1);
+    assert!(dead_letter.last_error.contains("inactive"));
+}
+
 #[tokio::test]
 async fn subscribe_fails_if_there_is_a_fatal_database_error() {
     let app = spawn_app().await;
     let body = "name=Fred&email=fred@example.com";
 
-    sqlx::query!("ALTER TABLE subscriptions_tokens DROP COLUMN subscriptions_token;")
+    sqlx::query!("ALTER TABLE list_memberships DROP COLUMN status;")
         .execute(&app.connection_pool)
         .await
         .unwrap();
@@ -140,3 +173,128 @@ async fn subscribe_fails_if_there_is_a_fatal_database_error() {
 
     assert_eq!(response.status().as_u16(), 500);
 }
+
+#[tokio::test]
+async fn subscribe_notifies_the_confirmation_workers() {
+    let app = spawn_app().await;
+    let mut listener = PgListener::connect_with(&app.connection_pool)
+        .await
+        .unwrap();
+    listener
+        .listen(SUBSCRIPTION_CONFIRMATION_CHANNEL)
+        .await
+        .unwrap();
+
+    let body = "name=le%20guin&email=ursula_le_guin%40gmail.com";
+    app.post_subscriptions(body.into()).await;
+
+    let notification = tokio::time::timeout(Duration::from_secs(5), listener.recv())
+        .await
+        .expect("No notification was received")
+        .unwrap();
+    assert_eq!(notification.channel(), SUBSCRIPTION_CONFIRMATION_CHANNEL);
+}
+
+async fn confirmation_links(app: &TestApp) -> Vec<reqwest::Url> {
+    app.email_server
+        .received_requests()
+        .await
+        .unwrap()
+        .iter()
+        .map(|r| app.get_confirmation_links(r).html)
+        .collect()
+}
+
+#[tokio::test]
+async fn resubscribing_a_pending_address_resends_the_confirmation_with_a_fresh_token() {
+    let app = spawn_app().await;
+    let body = "name=le%20guin&email=ursula_le_guin%40gmail.com";
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(PostmarkAccepted)
+        .expect(2)
+        .mount(&app.email_server)
+        .await;
+    app.post_subscriptions(body.into()).await;
+    app.dispatch_all_pending_emails().await;
+    sqlx::query!("UPDATE subscriptions SET confirmation_requested_at = now() - interval '1 hour'")
+        .execute(&app.connection_pool)
+        .await
+        .unwrap();
+
+    let response = app.post_subscriptions(body.into()).await;
+    assert_is_redirect_to_(&response, "/");
+    app.dispatch_all_pending_emails().await;
+
+    let html = app.get_home_html().await;
+    assert!(html.contains("We have sent you a new confirmation email."));
+    let links = confirmation_links(&app).await;
+    assert_eq!(links.len(), 2);
+    assert_ne!(links[0], links[1]);
+    let response = reqwest::get(links[0].clone()).await.unwrap();
+    assert_eq!(response.status().as_u16(), 404);
+    reqwest::get(links[1].clone())
+        .await
+        .unwrap()
+        .error_for_status()
+        .unwrap();
+    let saved = sqlx::query!("SELECT status FROM subscriptions")
+        .fetch_one(&app.connection_pool)
+        .await
+        .unwrap();
+    assert_eq!(saved.status, "confirmed");
+}
+
+#[tokio::test]
+async fn confirmation_resends_are_rate_limited_per_address() {
+    let app = spawn_app().await;
+    let body = "name=le%20guin&email=ursula_le_guin%40gmail.com";
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(PostmarkAccepted)
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+    app.post_subscriptions(body.into()).await;
+    app.dispatch_all_pending_emails().await;
+
+    let response = app.post_subscriptions(body.into()).await;
+    assert_is_redirect_to_(&response, "/");
+    app.dispatch_all_pending_emails().await;
+
+    let html = app.get_home_html().await;
+    assert!(html.contains("A confirmation email was sent recently. Please check your inbox."));
+    let links = confirmation_links(&app).await;
+    reqwest::get(links[0].clone())
+        .await
+        .unwrap()
+        .error_for_status()
+        .unwrap();
+}
+
+#[tokio::test]
+async fn resubscribing_a_confirmed_address_shows_a_friendly_message() {
+    let app = spawn_app().await;
+    let body = "name=le%20guin&email=ursula_le_guin%40gmail.com";
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(PostmarkAccepted)
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+    app.post_subscriptions(body.into()).await;
+    app.dispatch_all_pending_emails().await;
+    let links = confirmation_links(&app).await;
+    reqwest::get(links[0].clone())
+        .await
+        .unwrap()
+        .error_for_status()
+        .unwrap();
+
+    let response = app.post_subscriptions(body.into()).await;
+    assert_is_redirect_to_(&response, "/");
+    app.dispatch_all_pending_emails().await;
+
+    let html = app.get_home_html().await;
+    assert!(html.contains("You are already subscribed to Newsletter."));
+}
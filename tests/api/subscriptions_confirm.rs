@@ -1,6 +1,6 @@
-use crate::helpers::spawn_app;
+use crate::helpers::{spawn_app, PostmarkAccepted, TestApp};
 use wiremock::matchers::{method, path};
-use wiremock::{Mock, ResponseTemplate};
+use wiremock::Mock;
 
 #[tokio::test]
 async fn confirmations_without_token_are_rejected_with_a_400() {
@@ -20,7 +20,7 @@ async fn the_link_returned_by_subscribe_returns_a_200_if_called() {
 
     Mock::given(path("/email"))
         .and(method("POST"))
-        .respond_with(ResponseTemplate::new(200))
+        .respond_with(PostmarkAccepted)
         .mount(&app.email_server)
         .await;
 
@@ -42,7 +42,7 @@ async fn clicking_on_the_confirmation_link_confirms_a_subscriber() {
 
     Mock::given(path("/email"))
         .and(method("POST"))
-        .respond_with(ResponseTemplate::new(200))
+        .respond_with(PostmarkAccepted)
         .mount(&app.email_server)
         .await;
 
@@ -68,3 +68,169 @@ async fn clicking_on_the_confirmation_link_confirms_a_subscriber() {
     assert_eq!(saved.name, "le guin");
     assert_eq!(saved.status, "confirmed");
 }
+
+async fn subscribe_and_get_confirmation_link(app: &TestApp) -> reqwest::Url {
+    let body = "name=le%20guin&email=ursula_le_guin%40gmail.com";
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(PostmarkAccepted)
+        .mount(&app.email_server)
+        .await;
+    app.post_subscriptions(body.into()).await;
+    app.dispatch_all_pending_emails().await;
+
+    let email_request = &app.email_server.received_requests().await.unwrap()[0];
+    app.get_confirmation_links(email_request).html
+}
+
+#[tokio::test]
+async fn a_confirmed_subscription_shows_a_confirmation_page() {
+    let app = spawn_app().await;
+    let link = subscribe_and_get_confirmation_link(&app).await;
+
+    let response = reqwest::get(link).await.unwrap();
+
+    assert_eq!(response.status().as_u16(), 200);
+    let html = response.text().await.unwrap();
+    assert!(html.contains("Your subscription to Newsletter is confirmed."));
+}
+
+#[tokio::test]
+async fn confirmation_tokens_are_stored_as_sha256_hashes() {
+    let app = spawn_app().await;
+    let link = subscribe_and_get_confirmation_link(&app).await;
+    let token = link
+        .query_pairs()
+        .find(|(k, _)| k == "subscription_token")
+        .unwrap()
+        .1
+        .into_owned();
+
+    let saved = sqlx::query!("SELECT token_hash FROM subscriptions_tokens")
+        .fetch_one(&app.connection_pool)
+        .await
+        .unwrap();
+
+    assert_ne!(saved.token_hash, token);
+    assert_eq!(saved.token_hash.len(), 64);
+    let matching = sqlx::query!(
+        "SELECT COUNT(*) AS \"count!\" FROM subscriptions_tokens
+        WHERE token_hash = encode(sha256(convert_to($1, 'UTF8')), 'hex')",
+        token
+    )
+    .fetch_one(&app.connection_pool)
+    .await
+    .unwrap();
+    assert_eq!(matching.count, 1);
+}
+
+#[tokio::test]
+async fn confirmation_links_can_only_be_used_once() {
+    let app = spawn_app().await;
+    let link = subscribe_and_get_confirmation_link(&app).await;
+    reqwest::get(link.clone())
+        .await
+        .unwrap()
+        .error_for_status()
+        .unwrap();
+
+    let response = reqwest::get(link).await.unwrap();
+
+    assert_eq!(response.status().as_u16(), 410);
+    let html = response.text().await.unwrap();
+    assert!(html.contains("This confirmation link has already been used."));
+}
+
+#[tokio::test]
+async fn expired_confirmation_links_are_rejected() {
+    let app = spawn_app().await;
+    let link = subscribe_and_get_confirmation_link(&app).await;
+    sqlx::query!("UPDATE subscriptions_tokens SET created_at = now() - interval '49 hours'")
+        .execute(&app.connection_pool)
+        .await
+        .unwrap();
+
+    let response = reqwest::get(link).await.unwrap();
+
+    assert_eq!(response.status().as_u16(), 410);
+    let html = response.text().await.unwrap();
+    assert!(html.contains("This confirmation link has expired."));
+    let saved = sqlx::query!("SELECT status FROM subscriptions")
+        .fetch_one(&app.connection_pool)
+        .await
+        .unwrap();
+    assert_eq!(saved.status, "pending_confirmation");
+}
+
+#[tokio::test]
+async fn unknown_confirmation_tokens_show_an_error_page() {
+    let app = spawn_app().await;
+
+    let response = reqwest::get(format!(
+        "{}/subscriptions/confirm?subscription_token=not-a-real-token",
+        app.address
+    ))
+    .await
+    .unwrap();
+
+    assert_eq!(response.status().as_u16(), 404);
+    assert!(response.headers()["Content-Type"]
+        .to_str()
+        .unwrap()
+        .starts_with("text/html"));
+    let html = response.text().await.unwrap();
+    assert!(html.contains("This confirmation link is not valid."));
+}
+
+#[tokio::test]
+async fn stale_pending_subscriptions_are_purged() {
+    let app = spawn_app().await;
+    let stale_link = subscribe_and_get_confirmation_link(&app).await;
+    sqlx::query!(
+        "UPDATE subscriptions SET
+            subscribed_at = now() - interval '3 days',
+            confirmation_requested_at = now() - interval '3 days'"
+    )
+    .execute(&app.connection_pool)
+    .await
+    .unwrap();
+    sqlx::query!("UPDATE subscriptions_tokens SET created_at = now() - interval '3 days'")
+        .execute(&app.connection_pool)
+        .await
+        .unwrap();
+    let body = "name=tom&email=tom%40gmail.com";
+    app.post_subscriptions(body.into()).await;
+    app.dispatch_all_pending_emails().await;
+
+    assert_eq!(app.purge_stale_subscriptions().await, 1);
+
+    let remaining = sqlx::query!("SELECT email FROM subscriptions")
+        .fetch_all(&app.connection_pool)
+        .await
+        .unwrap();
+    assert_eq!(remaining.len(), 1);
+    assert_eq!(remaining[0].email, "tom@gmail.com");
+    let response = reqwest::get(stale_link).await.unwrap();
+    assert_eq!(response.status().as_u16(), 404);
+}
+
+#[tokio::test]
+async fn confirmed_subscriptions_are_never_purged() {
+    let app = spawn_app().await;
+    let link = subscribe_and_get_confirmation_link(&app).await;
+    reqwest::get(link)
+        .await
+        .unwrap()
+        .error_for_status()
+        .unwrap();
+    sqlx::query!(
+        "UPDATE subscriptions SET
+            subscribed_at = now() - interval '30 days',
+            confirmation_requested_at = now() - interval '30 days'"
+    )
+    .execute(&app.connection_pool)
+    .await
+    .unwrap();
+
+    assert_eq!(app.purge_stale_subscriptions().await, 0);
+}
@@ -0,0 +1,437 @@
+use crate::helpers::{
+    assert_is_redirect_to_, create_confirmed_subscriber, spawn_app_logged_in, PostmarkAccepted,
+    TestApp,
+};
+use std::collections::HashMap;
+use wiremock::{
+    matchers::{any, method, path},
+    Mock,
+};
+
+async fn receive_preferences_link(app: &TestApp) -> reqwest::Url {
+    let _mock_guard = Mock::given(any())
+        .respond_with(PostmarkAccepted)
+        .expect(1)
+        .mount_as_scoped(&app.email_server)
+        .await;
+    app.publish_newsletter().await;
+    app.dispatch_all_pending_emails().await;
+
+    let email_request = app
+        .email_server
+        .received_requests()
+        .await
+        .unwrap()
+        .pop()
+        .unwrap();
+    let batch: serde_json::Value = serde_json::from_slice(&email_request.body).unwrap();
+    let links = app.get_links_matching(&batch[0], "/subscriptions/preferences?");
+    assert_eq!(links.html, links.plain_text);
+    links.html
+}
+
+fn preferences_params(link: &reqwest::Url, action: &str) -> HashMap<String, String> {
+    let mut params: HashMap<String, String> = link.query_pairs().into_owned().collect();
+    params.insert("action".into(), action.into());
+    params
+}
+
+fn preferences_location(link: &reqwest::Url) -> String {
+    format!("{}?{}", link.path(), link.query().unwrap())
+}
+
+async fn newsletter_recipients(app: &TestApp) -> usize {
+    let batches = |requests: Vec<wiremock::Request>| {
+        requests
+            .into_iter()
+            .filter(|r| r.url.path() == "/email/batch")
+            .map(|r| {
+                let batch: serde_json::Value = serde_json::from_slice(&r.body).unwrap();
+                batch.as_array().unwrap().len()
+            })
+            .collect::<Vec<_>>()
+    };
+    let n_before = batches(app.email_server.received_requests().await.unwrap()).len();
+    let _mock_guard = Mock::given(path("/email/batch"))
+        .respond_with(PostmarkAccepted)
+        .mount_as_scoped(&app.email_server)
+        .await;
+    app.publish_newsletter().await;
+    app.dispatch_all_pending_emails().await;
+    batches(app.email_server.received_requests().await.unwrap())[n_before..]
+        .iter()
+        .sum()
+}
+
+async fn newsletter_addresses(app: &TestApp) -> Vec<String> {
+    let n_before = app.email_server.received_requests().await.unwrap().len();
+    let _mock_guard = Mock::given(path("/email/batch"))
+        .respond_with(PostmarkAccepted)
+        .mount_as_scoped(&app.email_server)
+        .await;
+    app.publish_newsletter().await;
+    app.dispatch_all_pending_emails().await;
+    app.email_server.received_requests().await.unwrap()[n_before..]
+        .iter()
+        .filter(|r| r.url.path() == "/email/batch")
+        .flat_map(|r| {
+            let batch: Vec<serde_json::Value> = serde_json::from_slice(&r.body).unwrap();
+            batch
+                .into_iter()
+                .map(|message| message["To"].as_str().unwrap().to_owned())
+                .collect::<Vec<_>>()
+        })
+        .collect()
+}
+
+#[tokio::test]
+async fn the_preferences_link_shows_the_preference_center() {
+    let app = spawn_app_logged_in().await;
+    create_confirmed_subscriber(&app).await;
+    let link = receive_preferences_link(&app).await;
+    let saved = sqlx::query!("SELECT name, email FROM subscriptions")
+        .fetch_one(&app.connection_pool)
+        .await
+        .unwrap();
+
+    let response = app.get_preferences(&link).await;
+
+    assert_eq!(response.status().as_u16(), 200);
+    let html = response.text().await.unwrap();
+    assert!(html.contains(r#"action="/subscriptions/preferences""#));
+    assert!(html.contains(&saved.email));
+    assert!(html.contains("Newsletter"));
+    assert!(html.contains("Pause delivery for a month"));
+}
+
+#[tokio::test]
+async fn preferences_with_a_tampered_token_are_rejected_with_a_401() {
+    let app = spawn_app_logged_in().await;
+    create_confirmed_subscriber(&app).await;
+    let link = receive_preferences_link(&app).await;
+    let mut params = preferences_params(&link, "pause");
+    params.insert("subscriber_id".into(), uuid::Uuid::new_v4().to_string());
+
+    let response = app.post_preferences(&params).await;
+
+    assert_eq!(response.status().as_u16(), 401);
+    let saved = sqlx::query!("SELECT paused_until FROM subscriptions")
+        .fetch_one(&app.connection_pool)
+        .await
+        .unwrap();
+    assert!(saved.paused_until.is_none());
+}
+
+#[tokio::test]
+async fn an_unsubscribe_token_does_not_open_the_preference_center() {
+    let app = spawn_app_logged_in().await;
+    create_confirmed_subscriber(&app).await;
+    let link = receive_preferences_link(&app).await;
+    let email_request = app
+        .email_server
+        .received_requests()
+        .await
+        .unwrap()
+        .pop()
+        .unwrap();
+    let batch: serde_json::Value = serde_json::from_slice(&email_request.body).unwrap();
+    let unsubscribe_link = app
+        .get_links_matching(&batch[0], "/subscriptions/unsubscribe?")
+        .html;
+    let mut forged = link.clone();
+    forged.set_query(unsubscribe_link.query());
+
+    let response = app.get_preferences(&forged).await;
+
+    assert_eq!(response.status().as_u16(), 401);
+}
+
+#[tokio::test]
+async fn subscribers_can_change_their_name() {
+    let app = spawn_app_logged_in().await;
+    create_confirmed_subscriber(&app).await;
+    let link = receive_preferences_link(&app).await;
+    let email = sqlx::query!("SELECT email FROM subscriptions")
+        .fetch_one(&app.connection_pool)
+        .await
+        .unwrap()
+        .email;
+    let mut params = preferences_params(&link, "update_profile");
+    params.insert("name".into(), "Ursula K. Le Guin".into());
+    params.insert("email".into(), email);
+
+    let response = app.post_preferences(&params).await;
+    assert_is_redirect_to_(&response, &preferences_location(&link));
+
+    let html = app.get_preferences(&link).await.text().await.unwrap();
+    assert!(html.contains("Your preferences have been updated."));
+    let saved = sqlx::query!("SELECT name, status FROM subscriptions")
+        .fetch_one(&app.connection_pool)
+        .await
+        .unwrap();
+    assert_eq!(saved.name, "Ursula K. Le Guin");
+    assert_eq!(saved.status, "confirmed");
+}
+
+#[tokio::test]
+async fn an_invalid_name_is_rejected() {
+    let app = spawn_app_logged_in().await;
+    create_confirmed_subscriber(&app).await;
+    let link = receive_preferences_link(&app).await;
+    let mut params = preferences_params(&link, "update_profile");
+    params.insert("name".into(), "".into());
+    params.insert("email".into(), "ursula_le_guin@gmail.com".into());
+
+    let response = app.post_preferences(&params).await;
+    assert_is_redirect_to_(&response, &preferences_location(&link));
+
+    let saved = sqlx::query!("SELECT email FROM subscriptions")
+        .fetch_one(&app.connection_pool)
+        .await
+        .unwrap();
+    assert_ne!(saved.email, "ursula_le_guin@gmail.com");
+}
+
+#[tokio::test]
+async fn paused_subscribers_do_not_receive_newsletters_until_they_resume() {
+    let app = spawn_app_logged_in().await;
+    create_confirmed_subscriber(&app).await;
+    let link = receive_preferences_link(&app).await;
+
+    let response = app
+        .post_preferences(&preferences_params(&link, "pause"))
+        .await;
+    assert_is_redirect_to_(&response, &preferences_location(&link));
+    let html = app.get_preferences(&link).await.text().await.unwrap();
+    assert!(html.contains("Delivery is paused until"));
+    assert!(html.contains("Resume delivery"));
+    assert_eq!(newsletter_recipients(&app).await, 0);
+
+    app.post_preferences(&preferences_params(&link, "resume"))
+        .await;
+    assert_eq!(newsletter_recipients(&app).await, 1);
+}
+
+#[tokio::test]
+async fn leaving_a_list_stops_delivery_and_joining_requires_confirmation() {
+    let app = spawn_app_logged_in().await;
+    create_confirmed_subscriber(&app).await;
+    let link = receive_preferences_link(&app).await;
+    let list_id = sqlx::query!("SELECT list_id FROM list_memberships")
+        .fetch_one(&app.connection_pool)
+        .await
+        .unwrap()
+        .list_id;
+    let mut params = preferences_params(&link, "leave_list");
+    params.insert("list_id".into(), list_id.to_string());
+
+    let response = app.post_preferences(&params).await;
+    assert_is_redirect_to_(&response, &preferences_location(&link));
+    assert!(app
+        .get_preferences(&link)
+        .await
+        .text()
+        .await
+        .unwrap()
+        .contains("You have left Newsletter."));
+    assert_eq!(newsletter_recipients(&app).await, 0);
+
+    let _mock_guard = Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(PostmarkAccepted)
+        .expect(1)
+        .mount_as_scoped(&app.email_server)
+        .await;
+    params.insert("action".into(), "join_list".into());
+    let response = app.post_preferences(&params).await;
+    assert_is_redirect_to_(&response, &preferences_location(&link));
+    app.dispatch_all_pending_emails().await;
+    assert_eq!(newsletter_recipients(&app).await, 0);
+
+    let confirmation = app
+        .email_server
+        .received_requests()
+        .await
+        .unwrap()
+        .into_iter()
+        .rfind(|r| r.url.path() == "/email")
+        .unwrap();
+    let confirmation_links = app.get_confirmation_links(&confirmation);
+    reqwest::get(confirmation_links.html)
+        .await
+        .unwrap()
+        .error_for_status()
+        .unwrap();
+    assert_eq!(newsletter_recipients(&app).await, 1);
+}
+
+#[tokio::test]
+async fn changing_the_email_address_requires_a_fresh_confirmation() {
+    let app = spawn_app_logged_in().await;
+    create_confirmed_subscriber(&app).await;
+    let link = receive_preferences_link(&app).await;
+    let mut params = preferences_params(&link, "update_profile");
+    params.insert("name".into(), "le guin".into());
+    params.insert("email".into(), "ursula_le_guin@gmail.com".into());
+    let _mock_guard = Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(PostmarkAccepted)
+        .expect(1)
+        .mount_as_scoped(&app.email_server)
+        .await;
+
+    let response = app.post_preferences(&params).await;
+    assert_is_redirect_to_(&response, &preferences_location(&link));
+    app.dispatch_all_pending_emails().await;
+
+    let html = app.get_preferences(&link).await.text().await.unwrap();
+    assert!(html.contains("Check ursula_le_guin@gmail.com to confirm your new email address."));
+    let saved = sqlx::query!("SELECT email, pending_email, status FROM subscriptions")
+        .fetch_one(&app.connection_pool)
+        .await
+        .unwrap();
+    assert_ne!(saved.email, "ursula_le_guin@gmail.com");
+    assert_eq!(
+        saved.pending_email.as_deref(),
+        Some("ursula_le_guin@gmail.com")
+    );
+    assert_eq!(saved.status, "confirmed");
+
+    let confirmation = app
+        .email_server
+        .received_requests()
+        .await
+        .unwrap()
+        .into_iter()
+        .rfind(|r| r.url.path() == "/email")
+        .unwrap();
+    let body: serde_json::Value = serde_json::from_slice(&confirmation.body).unwrap();
+    assert_eq!(body["To"], "ursula_le_guin@gmail.com");
+    let confirmation_links = app.get_confirmation_links(&confirmation);
+    let response = reqwest::get(confirmation_links.html).await.unwrap();
+    assert_eq!(response.status().as_u16(), 200);
+    assert!(response
+        .text()
+        .await
+        .unwrap()
+        .contains("Your new email address is confirmed."));
+
+    let saved = sqlx::query!("SELECT email, pending_email, status FROM subscriptions")
+        .fetch_one(&app.connection_pool)
+        .await
+        .unwrap();
+    assert_eq!(saved.email, "ursula_le_guin@gmail.com");
+    assert_eq!(saved.pending_email, None);
+    assert_eq!(saved.status, "confirmed");
+    assert_eq!(
+        newsletter_addresses(&app).await,
+        vec!["ursula_le_guin@gmail.com".to_owned()]
+    );
+}
+
+#[tokio::test]
+async fn an_unconfirmed_email_change_keeps_the_old_address_subscribed() {
+    let app = spawn_app_logged_in().await;
+    create_confirmed_subscriber(&app).await;
+    let old_email = sqlx::query!("SELECT email FROM subscriptions")
+        .fetch_one(&app.connection_pool)
+        .await
+        .unwrap()
+        .email;
+    let link = receive_preferences_link(&app).await;
+    let mut params = preferences_params(&link, "update_profile");
+    params.insert("name".into(), "le guin".into());
+    params.insert("email".into(), "ursula_le_guin@gmail.com".into());
+    let _mock_guard = Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(PostmarkAccepted)
+        .expect(1)
+        .mount_as_scoped(&app.email_server)
+        .await;
+
+    let response = app.post_preferences(&params).await;
+    assert_is_redirect_to_(&response, &preferences_location(&link));
+    app.dispatch_all_pending_emails().await;
+    sqlx::query!(
+        "UPDATE subscriptions SET
+            subscribed_at = now() - interval '30 days',
+            confirmation_requested_at = now() - interval '30 days'"
+    )
+    .execute(&app.connection_pool)
+    .await
+    .unwrap();
+    sqlx::query!("UPDATE subscriptions_tokens SET created_at = now() - interval '30 days'")
+        .execute(&app.connection_pool)
+        .await
+        .unwrap();
+
+    assert_eq!(app.purge_stale_subscriptions().await, 0);
+    assert_eq!(newsletter_addresses(&app).await, vec![old_email]);
+}
+
+#[tokio::test]
+async fn an_email_address_already_in_use_is_rejected() {
+    let app = spawn_app_logged_in().await;
+    create_confirmed_subscriber(&app).await;
+    let link = receive_preferences_link(&app).await;
+    create_confirmed_subscriber(&app).await;
+    let other = sqlx::query!("SELECT email FROM subscriptions ORDER BY subscribed_at DESC LIMIT 1")
+        .fetch_one(&app.connection_pool)
+        .await
+        .unwrap()
+        .email;
+    let mut params = preferences_params(&link, "update_profile");
+    params.insert("name".into(), "Ursula K. Le Guin".into());
+    params.insert("email".into(), other);
+
+    let response = app.post_preferences(&params).await;
+    assert_is_redirect_to_(&response, &preferences_location(&link));
+
+    let html = app.get_preferences(&link).await.text().await.unwrap();
+    assert!(html.contains("That email address is already subscribed."));
+    let saved = sqlx::query!("SELECT name, status FROM subscriptions")
+        .fetch_all(&app.connection_pool)
+        .await
+        .unwrap();
+    assert!(saved.iter().all(|r| r.status == "confirmed"));
+    assert!(saved.iter().all(|r| r.name != "Ursula K. Le Guin"));
+}
+
+#[tokio::test]
+async fn changing_the_email_address_while_an_issue_is_in_flight_does_not_strand_it() {
+    let app = spawn_app_logged_in().await;
+    create_confirmed_subscriber(&app).await;
+    let link = receive_preferences_link(&app).await;
+    app.publish_newsletter().await;
+    let mut params = preferences_params(&link, "update_profile");
+    params.insert("name".into(), "le guin".into());
+    params.insert("email".into(), "ursula_le_guin@gmail.com".into());
+    let _mock_guard = Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(PostmarkAccepted)
+        .expect(1)
+        .mount_as_scoped(&app.email_server)
+        .await;
+    let _batch_guard = Mock::given(path("/email/batch"))
+        .and(method("POST"))
+        .respond_with(PostmarkAccepted)
+        .expect(1)
+        .mount_as_scoped(&app.email_server)
+        .await;
+
+    let response = app.post_preferences(&params).await;
+    assert_is_redirect_to_(&response, &preferences_location(&link));
+    app.dispatch_all_pending_emails().await;
+
+    let queued = sqlx::query!("SELECT COUNT(*) AS \"count!\" FROM issue_delievery_queue")
+        .fetch_one(&app.connection_pool)
+        .await
+        .unwrap();
+    assert_eq!(queued.count, 0);
+    let statuses = sqlx::query!("SELECT status FROM newsletter_issues")
+        .fetch_all(&app.connection_pool)
+        .await
+        .unwrap();
+    assert_eq!(statuses.len(), 2);
+    assert!(statuses.iter().all(|r| r.status == "sent"));
+}
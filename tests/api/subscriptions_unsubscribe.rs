@@ -0,0 +1,177 @@
+use crate::helpers::{
+    assert_is_redirect_to_, create_confirmed_subscriber, spawn_app_logged_in, PostmarkAccepted,
+    TestApp,
+};
+use std::collections::HashMap;
+use wiremock::{matchers::any, Mock};
+
+async fn receive_newsletter(app: &TestApp) -> serde_json::Value {
+    let _mock_guard = Mock::given(any())
+        .respond_with(PostmarkAccepted)
+        .expect(1)
+        .mount_as_scoped(&app.email_server)
+        .await;
+    app.publish_newsletter().await;
+    app.dispatch_all_pending_emails().await;
+
+    let email_request = app
+        .email_server
+        .received_requests()
+        .await
+        .unwrap()
+        .pop()
+        .unwrap();
+    let batch: serde_json::Value = serde_json::from_slice(&email_request.body).unwrap();
+    assert_eq!(batch.as_array().unwrap().len(), 1);
+    batch[0].clone()
+}
+
+async fn receive_unsubscribe_link(app: &TestApp) -> reqwest::Url {
+    let email = receive_newsletter(app).await;
+    let links = app.get_links_matching(&email, "/subscriptions/unsubscribe?");
+    assert_eq!(links.html, links.plain_text);
+    links.html
+}
+
+#[tokio::test]
+async fn newsletters_carry_an_unsubscribe_link() {
+    let app = spawn_app_logged_in().await;
+    create_confirmed_subscriber(&app).await;
+
+    let link = receive_unsubscribe_link(&app).await;
+
+    assert_eq!(link.path(), "/subscriptions/unsubscribe");
+}
+
+#[tokio::test]
+async fn the_unsubscribe_link_shows_a_form() {
+    let app = spawn_app_logged_in().await;
+    create_confirmed_subscriber(&app).await;
+    let link = receive_unsubscribe_link(&app).await;
+
+    let response = app.get_unsubscribe(link).await;
+
+    assert_eq!(response.status().as_u16(), 200);
+    let html = response.text().await.unwrap();
+    assert!(html.contains(r#"action="/subscriptions/unsubscribe""#));
+}
+
+#[tokio::test]
+async fn unsubscribe_with_a_tampered_token_is_rejected_with_a_401() {
+    let app = spawn_app_logged_in().await;
+    create_confirmed_subscriber(&app).await;
+    let link = receive_unsubscribe_link(&app).await;
+    let mut params: HashMap<String, String> = link.query_pairs().into_owned().collect();
+    params.insert("subscriber_id".into(), uuid::Uuid::new_v4().to_string());
+
+    let response = app.post_unsubscribe(&params).await;
+
+    assert_eq!(response.status().as_u16(), 401);
+    let saved = sqlx::query!("SELECT status FROM subscriptions")
+        .fetch_one(&app.connection_pool)
+        .await
+        .expect("Failed to fetch query");
+    assert_eq!(saved.status, "confirmed");
+}
+
+#[tokio::test]
+async fn submitting_the_unsubscribe_form_unsubscribes_a_subscriber() {
+    let app = spawn_app_logged_in().await;
+    create_confirmed_subscriber(&app).await;
+    let link = receive_unsubscribe_link(&app).await;
+    let params: HashMap<String, String> = link.query_pairs().into_owned().collect();
+
+    let response = app.post_unsubscribe(&params).await;
+    assert_is_redirect_to_(&response, "/");
+
+    let html = app.get_home_html().await;
+    assert!(html.contains("You have been unsubscribed from our newsletter."));
+    let saved = sqlx::query!("SELECT status FROM subscriptions")
+        .fetch_one(&app.connection_pool)
+        .await
+        .expect("Failed to fetch query");
+    assert_eq!(saved.status, "unsubscribed");
+}
+
+#[tokio::test]
+async fn newsletters_are_not_delivered_to_unsubscribed_subscribers() {
+    let app = spawn_app_logged_in().await;
+    create_confirmed_subscriber(&app).await;
+    let link = receive_unsubscribe_link(&app).await;
+    let params: HashMap<String, String> = link.query_pairs().into_owned().collect();
+    app.post_unsubscribe(&params).await;
+
+    Mock::given(any())
+        .respond_with(PostmarkAccepted)
+        .expect(0)
+        .mount(&app.email_server)
+        .await;
+
+    app.publish_newsletter().await;
+    app.dispatch_all_pending_emails().await;
+}
+
+async fn receive_one_click_unsubscribe_link(app: &TestApp) -> reqwest::Url {
+    let body = receive_newsletter(app).await;
+    let headers = body["Headers"].as_array().unwrap();
+    assert_eq!(headers[0]["Name"], "List-Unsubscribe");
+    let value = headers[0]["Value"].as_str().unwrap();
+    let mut link =
+        reqwest::Url::parse(value.trim_start_matches('<').trim_end_matches('>')).unwrap();
+    link.set_port(Some(app.port)).unwrap();
+    link
+}
+
+#[tokio::test]
+async fn newsletters_carry_list_unsubscribe_headers() {
+    let app = spawn_app_logged_in().await;
+    create_confirmed_subscriber(&app).await;
+
+    let body = receive_newsletter(&app).await;
+
+    let headers = body["Headers"].as_array().unwrap();
+    assert_eq!(headers.len(), 2);
+    assert_eq!(headers[0]["Name"], "List-Unsubscribe");
+    assert!(headers[0]["Value"]
+        .as_str()
+        .unwrap()
+        .contains("/subscriptions/unsubscribe/one-click?"));
+    assert_eq!(headers[1]["Name"], "List-Unsubscribe-Post");
+    assert_eq!(headers[1]["Value"], "List-Unsubscribe=One-Click");
+}
+
+#[tokio::test]
+async fn one_click_unsubscribe_unsubscribes_a_subscriber() {
+    let app = spawn_app_logged_in().await;
+    create_confirmed_subscriber(&app).await;
+    let link = receive_one_click_unsubscribe_link(&app).await;
+
+    let response = app
+        .post_one_click_unsubscribe(link, "List-Unsubscribe=One-Click")
+        .await;
+
+    assert_eq!(response.status().as_u16(), 200);
+    let saved = sqlx::query!("SELECT status FROM subscriptions")
+        .fetch_one(&app.connection_pool)
+        .await
+        .expect("Failed to fetch query");
+    assert_eq!(saved.status, "unsubscribed");
+}
+
+#[tokio::test]
+async fn one_click_unsubscribe_with_an_unexpected_body_is_rejected_with_a_400() {
+    let app = spawn_app_logged_in().await;
+    create_confirmed_subscriber(&app).await;
+    let link = receive_one_click_unsubscribe_link(&app).await;
+
+    let response = app
+        .post_one_click_unsubscribe(link, "List-Unsubscribe=Maybe")
+        .await;
+
+    assert_eq!(response.status().as_u16(), 400);
+    let saved = sqlx::query!("SELECT status FROM subscriptions")
+        .fetch_one(&app.connection_pool)
+        .await
+        .expect("Failed to fetch query");
+    assert_eq!(saved.status, "confirmed");
+}
@@ -0,0 +1,236 @@
+use crate::helpers::{
+    create_confirmed_subscriber, spawn_app, spawn_app_logged_in, PostmarkAccepted, TestApp,
+};
+use wiremock::{matchers::any, Mock};
+
+fn bounce_payload(email: &str, inactive: bool) -> serde_json::Value {
+    serde_json::json!({
+        "RecordType": "Bounce",
+        "ID": 4323372036854775807_i64,
+        "Type": if inactive { "HardBounce" } else { "SoftBounce" },
+        "TypeCode": if inactive { 1 } else { 4096 },
+        "MessageID": "883953f4-6105-42a2-a16a-77a8eac79483",
+        "Email": email,
+        "BouncedAt": "2023-11-05T12:00:00Z",
+        "Description": "The server was unable to deliver your message.",
+        "Inactive": inactive,
+        "CanActivate": true,
+    })
+}
+
+fn spam_complaint_payload(email: &str) -> serde_json::Value {
+    serde_json::json!({
+        "RecordType": "SpamComplaint",
+        "ID": 42,
+        "Type": "SpamComplaint",
+        "TypeCode": 512,
+        "MessageID": "00000000-0000-0000-0000-000000000000",
+        "Email": email,
+        "BouncedAt": "2023-11-05T12:00:00Z",
+        "Description": "The subscriber explicitly marked this message as spam.",
+        "Inactive": true,
+    })
+}
+
+async fn subscriber_email(app: &TestApp) -> String {
+    sqlx::query!("SELECT email FROM subscriptions")
+        .fetch_one(&app.connection_pool)
+        .await
+        .expect("Failed to fetch saved subscription")
+        .email
+}
+
+async fn subscriber_status(app: &TestApp) -> String {
+    sqlx::query!("SELECT status FROM subscriptions")
+        .fetch_one(&app.connection_pool)
+        .await
+        .expect("Failed to fetch saved subscription")
+        .status
+}
+
+#[tokio::test]
+async fn requests_without_credentials_are_rejected() {
+    let app = spawn_app().await;
+
+    let response = app
+        .app_client
+        .post(format!("{}/webhooks/email/bounce", &app.address))
+        .json(&bounce_payload("ursula@example.com", true))
+        .send()
+        .await
+        .expect("Failed to execute request");
+
+    assert_eq!(response.status().as_u16(), 401);
+    assert_eq!(
+        response.headers()["WWW-Authenticate"],
+        r#"Basic realm="webhooks""#
+    );
+}
+
+#[tokio::test]
+async fn requests_with_the_wrong_password_are_rejected() {
+    let app = spawn_app().await;
+
+    let response = app
+        .app_client
+        .post(format!("{}/webhooks/email/bounce", &app.address))
+        .basic_auth(&app.webhook_credentials.username, Some("not-the-password"))
+        .json(&bounce_payload("ursula@example.com", true))
+        .send()
+        .await
+        .expect("Failed to execute request");
+
+    assert_eq!(response.status().as_u16(), 401);
+}
+
+#[tokio::test]
+async fn a_hard_bounce_suppresses_the_subscriber() {
+    let app = spawn_app().await;
+    create_confirmed_subscriber(&app).await;
+    let email = subscriber_email(&app).await;
+
+    let response = app.post_bounce_webhook(&bounce_payload(&email, true)).await;
+
+    assert_eq!(response.status().as_u16(), 200);
+    assert_eq!(subscriber_status(&app).await, "bounced");
+    let suppression =
+        sqlx::query!("SELECT email, reason, provider_message_id FROM email_suppressions")
+            .fetch_one(&app.connection_pool)
+            .await
+            .expect("Failed to fetch suppression");
+    assert_eq!(suppression.email, email);
+    assert_eq!(suppression.reason, "bounced");
+    assert_eq!(
+        suppression.provider_message_id.as_deref(),
+        Some("883953f4-6105-42a2-a16a-77a8eac79483")
+    );
+}
+
+#[tokio::test]
+async fn suppression_ignores_the_case_of_the_address() {
+    let app = spawn_app().await;
+    create_confirmed_subscriber(&app).await;
+    let email = subscriber_email(&app).await;
+
+    let response = app
+        .post_bounce_webhook(&spam_complaint_payload(&email.to_uppercase()))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 200);
+    assert_eq!(subscriber_status(&app).await, "complained");
+    let suppression = sqlx::query!("SELECT email FROM email_suppressions")
+        .fetch_one(&app.connection_pool)
+        .await
+        .expect("Failed to fetch suppression");
+    assert_eq!(suppression.email, email.to_lowercase());
+}
+
+#[tokio::test]
+async fn a_spam_complaint_suppresses_the_subscriber() {
+    let app = spawn_app().await;
+    create_confirmed_subscriber(&app).await;
+    let email = subscriber_email(&app).await;
+
+    let response = app
+        .post_bounce_webhook(&spam_complaint_payload(&email))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 200);
+    assert_eq!(subscriber_status(&app).await, "complained");
+    let suppression = sqlx::query!("SELECT reason FROM email_suppressions")
+        .fetch_one(&app.connection_pool)
+        .await
+        .expect("Failed to fetch suppression");
+    assert_eq!(suppression.reason, "complained");
+}
+
+#[tokio::test]
+async fn a_soft_bounce_does_not_suppress_the_subscriber() {
+    let app = spawn_app().await;
+    create_confirmed_subscriber(&app).await;
+    let email = subscriber_email(&app).await;
+
+    let response = app
+        .post_bounce_webhook(&bounce_payload(&email, false))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 200);
+    assert_eq!(subscriber_status(&app).await, "confirmed");
+    let suppressions = sqlx::query!("SELECT COUNT(*) AS \"count!\" FROM email_suppressions")
+        .fetch_one(&app.connection_pool)
+        .await
+        .expect("Failed to count suppressions");
+    assert_eq!(suppressions.count, 0);
+}
+
+#[tokio::test]
+async fn unknown_record_types_are_rejected() {
+    let app = spawn_app().await;
+
+    let response = app
+        .post_bounce_webhook(&serde_json::json!({
+            "RecordType": "Open",
+            "Email": "ursula@example.com",
+        }))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 400);
+}
+
+#[tokio::test]
+async fn newsletters_are_not_enqueued_for_suppressed_addresses() {
+    let app = spawn_app_logged_in().await;
+    create_confirmed_subscriber(&app).await;
+    let email = subscriber_email(&app).await;
+    sqlx::query!(
+        "INSERT INTO email_suppressions (email, reason, suppressed_at) VALUES ($1, 'bounced', now())",
+        email
+    )
+    .execute(&app.connection_pool)
+    .await
+    .expect("Failed to suppress the address");
+
+    Mock::given(any())
+        .respond_with(PostmarkAccepted)
+        .expect(0)
+        .mount(&app.email_server)
+        .await;
+
+    app.publish_newsletter().await;
+    app.dispatch_all_pending_emails().await;
+
+    let queued = sqlx::query!("SELECT COUNT(*) AS \"count!\" FROM issue_delievery_queue")
+        .fetch_one(&app.connection_pool)
+        .await
+        .expect("Failed to fetch query");
+    assert_eq!(queued.count, 0);
+}
+
+#[tokio::test]
+async fn confirmation_emails_are_not_sent_to_suppressed_addresses() {
+    let app = spawn_app().await;
+    sqlx::query!(
+        "INSERT INTO email_suppressions (email, reason, suppressed_at) \
+        VALUES ('ursula_le_guin@gmail.com', 'complained', now())"
+    )
+    .execute(&app.connection_pool)
+    .await
+    .expect("Failed to suppress the address");
+
+    Mock::given(any())
+        .respond_with(PostmarkAccepted)
+        .expect(0)
+        .mount(&app.email_server)
+        .await;
+
+    app.post_subscriptions("name=le%20guin&email=ursula_le_guin%40gmail.com".into())
+        .await;
+    app.dispatch_all_pending_emails().await;
+
+    let queued =
+        sqlx::query!("SELECT COUNT(*) AS \"count!\" FROM subscription_confirmation_delivery_queue")
+            .fetch_one(&app.connection_pool)
+            .await
+            .expect("Failed to fetch query");
+    assert_eq!(queued.count, 0);
+}